@@ -0,0 +1,28 @@
+-- Notify listeners (the live price service) when the blacklist changes
+CREATE OR REPLACE FUNCTION notify_blacklist_update()
+RETURNS trigger AS $$
+BEGIN
+  PERFORM pg_notify(
+    'blacklist_updates',
+    json_build_object(
+      'table', TG_TABLE_NAME,
+      'op', TG_OP,
+      'ticker', COALESCE(NEW.ticker, OLD.ticker)
+    )::text
+  );
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Create triggers on ticker_blacklist and blacklist_patterns
+DROP TRIGGER IF EXISTS ticker_blacklist_notify ON ticker_blacklist;
+CREATE TRIGGER ticker_blacklist_notify
+AFTER INSERT OR UPDATE OR DELETE ON ticker_blacklist
+FOR EACH ROW
+EXECUTE FUNCTION notify_blacklist_update();
+
+DROP TRIGGER IF EXISTS blacklist_patterns_notify ON blacklist_patterns;
+CREATE TRIGGER blacklist_patterns_notify
+AFTER INSERT OR UPDATE OR DELETE ON blacklist_patterns
+FOR EACH ROW
+EXECUTE FUNCTION notify_blacklist_update();
@@ -0,0 +1,357 @@
+// Price level alerts from trader calls ("over 2.50", "stop 1.80"). Evaluated on
+// every live trade; a trigger records the exact print that crossed the level and
+// is pushed on the stream as an `alert` event.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::DateTime;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{current_time_ns, norm_symbol, resolve, start_live_subscription, stream::StreamEvent, tape::TapeTrade, to_dt, to_ns, AppState};
+
+const CREATE_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS level_alerts (
+  id BIGINT PRIMARY KEY,
+  symbol VARCHAR(16) NOT NULL,
+  level DOUBLE PRECISION NOT NULL,
+  direction VARCHAR(8) NOT NULL,
+  source_message_id VARCHAR(50),
+  author VARCHAR(255),
+  note TEXT,
+  reference_price DOUBLE PRECISION,
+  status VARCHAR(16) NOT NULL DEFAULT 'active',
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  triggered_at TIMESTAMP WITH TIME ZONE,
+  trigger_trade JSONB
+);
+CREATE INDEX IF NOT EXISTS idx_level_alerts_status ON level_alerts(status);
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Above, // "over", "break" from below
+    Below, // "stop", "lose", "under"
+    Cross, // either way
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    Active,
+    Triggered,
+    Cancelled,
+}
+
+impl AlertStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AlertStatus::Active => "active",
+            AlertStatus::Triggered => "triggered",
+            AlertStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "active" => Some(AlertStatus::Active),
+            "triggered" => Some(AlertStatus::Triggered),
+            "cancelled" => Some(AlertStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LevelAlert {
+    pub id: u64,
+    pub symbol: String,
+    pub level: f64,
+    pub direction: Direction,
+    pub source_message_id: Option<String>,
+    pub author: Option<String>,
+    pub note: Option<String>,
+    pub created_at_ns: u64,
+    pub reference_price: Option<f64>, // last price when the alert was set
+    pub status: AlertStatus,
+    pub triggered_at_ns: Option<u64>,
+    pub trigger: Option<TapeTrade>,
+    #[serde(skip)]
+    last_px: Option<f64>,
+}
+
+impl LevelAlert {
+    // A level fires when a print reaches it from the other side; an alert set while
+    // price is already through the level waits for it to come back and cross again.
+    // With no price known when it was set, the first print only arms it.
+    fn crossed_by(&self, px: f64) -> bool {
+        let prev = self.last_px.or(self.reference_price);
+        match self.direction {
+            Direction::Above => px >= self.level && prev.is_some_and(|p| p < self.level),
+            Direction::Below => px <= self.level && prev.is_some_and(|p| p > self.level),
+            Direction::Cross => prev.is_some_and(|p| (p < self.level && px >= self.level) || (p > self.level && px <= self.level)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AlertBook {
+    next_id: u64,
+    by_symbol: HashMap<String, Vec<LevelAlert>>,
+}
+
+impl AlertBook {
+    pub fn watches(&self, symbol: &str) -> bool {
+        self.by_symbol.get(symbol).is_some_and(|list| list.iter().any(|a| a.status == AlertStatus::Active))
+    }
+
+    // Evaluate active alerts for the trade's symbol; returns the ones that fired
+    pub fn on_trade(&mut self, trade: &TapeTrade) -> Vec<LevelAlert> {
+        let Some(list) = self.by_symbol.get_mut(&trade.symbol) else { return Vec::new() };
+        let mut fired = Vec::new();
+        for alert in list.iter_mut().filter(|a| a.status == AlertStatus::Active) {
+            if alert.crossed_by(trade.price) {
+                alert.status = AlertStatus::Triggered;
+                alert.triggered_at_ns = Some(current_time_ns());
+                alert.trigger = Some(trade.clone());
+                fired.push(alert.clone());
+            }
+            alert.last_px = Some(trade.price);
+        }
+        fired
+    }
+
+    fn insert(&mut self, alert: LevelAlert) {
+        self.next_id = self.next_id.max(alert.id);
+        self.by_symbol.entry(alert.symbol.clone()).or_default().push(alert);
+    }
+
+    fn cancel(&mut self, id: u64) -> Option<LevelAlert> {
+        let alert = self.by_symbol.values_mut().flatten().find(|a| a.id == id)?;
+        if alert.status == AlertStatus::Active {
+            alert.status = AlertStatus::Cancelled;
+        }
+        Some(alert.clone())
+    }
+
+    fn list(&self, symbol: Option<&str>, status: Option<AlertStatus>) -> Vec<LevelAlert> {
+        let mut out: Vec<LevelAlert> = self.by_symbol.iter()
+            .filter(|(sym, _)| symbol.is_none_or(|s| s == sym.as_str()))
+            .flat_map(|(_, list)| list.iter())
+            .filter(|a| status.is_none_or(|s| a.status == s))
+            .cloned()
+            .collect();
+        out.sort_by_key(|a| a.id);
+        out
+    }
+}
+
+// Create the table and load every alert; active ones get their symbols subscribed
+// unless the symbol has since been blacklisted
+pub async fn load(state: &AppState) {
+    let Some(db) = &state.db else { return };
+    if let Err(e) = db.batch_execute(CREATE_TABLE).await {
+        error!("Failed to create level_alerts: {}", e);
+        return;
+    }
+    let rows = match db.query(
+        "SELECT id, symbol, level, direction, source_message_id, author, note, reference_price,
+                status, created_at, triggered_at, trigger_trade
+         FROM level_alerts ORDER BY id",
+        &[],
+    ).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to load level alerts: {}", e);
+            return;
+        }
+    };
+
+    let mut book = state.alerts.write().await;
+    for r in rows {
+        let direction: String = r.get(3);
+        let status: String = r.get(8);
+        let trigger: Option<serde_json::Value> = r.get(11);
+        let created_at: DateTime<chrono::Utc> = r.get(9);
+        let triggered_at: Option<DateTime<chrono::Utc>> = r.get(10);
+        book.insert(LevelAlert {
+            id: r.get::<_, i64>(0) as u64,
+            symbol: r.get(1),
+            level: r.get(2),
+            direction: serde_json::from_value(serde_json::Value::String(direction)).unwrap_or(Direction::Cross),
+            source_message_id: r.get(4),
+            author: r.get(5),
+            note: r.get(6),
+            created_at_ns: to_ns(created_at),
+            reference_price: r.get(7),
+            status: AlertStatus::parse(&status).unwrap_or(AlertStatus::Cancelled),
+            triggered_at_ns: triggered_at.and_then(|t| t.timestamp_nanos_opt()).map(|t| t as u64),
+            trigger: trigger.and_then(|t| serde_json::from_value(t).ok()),
+            last_px: None,
+        });
+    }
+    let (symbols, blocked) = state.blacklist.read().await
+        .partition(book.by_symbol.keys().filter(|s| book.watches(s)).cloned().collect(), None);
+    drop(book);
+    if !blocked.is_empty() {
+        info!("Not watching blacklisted alert symbols: {:?}", blocked.iter().map(|b| &b.symbol).collect::<Vec<_>>());
+    }
+    info!("Loaded level alerts; watching {} symbols", symbols.len());
+    if !symbols.is_empty() {
+        let _ = start_live_subscription(symbols, state.clone()).await;
+    }
+}
+
+async fn persist(state: &AppState, alert: &LevelAlert) {
+    let Some(db) = &state.db else { return };
+    let direction = serde_json::to_value(alert.direction).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+    let trigger = alert.trigger.as_ref().and_then(|t| serde_json::to_value(t).ok());
+    let result = db.execute(
+        "INSERT INTO level_alerts (id, symbol, level, direction, source_message_id, author, note, reference_price,
+                                   status, created_at, triggered_at, trigger_trade)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+         ON CONFLICT (id) DO UPDATE SET status = EXCLUDED.status,
+                                        triggered_at = EXCLUDED.triggered_at,
+                                        trigger_trade = EXCLUDED.trigger_trade",
+        &[
+            &(alert.id as i64),
+            &alert.symbol,
+            &alert.level,
+            &direction,
+            &alert.source_message_id,
+            &alert.author,
+            &alert.note,
+            &alert.reference_price,
+            &alert.status.as_str(),
+            &to_dt(alert.created_at_ns),
+            &alert.triggered_at_ns.map(to_dt),
+            &trigger,
+        ],
+    ).await;
+    if let Err(e) = result {
+        error!("Failed to persist alert {}: {}", alert.id, e);
+    }
+}
+
+// Called for every live trade
+pub async fn on_trade(state: &AppState, trade: &TapeTrade) {
+    let fired = {
+        let mut book = state.alerts.write().await;
+        if !book.watches(&trade.symbol) {
+            return;
+        }
+        book.on_trade(trade)
+    };
+    for alert in fired {
+        info!("Alert {} fired: {} {:?} {} at ${:.4}", alert.id, alert.symbol, alert.direction, alert.level, trade.price);
+        state.events.publish(StreamEvent::Alert(alert.clone()));
+        let persist_state = state.clone();
+        tokio::spawn(async move { persist(&persist_state, &alert).await });
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAlertBody {
+    symbol: String,
+    level: f64,
+    direction: Direction,
+    #[serde(default)]
+    source_message_id: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    note: Option<String>,
+    // Message text the call came from; lets context-dependent blacklist entries through
+    #[serde(default)]
+    context: Option<String>,
+}
+
+// POST /api/alerts { symbol, level, direction: above|below|cross, source_message_id?, author?, note?, context? }
+pub async fn create_alert(State(state): State<AppState>, Json(body): Json<CreateAlertBody>) -> impl IntoResponse {
+    if !(body.level.is_finite() && body.level > 0.0) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "level must be a positive price"})));
+    }
+    let resolution = resolve::resolve_symbols(&state, std::slice::from_ref(&body.symbol)).await;
+    let Some(symbol) = resolution.accepted.into_iter().next() else {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({
+            "error": "invalid symbol",
+            "rejected": resolution.rejected
+        })));
+    };
+    if let Some(blocked) = state.blacklist.read().await.check(&symbol, body.context.as_deref(), None) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({
+            "error": "blacklisted symbol",
+            "blacklisted": [blocked]
+        })));
+    }
+
+    let reference_price = state.prices.read().await.get(&symbol).and_then(|p| p.price);
+    let alert = {
+        let mut book = state.alerts.write().await;
+        book.next_id += 1;
+        let alert = LevelAlert {
+            id: book.next_id,
+            symbol: symbol.clone(),
+            level: body.level,
+            direction: body.direction,
+            source_message_id: body.source_message_id,
+            author: body.author,
+            note: body.note,
+            created_at_ns: current_time_ns(),
+            reference_price,
+            status: AlertStatus::Active,
+            triggered_at_ns: None,
+            trigger: None,
+            last_px: None,
+        };
+        book.insert(alert.clone());
+        alert
+    };
+    persist(&state, &alert).await;
+
+    if !state.subscribed_symbols.read().await.contains(&symbol) {
+        if let Err(e) = start_live_subscription(vec![symbol.clone()], state.clone()).await {
+            error!("Failed to subscribe {} for alert {}: {}", symbol, alert.id, e);
+        }
+    }
+    info!("Alert {} set: {} {:?} {}", alert.id, symbol, alert.direction, alert.level);
+    (StatusCode::CREATED, Json(serde_json::json!(alert)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertsQuery {
+    #[serde(default)]
+    symbol: Option<String>,
+    #[serde(default)]
+    status: Option<String>,
+}
+
+// GET /api/alerts[?symbol=AAPL][&status=active|triggered|cancelled]
+pub async fn list_alerts(Query(q): Query<AlertsQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let symbol = q.symbol.map(|s| norm_symbol(&s));
+    let status = match q.status.as_deref() {
+        None => None,
+        Some(s) => match AlertStatus::parse(s) {
+            Some(status) => Some(status),
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "unknown status"}))),
+        },
+    };
+    let alerts = state.alerts.read().await.list(symbol.as_deref(), status);
+    (StatusCode::OK, Json(serde_json::json!({ "alerts": alerts })))
+}
+
+// DELETE /api/alerts/:id - cancel an active alert
+pub async fn cancel_alert(Path(id): Path<u64>, State(state): State<AppState>) -> impl IntoResponse {
+    let Some(alert) = state.alerts.write().await.cancel(id) else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such alert"})));
+    };
+    persist(&state, &alert).await;
+    (StatusCode::OK, Json(serde_json::json!(alert)))
+}
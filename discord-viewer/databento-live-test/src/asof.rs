@@ -0,0 +1,308 @@
+// Time travel: what each symbol was doing at a past instant. Returns the shape of
+// /api/live/prices, with the rule fields alongside (bid/ask, HOD, day volume, VWAP,
+// change %, halt state). Rebuilt from the raw feed recordings when they cover the
+// symbol at that instant, otherwise from the historical API: minute bars up to
+// the instant's minute, the trades inside it, the last one-second BBO and the
+// day's status messages. The day is the session that had started by the instant.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use databento::dbn::{
+    decode::{DbnDecoder, DecodeRecordRef},
+    BboMsg, Record, StatusAction, StatusMsg, SymbolMappingMsg, TradeMsg,
+};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    bars::{Bar, BAR_NS},
+    calendar,
+    condition::{Field, Fields},
+    hist, norm_symbol, options,
+    outcomes::parse_ts,
+    recorder::FileEntry,
+    rules::{self, SymbolFields},
+    sessions, to_dt, to_ns,
+    AppState, LastPrice,
+};
+
+const MAX_SYMBOLS: usize = 25;
+
+// Recorded records arrive in receive order; stop reading this far past the instant
+const READ_PAST_NS: u64 = 60 * 1_000_000_000;
+
+#[derive(Debug, Deserialize)]
+pub struct AsOfQuery {
+    symbols: String,
+    at: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AsOfPrice {
+    #[serde(flatten)]
+    pub price: LastPrice,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    pub hod: Option<f64>,
+    pub volume: Option<f64>,
+    pub vwap: Option<f64>,
+    pub prev_close: Option<f64>,
+    pub change_pct: Option<f64>,
+    pub halted: bool,
+    pub source: &'static str, // recording | historical
+}
+
+fn snapshot(fields: &SymbolFields, prev_close: Option<f64>, source: &'static str) -> Option<AsOfPrice> {
+    let (px, ts) = fields.last_trade()?;
+    Some(AsOfPrice {
+        price: LastPrice { price: Some(px), ts_event_ns: Some(ts), session: Some(calendar::session_at_ns(ts)), live: false },
+        bid: fields.get(Field::Bid),
+        ask: fields.get(Field::Ask),
+        hod: fields.get(Field::Hod),
+        volume: fields.get(Field::Volume),
+        vwap: fields.get(Field::Vwap),
+        prev_close,
+        change_pct: fields.get(Field::ChangePct),
+        halted: fields.get(Field::Halted).is_some_and(|h| h > 0.0),
+        source,
+    })
+}
+
+// Recordings of `date` that cover the symbol at `at_ns`: the file was still open, or
+// closed after the instant, and had seen the symbol by then
+fn recorded_since(files: &[FileEntry], date: NaiveDate, symbol: &str, at_ns: u64) -> Option<u64> {
+    let date = date.to_string();
+    let day: Vec<&FileEntry> = files.iter().filter(|f| f.date == date).collect();
+    let covered = day.iter().any(|f| {
+        f.symbols.get(symbol).is_some_and(|s| s.first_ts_ns <= at_ns) && f.closed_at_ns.is_none_or(|t| t >= at_ns)
+    });
+    covered.then(|| day.iter().filter_map(|f| f.symbols.get(symbol)).map(|s| s.first_ts_ns).min())?
+}
+
+// Replay the day's recordings into `fields` up to `at_ns`
+fn replay_recordings(dir: PathBuf, paths: Vec<String>, mut fields: HashMap<String, SymbolFields>, at_ns: u64) -> HashMap<String, SymbolFields> {
+    let until_ns = at_ns + READ_PAST_NS;
+    for path in paths {
+        let mut decoder = match DbnDecoder::from_zstd_file(dir.join(&path)) {
+            Ok(decoder) => decoder,
+            Err(e) => {
+                warn!("Cannot read recording {}: {}", path, e);
+                continue;
+            }
+        };
+        let mut mapping: HashMap<u32, String> = HashMap::new();
+        // A file being written ends where it was last flushed
+        while let Ok(Some(rec)) = decoder.decode_record_ref() {
+            let ts_ns = rec.header().ts_event;
+            if let Some(msg) = rec.get::<SymbolMappingMsg>() {
+                mapping.insert(msg.hd.instrument_id, msg.stype_out_symbol().unwrap_or_default().to_string());
+                continue;
+            }
+            if ts_ns > until_ns {
+                break;
+            }
+            let Some(symbol_fields) = mapping.get(&rec.header().instrument_id).and_then(|s| fields.get_mut(s)) else { continue };
+            if let Some(trade) = rec.get::<TradeMsg>() {
+                if ts_ns <= at_ns {
+                    symbol_fields.on_trade(trade.price as f64 / 1_000_000_000.0, trade.size, ts_ns);
+                }
+            } else if let Some(quote) = rec.get::<BboMsg>() {
+                if quote.ts_recv <= at_ns {
+                    let level = &quote.levels[0];
+                    symbol_fields.on_quote(options::px(level.bid_px), options::px(level.ask_px), quote.ts_recv);
+                }
+            } else if let Some(status) = rec.get::<StatusMsg>() {
+                if let Some(halted) = status.action().ok().and_then(rules::halts).filter(|_| ts_ns <= at_ns) {
+                    symbol_fields.on_status(halted, ts_ns);
+                }
+            }
+        }
+    }
+    fields
+}
+
+async fn from_history(dataset: &str, symbol: &str, date: NaiveDate, at: DateTime<Utc>) -> Result<SymbolFields, hist::HistError> {
+    let start = calendar::pre_open(date);
+    let end = at.min(calendar::post_close(date)).max(start);
+    let end_ns = to_ns(end);
+    let minute = to_dt(end_ns - end_ns % BAR_NS).max(start);
+    let (bars, trades, status, quote) = tokio::join!(
+        hist::minute_bars(dataset, symbol, start, minute),
+        hist::trades(dataset, symbol, minute, end + chrono::Duration::nanoseconds(1), None),
+        hist::status(dataset, symbol, start, end + chrono::Duration::nanoseconds(1)),
+        hist::quote_at(dataset, symbol, end + chrono::Duration::nanoseconds(1)),
+    );
+    let mut fields = SymbolFields::default();
+    fields.add_bars(&bars?);
+    let mut trades = trades?;
+    trades.sort_by_key(|t| t.ts_event);
+    for t in trades {
+        fields.on_trade(t.price, t.size, t.ts_event);
+    }
+    for (action, ts_ns) in status? {
+        if let Some(halted) = StatusAction::try_from(action).ok().and_then(rules::halts) {
+            fields.on_status(halted, ts_ns);
+        }
+    }
+    if let Some((bid, ask, ts_ns)) = quote? {
+        fields.on_quote(bid, ask, ts_ns.max(to_ns(start)));
+    }
+    Ok(fields)
+}
+
+async fn prev_close(dataset: &str, symbol: &str, date: NaiveDate) -> Option<f64> {
+    match hist::previous_close(dataset, symbol, date).await {
+        Ok(close) => close,
+        Err(e) => {
+            warn!("Previous close lookup failed for {}: {}", symbol, e);
+            None
+        }
+    }
+}
+
+// GET /api/live/prices/at?symbols=AAPL,NVDA&at=2026-10-16T13:41:12Z
+pub async fn get_prices_at(Query(q): Query<AsOfQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let bad_request = |error: String| (http::StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": error})));
+    let at = match parse_ts(q.at.as_deref()) {
+        Ok(Some(at)) if at <= Utc::now() => at,
+        Ok(Some(_)) => return bad_request("at is in the future".to_string()),
+        Ok(None) => return bad_request("at is required (RFC3339)".to_string()),
+        Err(e) => return bad_request(e),
+    };
+    let mut symbols: Vec<String> = q.symbols.split(',').map(norm_symbol).filter(|s| !s.is_empty()).collect();
+    symbols.sort();
+    symbols.dedup();
+    if symbols.is_empty() || symbols.len() > MAX_SYMBOLS {
+        return bad_request(format!("symbols must list 1 to {} symbols", MAX_SYMBOLS));
+    }
+    let at_ns = to_ns(at);
+    let date = calendar::last_session_date(at);
+    let datasets: HashMap<String, String> = join_all(symbols.iter().map(|s| {
+        let state = &state;
+        async move { (s.clone(), sessions::dataset_for(state, s).await) }
+    })).await.into_iter().collect();
+    let datasets = &datasets;
+
+    // Symbols the recordings cover, with when they were first recorded that day
+    let (recorded, paths, dir) = match &state.recorder {
+        Some(recorder) => {
+            let files = recorder.index.read().await;
+            let recorded: HashMap<String, u64> = symbols.iter()
+                .filter_map(|s| recorded_since(&files, date, s, at_ns).map(|first| (s.clone(), first)))
+                .collect();
+            let mut day: Vec<&FileEntry> = files.iter()
+                .filter(|f| f.date == date.to_string() && f.opened_at_ns <= at_ns)
+                .filter(|f| recorded.keys().any(|s| f.symbols.contains_key(s)))
+                .collect();
+            day.sort_by_key(|f| f.opened_at_ns);
+            (recorded, day.into_iter().map(|f| f.path.clone()).collect(), recorder.dir.clone())
+        }
+        None => (HashMap::new(), Vec::new(), PathBuf::new()),
+    };
+
+    // A recording that started after the pre-market is seeded with the bars before it
+    let seeded = join_all(recorded.iter().map(|(symbol, first_ns)| async move {
+        let mut fields = SymbolFields::default();
+        let start = calendar::pre_open(date);
+        let first_minute = to_dt(first_ns - first_ns % BAR_NS);
+        if first_minute > start {
+            match hist::minute_bars(&datasets[symbol], symbol, start, first_minute).await {
+                Ok(bars) => fields.add_bars(&bars.into_iter().filter(|b: &Bar| b.start_ns < *first_ns).collect::<Vec<_>>()),
+                Err(e) => warn!("No bars before the recording of {}: {}", symbol, e),
+            }
+        }
+        (symbol.clone(), fields)
+    })).await.into_iter().collect();
+    let mut fields: HashMap<String, (SymbolFields, &'static str)> = if recorded.is_empty() {
+        HashMap::new()
+    } else {
+        match tokio::task::spawn_blocking(move || replay_recordings(dir, paths, seeded, at_ns)).await {
+            Ok(replayed) => replayed.into_iter().map(|(s, f)| (s, (f, "recording"))).collect(),
+            Err(e) => {
+                warn!("Replaying recordings failed: {}", e);
+                HashMap::new()
+            }
+        }
+    };
+
+    let missing: HashSet<&String> = symbols.iter().filter(|s| !fields.contains_key(*s)).collect();
+    let rebuilt = join_all(missing.into_iter().map(|symbol| async move {
+        (symbol.clone(), from_history(&datasets[symbol], symbol, date, at).await)
+    })).await;
+    for (symbol, result) in rebuilt {
+        match result {
+            Ok(f) => {
+                fields.insert(symbol, (f, "historical"));
+            }
+            Err(e) => warn!("Cannot rebuild {} at {}: {}", symbol, at.to_rfc3339(), e),
+        }
+    }
+
+    let closes = join_all(fields.keys().map(|s| async move { (s.clone(), prev_close(&datasets[s], s, date).await) })).await;
+    let mut result: HashMap<String, AsOfPrice> = HashMap::new();
+    for (symbol, close) in closes {
+        let Some((symbol_fields, source)) = fields.get_mut(&symbol) else { continue };
+        if let Some(close) = close {
+            symbol_fields.set_prev_close(close);
+        }
+        if let Some(price) = snapshot(symbol_fields, close, source) {
+            result.insert(symbol, price);
+        }
+    }
+    (http::StatusCode::OK, Json(serde_json::json!(result)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::recorder::SymbolSpan;
+
+    use super::*;
+
+    fn file(date: &str, closed_at_ns: Option<u64>, spans: &[(&str, u64, u64)]) -> FileEntry {
+        FileEntry {
+            date: date.to_string(),
+            closed_at_ns,
+            symbols: spans.iter()
+                .map(|(symbol, first_ts_ns, last_ts_ns)| (symbol.to_string(), SymbolSpan { records: 1, first_ts_ns: *first_ts_ns, last_ts_ns: *last_ts_ns }))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn recorded_since_needs_the_symbol_seen_and_the_file_open() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let files = vec![file("2026-10-15", Some(500), &[("AAPL", 100, 450), ("MSFT", 300, 400)])];
+        assert_eq!(recorded_since(&files, date, "AAPL", 300), Some(100));
+        assert_eq!(recorded_since(&files, date, "AAPL", 500), Some(100));
+        // Before the symbol's first record, or after the connection dropped
+        assert_eq!(recorded_since(&files, date, "MSFT", 200), None);
+        assert_eq!(recorded_since(&files, date, "AAPL", 600), None);
+        assert_eq!(recorded_since(&files, date, "TSLA", 300), None);
+    }
+
+    #[test]
+    fn recorded_since_spans_reconnects_within_the_day() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let files = vec![
+            file("2026-10-14", None, &[("AAPL", 10, 20)]),
+            file("2026-10-15", Some(500), &[("AAPL", 100, 450)]),
+            file("2026-10-15", None, &[("AAPL", 700, 900)]),
+        ];
+        // The open file covers the instant; replay starts from the day's first record
+        assert_eq!(recorded_since(&files, date, "AAPL", 800), Some(100));
+        // Between the two files nothing was recording
+        assert_eq!(recorded_since(&files, date, "AAPL", 600), None);
+        // Other days' files don't count
+        assert_eq!(recorded_since(&files, date.pred_opt().unwrap(), "AAPL", 800), Some(10));
+        assert_eq!(recorded_since(&files, date.succ_opt().unwrap(), "AAPL", 800), None);
+    }
+}
@@ -0,0 +1,145 @@
+// Historical backfill for symbols the live feed hasn't covered yet.
+
+use chrono::Utc;
+use futures_util::future::join_all;
+use tracing::{info, warn};
+
+use crate::{bars, calendar, current_time_ns, hist, sessions, stream::StreamEvent, to_dt, AppState, LastPrice, PriceUpdate};
+
+// Don't ask upstream about the same symbol more than once per window
+const RETRY_NS: u64 = 300 * 1_000_000_000;
+// Lookups tracked at once; beyond this, new symbols wait for old attempts to expire
+const MAX_ATTEMPTS: usize = 2_000;
+
+// Give a symbol with no price its most recent historical trade, marked non-live.
+// The first live trade overwrites it in apply_trade. Returns true when a price was stored.
+pub async fn last_price(state: &AppState, symbol: &str) -> bool {
+    if state.prices.read().await.get(symbol).is_some_and(|p| p.price.is_some()) {
+        return false;
+    }
+    {
+        let now = current_time_ns();
+        let mut attempts = state.backfill_attempts.write().await;
+        if attempts.get(symbol).is_some_and(|t| now.saturating_sub(*t) < RETRY_NS) {
+            return false;
+        }
+        if attempts.len() >= MAX_ATTEMPTS {
+            attempts.retain(|_, t| now.saturating_sub(*t) < RETRY_NS);
+            if attempts.len() >= MAX_ATTEMPTS {
+                warn!("Too many pending price backfills; skipping {}", symbol);
+                return false;
+            }
+        }
+        attempts.insert(symbol.to_string(), now);
+    }
+
+    let dataset = sessions::dataset_for(state, symbol).await;
+    let trade = match hist::last_trade(&dataset, symbol, Utc::now()).await {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            info!("No historical trade to backfill {}", symbol);
+            return false;
+        }
+        Err(e) => {
+            warn!("Price backfill failed for {}: {}", symbol, e);
+            return false;
+        }
+    };
+    let session = calendar::session_at_ns(trade.ts_event);
+    {
+        let mut prices = state.prices.write().await;
+        // A live trade may have landed while we were waiting on upstream
+        if prices.get(symbol).is_some_and(|p| p.live) {
+            return false;
+        }
+        prices.insert(symbol.to_string(), LastPrice {
+            price: Some(trade.price),
+            ts_event_ns: Some(trade.ts_event),
+            session: Some(session),
+            live: false,
+        });
+    }
+    info!("Backfilled {} with ${:.4} from {:?} session", symbol, trade.price, session);
+    state.events.publish(StreamEvent::Price(PriceUpdate {
+        symbol: symbol.to_string(),
+        price: trade.price,
+        timestamp: trade.ts_event,
+        session,
+        live: false,
+    }));
+    true
+}
+
+pub async fn last_prices(state: &AppState, symbols: &[String]) {
+    join_all(symbols.iter().map(|s| last_price(state, s))).await;
+}
+
+// Fill today's minute bars from the pre-market open up to the first live trade (or
+// up to now, if none has come in yet) so HOD, volume baselines and charts don't
+// start at the moment we subscribed.
+pub async fn intraday_bars(state: &AppState, symbol: &str) {
+    let now_ns = current_time_ns();
+    let date = calendar::exchange_date(now_ns);
+    if !calendar::is_trading_day(date) {
+        return;
+    }
+    if state.bars.read().await.get(symbol).is_some_and(|s| s.backfilled && s.date == Some(date)) {
+        return;
+    }
+    let start = calendar::pre_open(date);
+    let end_ns = bars::minute_start(now_ns);
+    let end = to_dt(end_ns);
+    if end <= start {
+        return;
+    }
+
+    let dataset = sessions::dataset_for(state, symbol).await;
+    let history = match hist::minute_bars(&dataset, symbol, start, end).await {
+        Ok(b) => b,
+        Err(e) => {
+            warn!("Intraday bar backfill failed for {}: {}", symbol, e);
+            return;
+        }
+    };
+
+    // Trades in the minute history stops short of: up to the first live trade, or
+    // up to now if none has arrived yet
+    let first_live = state.bars.read().await.get(symbol).and_then(|s| s.first_live_ns);
+    let trades_end_ns = first_live.unwrap_or(now_ns);
+    let trades_start_ns = bars::minute_start(trades_end_ns);
+    let trades = if trades_end_ns == trades_start_ns {
+        Some(Vec::new())
+    } else {
+        match hist::trades(&dataset, symbol, to_dt(trades_start_ns), to_dt(trades_end_ns), None).await {
+            Ok(trades) => Some(trades),
+            Err(e) => {
+                warn!("Boundary trades unavailable for {}: {}", symbol, e);
+                None
+            }
+        }
+    };
+
+    let added = {
+        let mut all_bars = state.bars.write().await;
+        let series = all_bars.entry(symbol.to_string()).or_default();
+        // Live trades may have started while we were fetching; the partial minute then
+        // stops at the first of them. If the boundary moved to another minute, only
+        // whole minutes are safe to add.
+        let boundary = series.first_live_ns.unwrap_or(trades_end_ns);
+        let usable = series.first_live_ns == first_live
+            || (first_live.is_none() && bars::minute_start(boundary) == trades_start_ns);
+        match trades.filter(|_| usable) {
+            Some(trades) => {
+                let mut partial = bars::BarSeries::default();
+                for t in trades.iter().filter(|t| t.ts_event < boundary) {
+                    partial.on_trade(symbol, t.price, t.size, t.ts_event);
+                }
+                series.merge_history(history, end_ns, partial.current, boundary)
+            }
+            None => series.merge_history(history, end_ns, None, end_ns),
+        }
+    };
+    state.scanner.write().await.backfill(symbol, &added);
+    state.rules.write().await.backfill(symbol, &added);
+    info!("Backfilled {} minute bars for {} since {}", added.len(), symbol, start);
+}
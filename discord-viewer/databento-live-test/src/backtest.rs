@@ -0,0 +1,659 @@
+// Backtest mode: `databento-live-test backtest --from 2026-10-01 --to 2026-10-16 ...`
+//
+// Loads the ticker detections for the range from Postgres and replays them, day by
+// day, interleaved with the historical trades (or minute bars) of the mentioned
+// symbols through the live trade path: bars, scanner, alert rules, call outcomes
+// and paper strategies all run unchanged, on a clock driven by event time. Nothing
+// is written back to Postgres. The report lists every trigger with how the symbol
+// traded afterwards, the calls' outcomes and the paper strategies' results.
+//
+// Options:
+//   --from DATE, --to DATE      trading days to replay (--to defaults to --from)
+//   --source trades|bars        replay every print, or four prints per minute bar (default trades)
+//   --rule COND                 alert rule to test, repeatable
+//   --rules-from-db             also test the enabled rules in alert_rules
+//   --paper JSON|@FILE          paper strategy as for POST /api/paper/strategies, repeatable
+//   --paper-from-db             also run the enabled strategies in paper_strategies
+//   --symbols A,B               only replay these symbols
+//   --min-confidence X          detection threshold (default AUTO_SUBSCRIBE_MIN_CONFIDENCE)
+//   --out FILE                  write the report here instead of stdout
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{NaiveDate, Utc};
+use databento::dbn::TradeMsg;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast::error::TryRecvError, mpsc};
+use tracing::{info, warn};
+
+use crate::{
+    apply_trade,
+    bars::{Bar, BAR_NS},
+    blacklist::Blacklist,
+    calendar, close_stale_bars, db, hist,
+    listen::{self, DetectionPrice},
+    new_state, norm_symbol,
+    outcomes::{self, CallOutcome, Outcome},
+    paper::{self, StrategyConfig},
+    sessions::{RoutingRules, SessionCommand},
+    stream::{StreamEvent, StreamHub},
+    to_ns,
+    AppState, SIM_CLOCK_NS,
+};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+#[derive(Debug)]
+struct Options {
+    from: NaiveDate,
+    to: NaiveDate,
+    bars: bool,
+    rules: Vec<String>,
+    rules_from_db: bool,
+    paper: Vec<String>,
+    paper_from_db: bool,
+    symbols: Option<HashSet<String>>,
+    min_confidence: f64,
+    out: Option<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut opts = Options {
+            from: NaiveDate::MIN,
+            to: NaiveDate::MIN,
+            bars: false,
+            rules: Vec::new(),
+            rules_from_db: false,
+            paper: Vec::new(),
+            paper_from_db: false,
+            symbols: None,
+            min_confidence: listen::min_confidence(),
+            out: None,
+        };
+        let mut to = None;
+        let mut it = args.iter();
+        while let Some(arg) = it.next() {
+            let mut value = || it.next().cloned().ok_or_else(|| anyhow!("{} needs a value", arg));
+            let date = |s: String| NaiveDate::parse_from_str(&s, "%Y-%m-%d").with_context(|| format!("invalid date '{}'", s));
+            match arg.as_str() {
+                "--from" => opts.from = date(value()?)?,
+                "--to" => to = Some(date(value()?)?),
+                "--source" => opts.bars = match value()?.as_str() {
+                    "trades" => false,
+                    "bars" => true,
+                    other => bail!("--source must be trades or bars, not '{}'", other),
+                },
+                "--rule" => opts.rules.push(value()?),
+                "--rules-from-db" => opts.rules_from_db = true,
+                "--paper" => opts.paper.push(value()?),
+                "--paper-from-db" => opts.paper_from_db = true,
+                "--symbols" => opts.symbols = Some(value()?.split(',').map(norm_symbol).filter(|s| !s.is_empty()).collect()),
+                "--min-confidence" => opts.min_confidence = value()?.parse().context("invalid --min-confidence")?,
+                "--out" => opts.out = Some(value()?),
+                other => bail!("unknown backtest option '{}'", other),
+            }
+        }
+        if opts.from == NaiveDate::MIN {
+            bail!("backtest needs --from YYYY-MM-DD");
+        }
+        opts.to = to.unwrap_or(opts.from);
+        if opts.to < opts.from {
+            bail!("--to is before --from");
+        }
+        Ok(opts)
+    }
+}
+
+#[derive(Deserialize)]
+struct PaperSpec {
+    name: String,
+    #[serde(flatten)]
+    config: StrategyConfig,
+}
+
+// A ticker detection as stored by the extractor
+#[derive(Clone, Debug)]
+struct Mention {
+    symbol: String,
+    message_id: String,
+    author: Option<String>,
+    confidence: Option<f64>,
+    ts_ns: u64,
+    content: Option<String>,
+}
+
+// Something the live path would have alerted on, and how the symbol traded after it
+#[derive(Debug, Serialize)]
+struct Trigger {
+    kind: &'static str, // scanner | rule | alert
+    name: String,
+    symbol: String,
+    ts_event_ns: u64,
+    price: f64,
+    detail: serde_json::Value,
+    outcome: Outcome,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct MentionCounts {
+    loaded: usize,
+    replayed: usize,
+    below_confidence: usize,
+    blacklisted: usize,
+    duplicate: usize,
+    filtered: usize,
+}
+
+#[derive(Default)]
+struct Run {
+    triggers: Vec<Trigger>,
+    open_triggers: HashMap<String, Vec<usize>>, // symbol -> triggers still being followed
+    mentions: MentionCounts,
+    seen: HashSet<(String, String)>, // (message_id, symbol)
+    prints: u64,
+    lagged: u64,
+    next_step_ns: u64,
+}
+
+// Trading session a mention belongs to: the same day until the post-market ends,
+// otherwise the next trading day
+fn session_date(ts_ns: u64) -> NaiveDate {
+    let date = calendar::exchange_date(ts_ns);
+    if calendar::is_trading_day(date) && ts_ns < to_ns(calendar::post_close(date)) {
+        date
+    } else {
+        calendar::next_trading_day(date)
+    }
+}
+
+async fn load_mentions(db: &db::Db, opts: &Options) -> Result<Vec<Mention>> {
+    let start = calendar::post_close(calendar::previous_trading_day(opts.from));
+    let end = calendar::post_close(opts.to);
+    let rows = db.query(
+        "SELECT td.ticker_symbol, td.message_id, a.username, td.confidence_score::float8, m.discord_timestamp, m.content
+         FROM ticker_detections td
+         JOIN messages m ON m.id = td.message_id
+         LEFT JOIN authors a ON a.id = m.author_id
+         WHERE m.discord_timestamp >= $1 AND m.discord_timestamp < $2
+         ORDER BY m.discord_timestamp",
+        &[&start, &end],
+    ).await.context("loading ticker detections")?;
+    Ok(rows.iter().map(|r| Mention {
+        symbol: norm_symbol(&r.get::<_, String>(0)),
+        message_id: r.get(1),
+        author: r.get(2),
+        confidence: r.get(3),
+        ts_ns: to_ns(r.get(4)),
+        content: r.get(5),
+    }).collect())
+}
+
+async fn setup_rules(state: &AppState, db: &db::Db, opts: &Options) -> Result<()> {
+    let mut conditions: Vec<(Option<String>, String)> = opts.rules.iter().map(|c| (None, c.clone())).collect();
+    if opts.rules_from_db {
+        let rows = db.query("SELECT name, condition FROM alert_rules WHERE enabled ORDER BY id", &[]).await
+            .context("loading alert_rules")?;
+        conditions.extend(rows.iter().map(|r| (Some(r.get(0)), r.get(1))));
+    }
+    let mut book = state.rules.write().await;
+    for (name, condition) in conditions {
+        let rule = book.add(&condition, name).map_err(|e| anyhow!("rule '{}': {}", condition, e))?;
+        info!("Testing rule {}: {}", rule.id, rule.condition);
+    }
+    Ok(())
+}
+
+async fn setup_paper(state: &AppState, db: &db::Db, opts: &Options) -> Result<()> {
+    let mut specs = Vec::new();
+    for spec in &opts.paper {
+        let json = match spec.strip_prefix('@') {
+            Some(path) => std::fs::read_to_string(path).with_context(|| format!("reading {}", path))?,
+            None => spec.clone(),
+        };
+        specs.push(serde_json::from_str::<PaperSpec>(&json).with_context(|| format!("invalid paper strategy {}", spec))?);
+    }
+    if opts.paper_from_db {
+        let rows = db.query("SELECT name, config FROM paper_strategies WHERE enabled ORDER BY id", &[]).await
+            .context("loading paper_strategies")?;
+        for r in rows {
+            let name: String = r.get(0);
+            match serde_json::from_value(r.get(1)) {
+                Ok(config) => specs.push(PaperSpec { name, config }),
+                Err(e) => warn!("Skipping paper strategy '{}': {}", name, e),
+            }
+        }
+    }
+    let mut book = state.paper.write().await;
+    for spec in specs {
+        let strategy = book.add_strategy(&spec.name, spec.config, true)
+            .map_err(|e| anyhow!("paper strategy '{}': {}", spec.name, e))?;
+        info!("Running paper strategy {} following {:?}", strategy.name, strategy.config.authors);
+    }
+    Ok(())
+}
+
+// One symbol's prints for the day. Trades are read from upstream as the replay
+// reaches them, in the order the feed delivered them, so a busy name never sits in
+// memory whole; bars become open, high/low, low/high, close prints.
+enum Prints {
+    Trades(hist::RangeStream),
+    Bars(std::vec::IntoIter<(f64, u32, u64)>),
+}
+
+struct Feed {
+    symbol: String,
+    prints: Prints,
+}
+
+impl Feed {
+    async fn open(symbol: &str, date: NaiveDate, bars: bool) -> Result<Feed, hist::HistError> {
+        let (start, end) = (calendar::pre_open(date), calendar::post_close(date));
+        let dataset = RoutingRules::from_env().route(symbol);
+        let prints = if bars {
+            let bars: Vec<Bar> = hist::minute_bars(&dataset, symbol, start, end).await?;
+            let prints: Vec<(f64, u32, u64)> = bars.iter().flat_map(|b| {
+                let path = if b.close >= b.open { [b.open, b.low, b.high, b.close] } else { [b.open, b.high, b.low, b.close] };
+                let size = (b.volume / 4).min(u32::MAX as u64) as u32;
+                path.into_iter().enumerate().map(move |(i, px)| (px, size, b.start_ns + i as u64 * BAR_NS / 4))
+            }).collect();
+            Prints::Bars(prints.into_iter())
+        } else {
+            Prints::Trades(hist::open_range(&dataset, symbol, "trades", start, end, None).await?)
+        };
+        Ok(Feed { symbol: symbol.to_string(), prints })
+    }
+
+    // (price, size, ts_event)
+    async fn next(&mut self) -> Result<Option<(f64, u32, u64)>, hist::HistError> {
+        match &mut self.prints {
+            Prints::Bars(prints) => Ok(prints.next()),
+            Prints::Trades(stream) => loop {
+                let Some(record) = stream.next().await? else { return Ok(None) };
+                if let Some(t) = hist::trade(&record) {
+                    return Ok(Some((t.price, t.size, t.ts_event)));
+                }
+            },
+        }
+    }
+}
+
+// Each replayed session starts from scratch, like the live server at the pre-market open
+async fn reset_session(state: &AppState) {
+    state.bars.write().await.clear();
+    state.scanner.write().await.reset();
+    state.rules.write().await.reset_fields();
+}
+
+fn trade_msg(px: f64, size: u32, ts_ns: u64) -> TradeMsg {
+    let mut msg = TradeMsg { price: (px * 1e9).round() as i64, size, ts_recv: ts_ns, ..Default::default() };
+    msg.hd.ts_event = ts_ns;
+    msg
+}
+
+impl Run {
+    // Move the clock to `ts_ns`, running the live path's periodic work once per second of event time
+    async fn advance(&mut self, state: &AppState, ts_ns: u64) {
+        SIM_CLOCK_NS.store(ts_ns.max(1), Ordering::Relaxed);
+        if ts_ns >= self.next_step_ns {
+            close_stale_bars(state).await;
+            outcomes::track(state).await;
+            paper::sweep(state).await;
+            self.next_step_ns = (ts_ns / NS_PER_SEC + 1) * NS_PER_SEC;
+        }
+    }
+
+    async fn print(&mut self, state: &AppState, symbol: &str, px: f64, size: u32, ts_ns: u64) {
+        self.prints += 1;
+        apply_trade(state, symbol, &trade_msg(px, size, ts_ns)).await;
+        if let Some(open) = self.open_triggers.get_mut(symbol) {
+            open.retain(|i| {
+                let outcome = &mut self.triggers[*i].outcome;
+                outcome.observe(px, ts_ns);
+                !outcome.complete
+            });
+        }
+    }
+
+    async fn mention(&mut self, state: &AppState, m: Mention, opts: &Options) {
+        if m.confidence.is_some_and(|c| c < opts.min_confidence) {
+            self.mentions.below_confidence += 1;
+            return;
+        }
+        if !self.seen.insert((m.message_id.clone(), m.symbol.clone())) {
+            self.mentions.duplicate += 1;
+            return;
+        }
+        if state.blacklist.read().await.check(&m.symbol, m.content.as_deref(), m.confidence).is_some() {
+            self.mentions.blacklisted += 1;
+            return;
+        }
+        self.mentions.replayed += 1;
+        let last = state.prices.read().await.get(&m.symbol).and_then(|p| p.price.zip(p.ts_event_ns));
+        let record = DetectionPrice {
+            symbol: m.symbol.clone(),
+            channel: "backtest".to_string(),
+            message_id: Some(m.message_id),
+            author: m.author,
+            confidence: m.confidence,
+            detected_at_ns: m.ts_ns,
+            price: last.map(|(px, _)| px),
+            price_ts_ns: last.map(|(_, ts)| ts),
+            price_source: last.map(|_| "live"),
+            outcome: last.map(|(px, _)| Outcome::new(px, m.ts_ns)),
+            row_id: None,
+        };
+        paper::on_message(state, &record, m.content.as_deref()).await;
+        state.detections.write().await.entry(m.symbol).or_default().push(record);
+    }
+
+    // Collect what the live path published since the last drain
+    async fn drain(&mut self, state: &AppState, rx: &mut tokio::sync::broadcast::Receiver<Arc<crate::stream::Envelope>>) {
+        loop {
+            let envelope = match rx.try_recv() {
+                Ok(envelope) => envelope,
+                Err(TryRecvError::Lagged(n)) => {
+                    self.lagged += n;
+                    continue;
+                }
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            };
+            let (kind, name, symbol, ts, detail) = match &envelope.event {
+                StreamEvent::Scanner(hit) => ("scanner", hit.kind.to_string(), hit.symbol.clone(), hit.ts_event_ns, serde_json::json!(hit)),
+                StreamEvent::Rule(hit) => ("rule", hit.rule_name.clone(), hit.symbol.clone(), hit.ts_event_ns, serde_json::json!(hit)),
+                StreamEvent::Alert(alert) => (
+                    "alert",
+                    format!("{:?} {}", alert.direction, alert.level),
+                    alert.symbol.clone(),
+                    alert.triggered_at_ns.unwrap_or(alert.created_at_ns),
+                    serde_json::json!(alert),
+                ),
+                _ => continue,
+            };
+            let Some(price) = state.prices.read().await.get(&symbol).and_then(|p| p.price) else { continue };
+            self.open_triggers.entry(symbol.clone()).or_default().push(self.triggers.len());
+            self.triggers.push(Trigger { kind, name, symbol, ts_event_ns: ts, price, detail, outcome: Outcome::new(price, ts) });
+        }
+    }
+
+    // One session: the mentions and the feeds' prints merged by time through the live
+    // path, then the day's time-based exits and horizons played out to the post-market close
+    async fn replay_day(
+        &mut self,
+        state: &AppState,
+        rx: &mut tokio::sync::broadcast::Receiver<Arc<crate::stream::Envelope>>,
+        date: NaiveDate,
+        mentions: Vec<Mention>,
+        mut feeds: Vec<Feed>,
+        opts: &Options,
+    ) {
+        let mut mentions: VecDeque<Mention> = mentions.into();
+        mentions.make_contiguous().sort_by_key(|m| m.ts_ns);
+
+        // Each feed holds at most one pending print
+        let mut heads: Vec<(f64, u32)> = vec![(0.0, 0); feeds.len()];
+        let mut queue: BinaryHeap<Reverse<(u64, usize)>> = BinaryHeap::new();
+        for (i, feed) in feeds.iter_mut().enumerate() {
+            match feed.next().await {
+                Ok(Some((px, size, ts_ns))) => {
+                    heads[i] = (px, size);
+                    queue.push(Reverse((ts_ns, i)));
+                }
+                Ok(None) => info!("{} {}: no prints", date, feed.symbol),
+                Err(e) => warn!("{} {}: no historical data: {}", date, feed.symbol, e),
+            }
+        }
+
+        loop {
+            let next_print_ns = queue.peek().map(|Reverse((ts_ns, _))| *ts_ns);
+            if mentions.front().is_some_and(|m| next_print_ns.is_none_or(|ts_ns| m.ts_ns <= ts_ns)) {
+                let Some(m) = mentions.pop_front() else { break };
+                self.advance(state, m.ts_ns).await;
+                self.mention(state, m, opts).await;
+                self.drain(state, rx).await;
+                continue;
+            }
+            let Some(Reverse((ts_ns, i))) = queue.pop() else { break };
+            let (px, size) = heads[i];
+            self.advance(state, ts_ns).await;
+            self.print(state, &feeds[i].symbol, px, size, ts_ns).await;
+            self.drain(state, rx).await;
+            match feeds[i].next().await {
+                Ok(Some((px, size, ts_ns))) => {
+                    heads[i] = (px, size);
+                    queue.push(Reverse((ts_ns, i)));
+                }
+                Ok(None) => {}
+                Err(e) => warn!("{} {}: prints cut short: {}", date, feeds[i].symbol, e),
+            }
+        }
+        let end_ns = to_ns(calendar::post_close(date));
+        self.next_step_ns = 0;
+        self.advance(state, end_ns).await;
+        self.drain(state, rx).await;
+        for trigger in &mut self.triggers {
+            trigger.outcome.freeze(end_ns);
+        }
+    }
+}
+
+fn pct(from: f64, to: Option<f64>) -> Option<f64> {
+    to.filter(|_| from > 0.0).map(|to| (to - from) / from * 100.0)
+}
+
+// Average forward returns per trigger (kind, name)
+fn trigger_summary(triggers: &[Trigger]) -> serde_json::Value {
+    let mut groups: BTreeMap<String, Vec<&Trigger>> = BTreeMap::new();
+    for t in triggers {
+        groups.entry(format!("{}:{}", t.kind, t.name)).or_default().push(t);
+    }
+    let avg = |list: &[&Trigger], f: &dyn Fn(&Outcome) -> Option<f64>| {
+        let values: Vec<f64> = list.iter().filter_map(|t| f(&t.outcome)).collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    let summary: BTreeMap<String, serde_json::Value> = groups.into_iter().map(|(key, list)| {
+        let json = serde_json::json!({
+            "count": list.len(),
+            "avg_max_gain_pct": avg(&list, &|o| Some(o.max_gain_pct)),
+            "avg_max_drawdown_pct": avg(&list, &|o| Some(o.max_drawdown_pct)),
+            "avg_return_5m_pct": avg(&list, &|o| pct(o.entry_price, o.price_5m)),
+            "avg_return_15m_pct": avg(&list, &|o| pct(o.entry_price, o.price_15m)),
+            "avg_return_60m_pct": avg(&list, &|o| pct(o.entry_price, o.price_60m)),
+            "avg_close_return_pct": avg(&list, &|o| pct(o.entry_price, o.close_price)),
+        });
+        (key, json)
+    }).collect();
+    serde_json::json!(summary)
+}
+
+pub async fn run(args: &[String]) -> Result<()> {
+    let opts = Options::parse(args)?;
+    if std::env::var("DATABENTO_API_KEY").map(|v| v.is_empty()).unwrap_or(true) {
+        bail!("backtest needs DATABENTO_API_KEY for historical trades");
+    }
+    let db = db::connect_from_env().await.ok_or_else(|| anyhow!("backtest needs DATABASE_URL for the detections"))?;
+
+    // A state of its own with no database, so nothing the live path persists is written
+    let events = Arc::new(StreamHub::new());
+    let mut rx = events.subscribe();
+    let (session_sender, _sessions) = mpsc::unbounded_channel::<SessionCommand>();
+    let state = new_state(None, events, session_sender);
+    *state.blacklist.write().await = Blacklist::load(&db).await.context("loading the blacklist")?;
+    setup_rules(&state, &db, &opts).await?;
+    setup_paper(&state, &db, &opts).await?;
+
+    let mentions = load_mentions(&db, &opts).await?;
+    let mut by_day: BTreeMap<NaiveDate, Vec<Mention>> = BTreeMap::new();
+    let mut run = Run::default();
+    run.mentions.loaded = mentions.len();
+    for m in mentions {
+        if opts.symbols.as_ref().is_some_and(|s| !s.contains(&m.symbol)) {
+            run.mentions.filtered += 1;
+            continue;
+        }
+        by_day.entry(session_date(m.ts_ns)).or_default().push(m);
+    }
+
+    let mut all_symbols: HashSet<String> = HashSet::new();
+    let mut days = 0;
+    let mut date = opts.from;
+    while date <= opts.to {
+        if !calendar::is_trading_day(date) {
+            date = date.succ_opt().expect("date in range");
+            continue;
+        }
+        days += 1;
+        let day_mentions = by_day.remove(&date).unwrap_or_default();
+        let mut symbols: Vec<String> = day_mentions.iter().map(|m| m.symbol.clone()).collect();
+        symbols.sort();
+        symbols.dedup();
+        info!("{}: replaying {} mentions across {} symbols", date, day_mentions.len(), symbols.len());
+
+        reset_session(&state).await;
+        let mut feeds: Vec<Feed> = Vec::new();
+        for symbol in &symbols {
+            match Feed::open(symbol, date, opts.bars).await {
+                Ok(feed) => feeds.push(feed),
+                Err(e) => warn!("{} {}: no historical data: {}", date, symbol, e),
+            }
+        }
+        all_symbols.extend(symbols);
+        run.replay_day(&state, &mut rx, date, day_mentions, feeds, &opts).await;
+        date = date.succ_opt().expect("date in range");
+    }
+
+    let calls: Vec<CallOutcome> = {
+        let detections = state.detections.read().await;
+        let mut calls: Vec<CallOutcome> = detections.values().flatten().map(CallOutcome::from_detection).collect();
+        calls.sort_by_key(|c| c.detected_at_ns);
+        calls
+    };
+    let mut symbols: Vec<String> = all_symbols.into_iter().collect();
+    symbols.sort();
+    let report = serde_json::json!({
+        "from": opts.from.to_string(),
+        "to": opts.to.to_string(),
+        "source": if opts.bars { "bars" } else { "trades" },
+        "days": days,
+        "symbols": symbols,
+        "prints": run.prints,
+        "mentions": run.mentions,
+        "triggers": {
+            "summary": trigger_summary(&run.triggers),
+            "list": run.triggers
+        },
+        "calls": {
+            "summary": outcomes::summarize(&calls),
+            "list": calls
+        },
+        "paper": state.paper.read().await.report(),
+        "lagged_events": run.lagged,
+        "generated_at": Utc::now().to_rfc3339()
+    });
+    SIM_CLOCK_NS.store(0, Ordering::Relaxed);
+
+    info!(
+        "Backtest {}..{}: {} days, {} prints, {} mentions replayed, {} triggers, {} calls",
+        opts.from, opts.to, days, run.prints, run.mentions.replayed,
+        report["triggers"]["list"].as_array().map_or(0, |l| l.len()),
+        report["calls"]["list"].as_array().map_or(0, |l| l.len()),
+    );
+    let json = serde_json::to_string_pretty(&report)?;
+    match &opts.out {
+        Some(path) => {
+            std::fs::write(path, json).with_context(|| format!("writing {}", path))?;
+            info!("Report written to {}", path);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ns(s: &str) -> u64 {
+        chrono::DateTime::parse_from_rfc3339(s).unwrap().timestamp_nanos_opt().unwrap() as u64
+    }
+
+    fn feed(symbol: &str, prints: Vec<(f64, u32, u64)>) -> Feed {
+        Feed { symbol: symbol.to_string(), prints: Prints::Bars(prints.into_iter()) }
+    }
+
+    fn mention(symbol: &str, message_id: &str, confidence: f64, ts_ns: u64) -> Mention {
+        Mention {
+            symbol: symbol.to_string(),
+            message_id: message_id.to_string(),
+            author: None,
+            confidence: Some(confidence),
+            ts_ns,
+            content: None,
+        }
+    }
+
+    #[test]
+    fn mentions_go_to_the_session_they_trade_in() {
+        // Thursday pre-market and post-market belong to Thursday; after 20:00 ET it's Friday
+        let thursday = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        assert_eq!(session_date(ns("2026-10-15T08:30:00Z")), thursday);
+        assert_eq!(session_date(ns("2026-10-15T23:59:00Z")), thursday);
+        assert_eq!(session_date(ns("2026-10-16T00:30:00Z")), thursday.succ_opt().unwrap());
+        // Weekend calls wait for Monday
+        assert_eq!(session_date(ns("2026-10-17T15:00:00Z")), NaiveDate::from_ymd_opt(2026, 10, 19).unwrap());
+
+        let args: Vec<String> = ["--from", "2026-10-15", "--source", "bars", "--symbols", "aapl, msft"].iter().map(|s| s.to_string()).collect();
+        let opts = Options::parse(&args).unwrap();
+        assert_eq!((opts.from, opts.to, opts.bars), (thursday, thursday, true));
+        assert_eq!(opts.symbols, Some(HashSet::from(["AAPL".to_string(), "MSFT".to_string()])));
+        assert!(Options::parse(&["--to".to_string(), "2026-10-15".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn replay_day_interleaves_mentions_and_prints() {
+        let events = Arc::new(StreamHub::new());
+        let mut rx = events.subscribe();
+        let (session_sender, _sessions) = mpsc::unbounded_channel::<SessionCommand>();
+        let state = new_state(None, events, session_sender);
+        let opts = Options::parse(&["--from".to_string(), "2026-10-15".to_string()]).unwrap();
+        let date = opts.from;
+
+        let t0 = ns("2026-10-15T14:00:00Z");
+        let minute = BAR_NS;
+        let feeds = vec![
+            feed("AAPL", vec![(10.0, 100, t0), (10.5, 100, t0 + minute), (11.0, 100, t0 + 2 * minute)]),
+            feed("MSFT", vec![(50.0, 10, t0 + 3 * minute)]),
+            feed("TSLA", Vec::new()),
+        ];
+        let mentions = vec![
+            // Out of order on purpose; a mention at a print's time sees the price before it
+            mention("MSFT", "2", 0.9, t0 + minute),
+            mention("AAPL", "1", 0.9, t0 + minute),
+            mention("AAPL", "1", 0.9, t0 + minute),
+            mention("AAPL", "3", 0.1, t0 + minute),
+        ];
+
+        let mut run = Run::default();
+        run.replay_day(&state, &mut rx, date, mentions, feeds, &opts).await;
+        SIM_CLOCK_NS.store(0, Ordering::Relaxed);
+
+        assert_eq!(run.prints, 4);
+        let counts = &run.mentions;
+        assert_eq!((counts.replayed, counts.duplicate, counts.below_confidence), (2, 1, 1));
+
+        let detections = state.detections.read().await;
+        let aapl = &detections["AAPL"][0];
+        assert_eq!((aapl.price, aapl.price_ts_ns), (Some(10.0), Some(t0)));
+        let outcome = aapl.outcome.as_ref().unwrap();
+        assert!(outcome.complete);
+        assert_eq!((outcome.close_price, outcome.max_price), (Some(11.0), 11.0));
+        // No MSFT print had come in yet; the call is priced by the first one
+        let msft = &detections["MSFT"][0];
+        assert_eq!((msft.price, msft.price_source), (Some(50.0), Some("first_live_trade")));
+        assert_eq!(msft.outcome.as_ref().map(|o| o.entry_price), Some(50.0));
+    }
+}
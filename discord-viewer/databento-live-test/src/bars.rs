@@ -0,0 +1,339 @@
+// One-minute OHLCV bars built from live trades. A series holds one exchange day
+// and starts over on the first trade (or sweep) of the next.
+
+use std::collections::VecDeque;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::NaiveDate;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{calendar, norm_symbol, AppState};
+
+pub const BAR_NS: u64 = 60 * 1_000_000_000;
+
+// Closed bars kept per symbol (a full extended-hours day)
+const MAX_CLOSED_BARS: usize = 16 * 60;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Bar {
+    pub symbol: String,
+    pub start_ns: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+    pub trades: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct BarSeries {
+    pub date: Option<NaiveDate>, // exchange day the bars belong to
+    pub current: Option<Bar>,
+    pub closed: VecDeque<Bar>,
+    pub first_live_ns: Option<u64>, // ts_event of the first live trade
+    pub backfilled: bool,
+    // Historical bars cover everything before this; older live prints are duplicates
+    history_end_ns: u64,
+}
+
+pub fn minute_start(ts_ns: u64) -> u64 {
+    ts_ns - ts_ns % BAR_NS
+}
+
+impl BarSeries {
+    // Start over when `date` is a later exchange day than the series holds
+    pub fn roll(&mut self, date: NaiveDate) {
+        if self.date.is_some_and(|d| d < date) {
+            *self = BarSeries::default();
+        }
+    }
+
+    // Fold a trade into the current bar; returns the previous bar if this trade closed it
+    pub fn on_trade(&mut self, symbol: &str, px: f64, size: u32, ts_ns: u64) -> Option<Bar> {
+        let date = calendar::exchange_date(ts_ns);
+        self.roll(date);
+        if ts_ns < self.history_end_ns || self.date.is_some_and(|d| d > date) {
+            return None;
+        }
+        self.date = Some(date);
+        self.first_live_ns.get_or_insert(ts_ns);
+        let start_ns = minute_start(ts_ns);
+        let mut closed = None;
+
+        if let Some(bar) = &mut self.current {
+            if start_ns == bar.start_ns {
+                bar.high = bar.high.max(px);
+                bar.low = bar.low.min(px);
+                bar.close = px;
+                bar.volume += size as u64;
+                bar.trades += 1;
+                return None;
+            }
+            if start_ns < bar.start_ns {
+                // Late print for an already-closed minute; leave history alone
+                return None;
+            }
+            closed = self.current.take();
+        }
+
+        self.current = Some(Bar {
+            symbol: symbol.to_string(),
+            start_ns,
+            open: px,
+            high: px,
+            low: px,
+            close: px,
+            volume: size as u64,
+            trades: 1,
+        });
+        if let Some(bar) = &closed {
+            self.push_closed(bar.clone());
+        }
+        closed
+    }
+
+    // Close the current bar once wall-clock time has moved past its minute
+    pub fn close_if_stale(&mut self, now_ns: u64) -> Option<Bar> {
+        let stale = self.current.as_ref().is_some_and(|b| now_ns >= b.start_ns + BAR_NS);
+        if !stale {
+            return None;
+        }
+        let bar = self.current.take()?;
+        self.push_closed(bar.clone());
+        Some(bar)
+    }
+
+    // Stitch historical bars in front of the live ones. `bars` are complete minutes
+    // ending at `end_ns`; `prefix` is the partial minute after them, built from trades
+    // up to `prefix_end_ns`. With live trades it is folded into the first live bar;
+    // before any, it becomes the current bar and live prints resume where it stops.
+    // Returns the historical bars that were added.
+    pub fn merge_history(&mut self, bars: Vec<Bar>, end_ns: u64, prefix: Option<Bar>, prefix_end_ns: u64) -> Vec<Bar> {
+        let date = calendar::exchange_date(end_ns.saturating_sub(1));
+        self.roll(date);
+        if self.date.is_some_and(|d| d > date) {
+            return Vec::new();
+        }
+        self.date = Some(date);
+        let limit = match self.first_live_ns {
+            Some(first) => end_ns.min(minute_start(first)),
+            None => end_ns,
+        };
+        let earliest = self.closed.front().or(self.current.as_ref()).map(|b| b.start_ns).unwrap_or(u64::MAX);
+        let added: Vec<Bar> = bars.into_iter()
+            .filter(|b| b.start_ns < limit && b.start_ns < earliest)
+            .collect();
+        for bar in added.iter().rev() {
+            self.closed.push_front(bar.clone());
+        }
+        while self.closed.len() > MAX_CLOSED_BARS {
+            self.closed.pop_front();
+        }
+
+        match (self.first_live_ns, prefix) {
+            (None, prefix) => {
+                self.history_end_ns = prefix_end_ns.max(end_ns);
+                self.current = prefix;
+            }
+            (Some(first), Some(p)) if p.start_ns == minute_start(first) => {
+                let boundary = self.closed.iter_mut()
+                    .chain(self.current.iter_mut())
+                    .find(|b| b.start_ns == p.start_ns);
+                if let Some(bar) = boundary {
+                    bar.open = p.open;
+                    bar.high = bar.high.max(p.high);
+                    bar.low = bar.low.min(p.low);
+                    bar.volume += p.volume;
+                    bar.trades += p.trades;
+                }
+            }
+            _ => {}
+        }
+        self.backfilled = true;
+        added
+    }
+
+    // Fold trades missed during an outage [gap_start, gap_end) into history, in order.
+    // Minutes the outage swallowed are created; minutes it cut into take the missed
+    // prints on the side they fell: before the live ones (open) or after them (close).
+    pub fn apply_missed(&mut self, symbol: &str, prints: &[(f64, u32, u64)], gap_start: u64, gap_end: u64) {
+        let had_current = self.current.is_some();
+        let mut all: Vec<Bar> = self.closed.drain(..).chain(self.current.take()).collect();
+        let mut reopened: Vec<u64> = Vec::new();
+        let mut created: Vec<u64> = Vec::new();
+        for &(px, size, ts) in prints {
+            let start_ns = minute_start(ts);
+            match all.iter_mut().find(|b| b.start_ns == start_ns) {
+                Some(bar) => {
+                    if start_ns >= gap_start && !reopened.contains(&start_ns) {
+                        bar.open = px;
+                        reopened.push(start_ns);
+                    }
+                    if start_ns + BAR_NS <= gap_end || created.contains(&start_ns) {
+                        bar.close = px;
+                    }
+                    bar.high = bar.high.max(px);
+                    bar.low = bar.low.min(px);
+                    bar.volume += size as u64;
+                    bar.trades += 1;
+                }
+                None => {
+                    reopened.push(start_ns);
+                    created.push(start_ns);
+                    all.push(Bar {
+                        symbol: symbol.to_string(),
+                        start_ns,
+                        open: px,
+                        high: px,
+                        low: px,
+                        close: px,
+                        volume: size as u64,
+                        trades: 1,
+                    });
+                    all.sort_by_key(|b| b.start_ns);
+                }
+            }
+        }
+        if had_current {
+            self.current = all.pop();
+        }
+        self.closed = all.into();
+        while self.closed.len() > MAX_CLOSED_BARS {
+            self.closed.pop_front();
+        }
+    }
+
+    pub fn all(&self) -> Vec<Bar> {
+        self.closed.iter().chain(self.current.iter()).cloned().collect()
+    }
+
+    fn push_closed(&mut self, bar: Bar) {
+        self.closed.push_back(bar);
+        while self.closed.len() > MAX_CLOSED_BARS {
+            self.closed.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BarsQuery {
+    symbol: String,
+    #[serde(default)]
+    since: Option<u64>,
+}
+
+// GET /api/live/bars?symbol=AAPL[&since=<bar start ns>] - today's stitched historical + live bars
+pub async fn get_bars(Query(q): Query<BarsQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let symbol = norm_symbol(&q.symbol);
+    if symbol.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "symbol is required"})));
+    }
+    let all_bars = state.bars.read().await;
+    let Some(series) = all_bars.get(&symbol) else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no bars for symbol", "symbol": symbol})));
+    };
+    let bars: Vec<Bar> = series.all().into_iter()
+        .filter(|b| q.since.is_none_or(|s| b.start_ns >= s))
+        .collect();
+    (StatusCode::OK, Json(serde_json::json!({
+        "symbol": symbol,
+        "date": series.date.map(|d| d.to_string()),
+        "backfilled": series.backfilled,
+        "first_live_ns": series.first_live_ns,
+        "count": bars.len(),
+        "bars": bars
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ns(s: &str) -> u64 {
+        chrono::DateTime::parse_from_rfc3339(s).unwrap().timestamp_nanos_opt().unwrap() as u64
+    }
+
+    #[test]
+    fn starts_over_each_exchange_day() {
+        let mut series = BarSeries::default();
+        series.on_trade("AAPL", 10.0, 100, ns("2026-10-15T19:59:30Z"));
+        series.on_trade("AAPL", 10.5, 100, ns("2026-10-15T23:59:00Z")); // 19:59 ET, post-market
+        assert_eq!(series.date, NaiveDate::from_ymd_opt(2026, 10, 15));
+        assert_eq!(series.all().len(), 2);
+
+        let friday_pre = ns("2026-10-16T08:00:10Z");
+        series.on_trade("AAPL", 11.0, 50, friday_pre);
+        assert_eq!(series.date, NaiveDate::from_ymd_opt(2026, 10, 16));
+        assert_eq!(series.first_live_ns, Some(friday_pre));
+        assert_eq!(series.all().len(), 1);
+        assert_eq!(series.current.as_ref().map(|b| b.volume), Some(50));
+
+        // A late print from the day before is dropped
+        assert_eq!(series.on_trade("AAPL", 9.0, 10, ns("2026-10-15T23:59:59Z")), None);
+        assert_eq!(series.all().len(), 1);
+    }
+
+    #[test]
+    fn sweep_rolls_idle_series() {
+        let mut series = BarSeries::default();
+        series.on_trade("AAPL", 10.0, 100, ns("2026-10-15T14:00:00Z"));
+        series.roll(NaiveDate::from_ymd_opt(2026, 10, 15).unwrap());
+        assert_eq!(series.all().len(), 1);
+        series.roll(NaiveDate::from_ymd_opt(2026, 10, 16).unwrap());
+        assert!(series.all().is_empty() && series.first_live_ns.is_none() && series.date.is_none());
+    }
+
+    fn bar(start: &str, open: f64, close: f64, volume: u64) -> Bar {
+        Bar {
+            symbol: "AAPL".into(),
+            start_ns: ns(start),
+            open,
+            high: open.max(close),
+            low: open.min(close),
+            close,
+            volume,
+            trades: 1,
+        }
+    }
+
+    #[test]
+    fn history_before_any_live_trade_keeps_the_partial_minute() {
+        let mut series = BarSeries::default();
+        let end = ns("2026-10-15T14:01:00Z");
+        let fetched_to = ns("2026-10-15T14:01:20Z");
+        let history = vec![bar("2026-10-15T14:00:00Z", 10.0, 10.2, 100)];
+        let added = series.merge_history(history, end, Some(bar("2026-10-15T14:01:00Z", 10.2, 10.3, 40)), fetched_to);
+        assert_eq!(added.len(), 1);
+        assert_eq!(series.current.as_ref().map(|b| b.volume), Some(40));
+
+        // Live prints the trade fetch already covered are duplicates; later ones extend the minute
+        assert_eq!(series.on_trade("AAPL", 10.3, 40, ns("2026-10-15T14:01:10Z")), None);
+        series.on_trade("AAPL", 10.5, 10, fetched_to);
+        let current = series.current.clone().unwrap();
+        assert_eq!((current.open, current.high, current.close, current.volume, current.trades), (10.2, 10.5, 10.5, 50, 2));
+        assert_eq!(series.first_live_ns, Some(fetched_to));
+
+        let closed = series.on_trade("AAPL", 10.4, 5, ns("2026-10-15T14:02:05Z")).unwrap();
+        assert_eq!((closed.start_ns, closed.volume), (ns("2026-10-15T14:01:00Z"), 50));
+        assert_eq!(series.all().len(), 3);
+    }
+
+    #[test]
+    fn history_after_live_trades_folds_the_prefix_into_the_first_live_bar() {
+        let mut series = BarSeries::default();
+        let first = ns("2026-10-15T14:01:30Z");
+        series.on_trade("AAPL", 10.4, 10, first);
+        let history = vec![bar("2026-10-15T14:00:00Z", 10.0, 10.2, 100), bar("2026-10-15T14:01:00Z", 10.2, 10.4, 60)];
+        let added = series.merge_history(history, ns("2026-10-15T14:02:00Z"), Some(bar("2026-10-15T14:01:00Z", 10.2, 10.1, 30)), first);
+        // The live minute is not replaced by its historical bar
+        assert_eq!(added.len(), 1);
+        let current = series.current.clone().unwrap();
+        assert_eq!((current.open, current.low, current.close, current.volume, current.trades), (10.2, 10.1, 10.4, 40, 2));
+    }
+}
@@ -0,0 +1,741 @@
+// Local time-series store for multi-day charts: one-minute and daily bars per symbol
+// in an embedded redb database, so a past session costs one upstream request the
+// first time it is charted and none after that.
+//
+// The live minute bars (which already carry the intraday backfill and outage
+// recovery) are written every BARSTORE_FLUSH_SECS. Once a session has ended, a day
+// the live feed covered from the pre-market open, with every outage recovered, is
+// marked complete. Sessions a request needs that aren't complete are fetched from
+// the historical API and stored: ohlcv-1m for minute resolutions, ohlcv-1h rolled
+// up for daily ones. A daily bar carries the regular session's prices and volume
+// (so its close is the closing print, not the last post-market one), with the
+// pre- and post-market volume alongside.
+//
+// Compaction rolls complete sessions older than the minute retention into their
+// daily bar, drops the remaining expired minutes and daily bars, then compacts the
+// file. It runs at startup and every BARSTORE_COMPACT_HOURS.
+//
+//   BARSTORE_DIR            enables the store (unset: off)
+//   BARSTORE_MINUTE_DAYS    days of minute bars kept (default 60)
+//   BARSTORE_DAILY_DAYS     days of daily bars kept (default 3650)
+//   BARSTORE_FLUSH_SECS     how often live bars are written (default 60)
+//   BARSTORE_COMPACT_HOURS  how often compaction runs (default 24)
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{Datelike, NaiveDate};
+use http::StatusCode;
+use redb::{Database, ReadableTable, ReadableTableMetadata, Table, TableDefinition};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+use crate::{
+    bars::{self, Bar, BAR_NS},
+    calendar, current_time_ns, env, hist, norm_symbol, sessions, to_dt, to_ns, AppState,
+};
+
+const DB_FILE: &str = "bars.redb";
+
+// open, high, low, close, volume, trades
+type Ohlcv = (f64, f64, f64, f64, u64, u32);
+
+// Ohlcv of the regular session, then the extended-hours volume
+type Daily = (f64, f64, f64, f64, u64, u32, u64);
+
+// (symbol, minute start ns) and (symbol, exchange date as yyyymmdd)
+const MINUTES: TableDefinition<(&str, u64), Ohlcv> = TableDefinition::new("minutes");
+const DAILY: TableDefinition<(&str, u32), Daily> = TableDefinition::new("daily_rth");
+const DAYS: TableDefinition<(&str, u32), u8> = TableDefinition::new("days");
+
+// Daily bars of the whole extended session, dropped at open
+const LEGACY_DAILY: TableDefinition<(&str, u32), Ohlcv> = TableDefinition::new("daily");
+
+// DAYS flags (2 marked a legacy daily bar and is ignored)
+const MINUTES_COMPLETE: u8 = 1; // every minute of the session is stored
+const DAILY_FINAL: u8 = 4; // the daily bar is stored, or the symbol didn't trade
+
+// Longest range one request may span, in calendar days
+const MAX_MINUTE_DAYS: i64 = 31;
+const MAX_DAILY_DAYS: i64 = 3660;
+
+// Live bars of an ended session are trusted once its last minute has been closed and flushed
+const SETTLE_NS: u64 = 120 * 1_000_000_000;
+
+#[derive(Clone, Debug)]
+pub struct BarStoreConfig {
+    pub dir: PathBuf,
+    pub minute_days: u32,
+    pub daily_days: u32,
+    pub flush: Duration,
+    pub compact: Duration,
+}
+
+impl BarStoreConfig {
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("BARSTORE_DIR").ok().filter(|d| !d.trim().is_empty())?;
+        Some(BarStoreConfig {
+            dir: PathBuf::from(dir.trim()),
+            minute_days: env("BARSTORE_MINUTE_DAYS", 60u32).max(1),
+            daily_days: env("BARSTORE_DAILY_DAYS", 3650u32).max(1),
+            flush: Duration::from_secs(env("BARSTORE_FLUSH_SECS", 60u64).max(1)),
+            compact: Duration::from_secs(env("BARSTORE_COMPACT_HOURS", 24u64).max(1) * 3600),
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("bar store: {0}")]
+    Db(Box<redb::Error>),
+    #[error("bar store task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[error(transparent)]
+    Hist(#[from] hist::HistError),
+}
+
+// redb's errors are large; keep them boxed
+macro_rules! from_redb {
+    ($($error:ty),*) => {
+        $(impl From<$error> for StoreError {
+            fn from(e: $error) -> Self {
+                StoreError::Db(Box::new(e.into()))
+            }
+        })*
+    };
+}
+
+from_redb!(redb::Error, redb::DatabaseError, redb::TransactionError, redb::TableError, redb::StorageError, redb::CommitError, redb::CompactionError);
+
+// A session's bar for daily charts: the regular session, plus the volume traded
+// before the open and after the close
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DailyBar {
+    #[serde(flatten)]
+    pub bar: Bar,
+    pub extended_volume: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Compaction {
+    pub rolled_sessions: usize,
+    pub dropped_minutes: u64,
+    pub dropped_days: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+#[derive(Clone)]
+pub struct BarStore {
+    db: Arc<RwLock<Database>>, // write-locked only to compact the file
+    pub config: Arc<BarStoreConfig>,
+}
+
+fn session_ns(date: NaiveDate) -> (u64, u64) {
+    (to_ns(calendar::pre_open(date)), to_ns(calendar::post_close(date)))
+}
+
+fn day_key(date: NaiveDate) -> u32 {
+    date.year() as u32 * 10_000 + date.month() * 100 + date.day()
+}
+
+fn key_day(key: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt((key / 10_000) as i32, key / 100 % 100, key % 100)
+}
+
+fn to_ohlcv(bar: &Bar) -> Ohlcv {
+    (bar.open, bar.high, bar.low, bar.close, bar.volume, bar.trades)
+}
+
+fn to_bar(symbol: &str, start_ns: u64, (open, high, low, close, volume, trades): Ohlcv) -> Bar {
+    Bar { symbol: symbol.to_string(), start_ns, open, high, low, close, volume, trades }
+}
+
+fn to_daily(daily: &DailyBar) -> Daily {
+    let (open, high, low, close, volume, trades) = to_ohlcv(&daily.bar);
+    (open, high, low, close, volume, trades, daily.extended_volume)
+}
+
+fn to_daily_bar(symbol: &str, date: NaiveDate, (open, high, low, close, volume, trades, extended_volume): Daily) -> DailyBar {
+    DailyBar { bar: to_bar(symbol, session_ns(date).0, (open, high, low, close, volume, trades)), extended_volume }
+}
+
+// One bar starting at `start_ns` from `bars`, oldest first
+fn roll_up(symbol: &str, start_ns: u64, bars: &[Bar]) -> Option<Bar> {
+    let (first, last) = (bars.first()?, bars.last()?);
+    Some(Bar {
+        symbol: symbol.to_string(),
+        start_ns,
+        open: first.open,
+        high: bars.iter().map(|b| b.high).fold(f64::MIN, f64::max),
+        low: bars.iter().map(|b| b.low).fold(f64::MAX, f64::min),
+        close: last.close,
+        volume: bars.iter().map(|b| b.volume).sum(),
+        trades: bars.iter().map(|b| b.trades).sum(),
+    })
+}
+
+// The daily bar of `date` from its bars (oldest first, each `width_ns` wide): the
+// ones overlapping the regular session, so an hourly 09:00 bar counts as regular.
+// A session without regular-hours prints falls back to all of them.
+fn daily_bar(symbol: &str, date: NaiveDate, bars: &[Bar], width_ns: u64) -> Option<DailyBar> {
+    let (open_ns, close_ns) = (to_ns(calendar::regular_open(date)), to_ns(calendar::regular_close(date)));
+    let regular: Vec<Bar> = bars.iter().filter(|b| b.start_ns + width_ns > open_ns && b.start_ns < close_ns).cloned().collect();
+    let bar = roll_up(symbol, session_ns(date).0, if regular.is_empty() { bars } else { &regular })?;
+    let total: u64 = bars.iter().map(|b| b.volume).sum();
+    Some(DailyBar { extended_volume: total - bar.volume, bar })
+}
+
+fn read_minutes(table: &impl ReadableTable<(&'static str, u64), Ohlcv>, symbol: &str, start_ns: u64, end_ns: u64) -> Result<Vec<Bar>, StoreError> {
+    let mut bars = Vec::new();
+    for row in table.range((symbol, start_ns)..(symbol, end_ns))? {
+        let (key, value) = row?;
+        bars.push(to_bar(symbol, key.value().1, value.value()));
+    }
+    Ok(bars)
+}
+
+// Give a complete session its daily bar. Sessions past the minute retention keep
+// only that; past the daily retention nothing is kept.
+fn close_session(
+    minutes: &mut Table<(&'static str, u64), Ohlcv>,
+    daily: &mut Table<(&'static str, u32), Daily>,
+    days: &mut Table<(&'static str, u32), u8>,
+    symbol: &str,
+    date: NaiveDate,
+    (minute_cutoff, daily_cutoff): (NaiveDate, NaiveDate),
+) -> Result<(), StoreError> {
+    let (start_ns, end_ns) = session_ns(date);
+    let key = (symbol, day_key(date));
+    if date < daily_cutoff {
+        minutes.retain_in((symbol, start_ns)..(symbol, end_ns), |_, _| false)?;
+        days.remove(key)?;
+        return Ok(());
+    }
+    if let Some(bar) = daily_bar(symbol, date, &read_minutes(minutes, symbol, start_ns, end_ns)?, BAR_NS) {
+        daily.insert(key, to_daily(&bar))?;
+    }
+    if date < minute_cutoff {
+        minutes.retain_in((symbol, start_ns)..(symbol, end_ns), |_, _| false)?;
+        days.insert(key, DAILY_FINAL)?;
+    } else {
+        days.insert(key, MINUTES_COMPLETE | DAILY_FINAL)?;
+    }
+    Ok(())
+}
+
+impl BarStore {
+    pub fn from_env() -> Option<Self> {
+        let config = BarStoreConfig::from_env()?;
+        match Self::open(config) {
+            Ok(store) => {
+                info!("Storing bars in {}", store.config.dir.join(DB_FILE).display());
+                Some(store)
+            }
+            Err(e) => {
+                error!("Bar store disabled: {}", e);
+                None
+            }
+        }
+    }
+
+    fn open(config: BarStoreConfig) -> Result<Self, StoreError> {
+        std::fs::create_dir_all(&config.dir).map_err(redb::Error::Io)?;
+        let db = Database::create(config.dir.join(DB_FILE))?;
+        let tx = db.begin_write()?;
+        tx.open_table(MINUTES)?;
+        tx.open_table(DAILY)?;
+        tx.open_table(DAYS)?;
+        if tx.delete_table(LEGACY_DAILY)? {
+            info!("Dropped the extended-session daily bars; sessions are rolled up again on request");
+        }
+        tx.commit()?;
+        Ok(BarStore { db: Arc::new(RwLock::new(db)), config: Arc::new(config) })
+    }
+
+    fn db(&self) -> RwLockReadGuard<'_, Database> {
+        self.db.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Sessions before these dates have lost their minutes / everything
+    fn cutoffs(&self) -> (NaiveDate, NaiveDate) {
+        let today = calendar::exchange_date(current_time_ns());
+        (
+            today - chrono::Duration::days(self.config.minute_days as i64),
+            today - chrono::Duration::days(self.config.daily_days as i64),
+        )
+    }
+
+    async fn blocking<T: Send + 'static>(&self, f: impl FnOnce(&BarStore) -> Result<T, StoreError> + Send + 'static) -> Result<T, StoreError> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || f(&store)).await?
+    }
+
+    // Store minute bars; `complete` lists the sessions they (with what is already
+    // stored) cover in full, which are closed into their daily bar
+    fn write_minutes(&self, symbol: &str, bars: &[Bar], complete: &[NaiveDate]) -> Result<(), StoreError> {
+        let cutoffs = self.cutoffs();
+        let db = self.db();
+        let tx = db.begin_write()?;
+        {
+            let mut minutes = tx.open_table(MINUTES)?;
+            let mut daily = tx.open_table(DAILY)?;
+            let mut days = tx.open_table(DAYS)?;
+            for bar in bars {
+                minutes.insert((symbol, bar.start_ns), to_ohlcv(bar))?;
+            }
+            for &date in complete {
+                close_session(&mut minutes, &mut daily, &mut days, symbol, date, cutoffs)?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Store daily bars for `dates`; a date without a bar didn't trade
+    fn write_daily(&self, symbol: &str, bars: &[(NaiveDate, DailyBar)], dates: &[NaiveDate]) -> Result<(), StoreError> {
+        let (_, daily_cutoff) = self.cutoffs();
+        let db = self.db();
+        let tx = db.begin_write()?;
+        {
+            let mut daily = tx.open_table(DAILY)?;
+            let mut days = tx.open_table(DAYS)?;
+            for (date, bar) in bars.iter().filter(|(d, _)| *d >= daily_cutoff) {
+                daily.insert((symbol, day_key(*date)), to_daily(bar))?;
+            }
+            for &date in dates.iter().filter(|d| **d >= daily_cutoff) {
+                let flags = days.get((symbol, day_key(date)))?.map_or(0, |f| f.value());
+                days.insert((symbol, day_key(date)), flags | DAILY_FINAL)?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn read_daily(&self, symbol: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<(NaiveDate, DailyBar)>, StoreError> {
+        let db = self.db();
+        let table = db.begin_read()?.open_table(DAILY)?;
+        let mut bars = Vec::new();
+        for row in table.range((symbol, day_key(from))..=(symbol, day_key(to)))? {
+            let (key, value) = row?;
+            if let Some(date) = key_day(key.value().1) {
+                bars.push((date, to_daily_bar(symbol, date, value.value())));
+            }
+        }
+        Ok(bars)
+    }
+
+    fn read_flags(&self, symbol: &str, from: NaiveDate, to: NaiveDate) -> Result<HashMap<NaiveDate, u8>, StoreError> {
+        let db = self.db();
+        let table = db.begin_read()?.open_table(DAYS)?;
+        let mut flags = HashMap::new();
+        for row in table.range((symbol, day_key(from))..=(symbol, day_key(to)))? {
+            let (key, value) = row?;
+            if let Some(date) = key_day(key.value().1) {
+                flags.insert(date, value.value());
+            }
+        }
+        Ok(flags)
+    }
+
+    fn compact_now(&self) -> Result<Compaction, StoreError> {
+        let path = self.config.dir.join(DB_FILE);
+        let size = || std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let mut stats = Compaction { bytes_before: size(), ..Default::default() };
+        let cutoffs = self.cutoffs();
+        let (minute_cutoff, daily_cutoff) = cutoffs;
+        {
+            let db = self.db();
+            let tx = db.begin_write()?;
+            {
+                let mut minutes = tx.open_table(MINUTES)?;
+                let mut daily = tx.open_table(DAILY)?;
+                let mut days = tx.open_table(DAYS)?;
+                let mut expired: Vec<(String, NaiveDate)> = Vec::new();
+                for row in days.range::<(&str, u32)>(..)? {
+                    let (key, flags) = row?;
+                    let (symbol, date) = key.value();
+                    if flags.value() & MINUTES_COMPLETE != 0 && key_day(date).is_some_and(|d| d < minute_cutoff) {
+                        expired.extend(key_day(date).map(|d| (symbol.to_string(), d)));
+                    }
+                }
+                for (symbol, date) in &expired {
+                    close_session(&mut minutes, &mut daily, &mut days, symbol, *date, cutoffs)?;
+                }
+                stats.rolled_sessions = expired.len();
+
+                // Partial sessions past the retention go too; a request fetches them again
+                let (minute_cutoff_ns, _) = session_ns(minute_cutoff);
+                let before = minutes.len()?;
+                minutes.retain(|(_, start_ns), _| start_ns >= minute_cutoff_ns)?;
+                stats.dropped_minutes = before - minutes.len()?;
+                let before = days.len()?;
+                daily.retain(|(_, date), _| date >= day_key(daily_cutoff))?;
+                days.retain(|(_, date), _| date >= day_key(daily_cutoff))?;
+                stats.dropped_days = before - days.len()?;
+            }
+            tx.commit()?;
+        }
+        self.db.write().unwrap_or_else(|e| e.into_inner()).compact()?;
+        stats.bytes_after = size();
+        Ok(stats)
+    }
+
+    pub async fn put_minutes(&self, symbol: &str, bars: Vec<Bar>, complete: Vec<NaiveDate>) -> Result<(), StoreError> {
+        let symbol = symbol.to_string();
+        self.blocking(move |s| s.write_minutes(&symbol, &bars, &complete)).await
+    }
+
+    pub async fn put_daily(&self, symbol: &str, bars: Vec<(NaiveDate, DailyBar)>, dates: Vec<NaiveDate>) -> Result<(), StoreError> {
+        let symbol = symbol.to_string();
+        self.blocking(move |s| s.write_daily(&symbol, &bars, &dates)).await
+    }
+
+    pub async fn minutes(&self, symbol: &str, start_ns: u64, end_ns: u64) -> Result<Vec<Bar>, StoreError> {
+        let symbol = symbol.to_string();
+        self.blocking(move |s| {
+            let db = s.db();
+            let table = db.begin_read()?.open_table(MINUTES)?;
+            read_minutes(&table, &symbol, start_ns, end_ns)
+        }).await
+    }
+
+    pub async fn daily(&self, symbol: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<(NaiveDate, DailyBar)>, StoreError> {
+        let symbol = symbol.to_string();
+        self.blocking(move |s| s.read_daily(&symbol, from, to)).await
+    }
+
+    pub async fn flags(&self, symbol: &str, from: NaiveDate, to: NaiveDate) -> Result<HashMap<NaiveDate, u8>, StoreError> {
+        let symbol = symbol.to_string();
+        self.blocking(move |s| s.read_flags(&symbol, from, to)).await
+    }
+
+    pub async fn compact(&self) -> Result<Compaction, StoreError> {
+        self.blocking(|s| s.compact_now()).await
+    }
+}
+
+// Whether the live feed saw all of `date` for the symbol: subscribed before the
+// pre-market open, or backfilled that day, and every outage recovered
+async fn live_covered(state: &AppState, symbol: &str, date: NaiveDate, first_live_ns: Option<u64>, backfilled: bool) -> bool {
+    let (open_ns, _) = session_ns(date);
+    let from_open = first_live_ns.is_some_and(|ts| ts <= open_ns || (backfilled && calendar::exchange_date(ts) == date));
+    from_open && state.gaps.read().await.for_symbol(symbol, date).iter()
+        .all(|o| o.status == "recovered" && !o.failed_symbols.iter().any(|s| s == symbol))
+}
+
+// Write the closed live bars that changed since the last flush, completing the
+// sessions the feed covered in full once they have ended
+async fn flush_live(state: &AppState, store: &BarStore, flushed: &mut HashMap<String, HashMap<u64, Bar>>, settled: &mut HashSet<(String, NaiveDate)>) {
+    let now = current_time_ns();
+    let snapshot: Vec<(String, Vec<Bar>, Option<u64>, bool)> = state.bars.read().await.iter()
+        .map(|(symbol, series)| (symbol.clone(), series.closed.iter().cloned().collect(), series.first_live_ns, series.backfilled))
+        .collect();
+    for (symbol, closed, first_live_ns, backfilled) in snapshot {
+        let written = flushed.entry(symbol.clone()).or_default();
+        let changed: Vec<Bar> = closed.iter().filter(|b| written.get(&b.start_ns) != Some(*b)).cloned().collect();
+        let mut ended: Vec<NaiveDate> = closed.iter().map(|b| calendar::exchange_date(b.start_ns)).collect();
+        ended.dedup();
+        ended.retain(|d| calendar::is_trading_day(*d) && session_ns(*d).1 + SETTLE_NS <= now && !settled.contains(&(symbol.clone(), *d)));
+        let mut complete = Vec::new();
+        for &date in &ended {
+            if live_covered(state, &symbol, date, first_live_ns, backfilled).await {
+                complete.push(date);
+            }
+        }
+        if changed.is_empty() && complete.is_empty() {
+            settled.extend(ended.into_iter().map(|d| (symbol.clone(), d)));
+            continue;
+        }
+        let count = changed.len();
+        match store.put_minutes(&symbol, changed, complete.clone()).await {
+            Ok(()) => {
+                *written = closed.into_iter().map(|b| (b.start_ns, b)).collect();
+                settled.extend(ended.into_iter().map(|d| (symbol.clone(), d)));
+                for date in complete {
+                    info!("Stored the complete {} session of {} from the live feed", date, symbol);
+                }
+            }
+            Err(e) => warn!("Cannot store {} live bars of {}: {}", count, symbol, e),
+        }
+    }
+}
+
+pub async fn run(state: AppState) {
+    let Some(store) = state.barstore.clone() else { return };
+    let mut flush = tokio::time::interval(store.config.flush);
+    let mut compact = tokio::time::interval(store.config.compact);
+    let mut flushed: HashMap<String, HashMap<u64, Bar>> = HashMap::new();
+    let mut settled: HashSet<(String, NaiveDate)> = HashSet::new();
+    loop {
+        tokio::select! {
+            _ = flush.tick() => flush_live(&state, &store, &mut flushed, &mut settled).await,
+            _ = compact.tick() => match store.compact().await {
+                Ok(c) => info!(
+                    "Bar store compacted: {} sessions rolled to daily, {} minutes and {} days dropped, {} -> {} bytes",
+                    c.rolled_sessions, c.dropped_minutes, c.dropped_days, c.bytes_before, c.bytes_after
+                ),
+                Err(e) => warn!("Bar store compaction failed: {}", e),
+            },
+        }
+    }
+}
+
+// Runs of consecutive sessions in `missing`; each is one upstream request
+fn runs(sessions: &[NaiveDate], missing: &[NaiveDate]) -> Vec<Vec<NaiveDate>> {
+    let mut runs: Vec<Vec<NaiveDate>> = Vec::new();
+    let mut extend = false;
+    for date in sessions {
+        let is_missing = missing.contains(date);
+        match runs.last_mut() {
+            Some(run) if is_missing && extend => run.push(*date),
+            _ if is_missing => runs.push(vec![*date]),
+            _ => {}
+        }
+        extend = is_missing;
+    }
+    runs
+}
+
+// Today's session so far: the live series when it covers the session, otherwise
+// upstream (stored, but not as complete). The flag is true when fetched.
+async fn today_minutes(state: &AppState, store: &BarStore, symbol: &str, today: NaiveDate, now: u64) -> Result<(Vec<Bar>, bool), StoreError> {
+    let (open_ns, _) = session_ns(today);
+    let live = state.bars.read().await.get(symbol)
+        .filter(|s| s.date == Some(today) && (s.backfilled || s.first_live_ns.is_some_and(|ts| ts <= open_ns)))
+        .map(|s| s.all());
+    if let Some(bars) = live {
+        return Ok((bars.into_iter().filter(|b| b.start_ns >= open_ns).collect(), false));
+    }
+    let end_ns = bars::minute_start(now);
+    if end_ns <= open_ns {
+        return Ok((Vec::new(), false));
+    }
+    let dataset = sessions::dataset_for(state, symbol).await;
+    let bars = hist::minute_bars(&dataset, symbol, calendar::pre_open(today), to_dt(end_ns)).await?;
+    store.put_minutes(symbol, bars.clone(), Vec::new()).await?;
+    Ok((bars, true))
+}
+
+// Minute bars of `sessions`, fetching the ones not stored in full. Returns the
+// bars and the sessions that came from upstream.
+async fn minute_range(state: &AppState, store: &BarStore, symbol: &str, sessions: &[NaiveDate], today: NaiveDate, now: u64) -> Result<(Vec<Bar>, Vec<NaiveDate>), StoreError> {
+    let (Some(&first), Some(&last)) = (sessions.first(), sessions.last()) else { return Ok((Vec::new(), Vec::new())) };
+    let flags = store.flags(symbol, first, last).await?;
+    let mut fetched: Vec<NaiveDate> = sessions.iter().copied()
+        .filter(|d| *d < today && flags.get(d).is_none_or(|f| f & MINUTES_COMPLETE == 0))
+        .collect();
+    let mut bars: BTreeMap<u64, Bar> = store.minutes(symbol, session_ns(first).0, session_ns(last).1).await?
+        .into_iter()
+        .filter(|b| !fetched.contains(&calendar::exchange_date(b.start_ns)))
+        .map(|b| (b.start_ns, b))
+        .collect();
+    for run in runs(sessions, &fetched) {
+        let (start, end) = (calendar::pre_open(run[0]), calendar::post_close(run[run.len() - 1]));
+        let history = hist::minute_bars(&sessions::dataset_for(state, symbol).await, symbol, start, end).await?;
+        info!("Fetched {} minute bars of {} for {} sessions from {}", history.len(), symbol, run.len(), run[0]);
+        store.put_minutes(symbol, history.clone(), run).await?;
+        bars.extend(history.into_iter().map(|b| (b.start_ns, b)));
+    }
+    if sessions.contains(&today) {
+        let (today_bars, upstream) = today_minutes(state, store, symbol, today, now).await?;
+        bars.extend(today_bars.into_iter().map(|b| (b.start_ns, b)));
+        if upstream {
+            fetched.push(today);
+        }
+    }
+    Ok((bars.into_values().collect(), fetched))
+}
+
+// Daily bars of `sessions`: stored, rolled up from stored minutes, or from upstream
+// hourly bars. Today's bar is rolled up from the session so far.
+async fn daily_range(state: &AppState, store: &BarStore, symbol: &str, sessions: &[NaiveDate], today: NaiveDate, now: u64) -> Result<(Vec<DailyBar>, Vec<NaiveDate>), StoreError> {
+    let (Some(&first), Some(&last)) = (sessions.first(), sessions.last()) else { return Ok((Vec::new(), Vec::new())) };
+    let flags = store.flags(symbol, first, last).await?;
+    let past = sessions.iter().copied().filter(|d| *d < today);
+    let rollable: Vec<NaiveDate> = past.clone()
+        .filter(|d| flags.get(d).is_some_and(|f| f & MINUTES_COMPLETE != 0 && f & DAILY_FINAL == 0))
+        .collect();
+    if !rollable.is_empty() {
+        store.put_minutes(symbol, Vec::new(), rollable).await?;
+    }
+    let mut fetched: Vec<NaiveDate> = past.filter(|d| flags.get(d).is_none_or(|f| f & (MINUTES_COMPLETE | DAILY_FINAL) == 0)).collect();
+    let mut bars: BTreeMap<NaiveDate, DailyBar> = store.daily(symbol, first, last).await?.into_iter().collect();
+    for run in runs(sessions, &fetched) {
+        let (start, end) = (calendar::pre_open(run[0]), calendar::post_close(run[run.len() - 1]));
+        let hourly = hist::ohlcv(&sessions::dataset_for(state, symbol).await, symbol, "ohlcv-1h", start, end).await?;
+        let daily: Vec<(NaiveDate, DailyBar)> = run.iter().filter_map(|date| {
+            let (open_ns, close_ns) = session_ns(*date);
+            let day: Vec<Bar> = hourly.iter().filter(|b| b.start_ns >= open_ns && b.start_ns < close_ns).cloned().collect();
+            daily_bar(symbol, *date, &day, 60 * BAR_NS).map(|bar| (*date, bar))
+        }).collect();
+        info!("Fetched {} daily bars of {} for {} sessions from {}", daily.len(), symbol, run.len(), run[0]);
+        store.put_daily(symbol, daily.clone(), run).await?;
+        bars.extend(daily);
+    }
+    if sessions.contains(&today) {
+        let (minutes, upstream) = today_minutes(state, store, symbol, today, now).await?;
+        if let Some(bar) = daily_bar(symbol, today, &minutes, BAR_NS) {
+            bars.insert(today, bar);
+        }
+        if upstream {
+            fetched.push(today);
+        }
+    }
+    Ok((bars.into_values().collect(), fetched))
+}
+
+// Minute bars (oldest first) in buckets of `minutes`
+fn resample(symbol: &str, bars: Vec<Bar>, minutes: u64) -> Vec<Bar> {
+    if minutes == 1 {
+        return bars;
+    }
+    let width = minutes * BAR_NS;
+    bars.chunk_by(|a, b| a.start_ns / width == b.start_ns / width)
+        .filter_map(|chunk| roll_up(symbol, chunk[0].start_ns - chunk[0].start_ns % width, chunk))
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistBarsQuery {
+    symbol: String,
+    from: Option<String>,
+    to: Option<String>,
+    resolution: Option<String>,
+}
+
+// GET /api/hist/bars?symbol=AAPL&from=2026-09-01&to=2026-10-16&resolution=5m
+// Dates are exchange dates (default today); resolution is 1m (default), 5m, 15m,
+// 30m, 1h or 1d. A daily bar's start_ns is its session's pre-market open; its
+// prices and volume are the regular session's, with extended_volume alongside.
+pub async fn get_hist_bars(Query(q): Query<HistBarsQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let error = |status: StatusCode, error: String| (status, Json(serde_json::json!({"error": error})));
+    let Some(store) = state.barstore.clone() else {
+        return error(StatusCode::SERVICE_UNAVAILABLE, "bar store disabled (set BARSTORE_DIR)".to_string());
+    };
+    let symbol = norm_symbol(&q.symbol);
+    if symbol.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "symbol is required".to_string());
+    }
+    let resolution = q.resolution.as_deref().unwrap_or("1m").trim().to_lowercase();
+    let minutes = match resolution.as_str() {
+        "1m" => Some(1),
+        "5m" => Some(5),
+        "15m" => Some(15),
+        "30m" => Some(30),
+        "1h" => Some(60),
+        "1d" => None,
+        _ => return error(StatusCode::BAD_REQUEST, "resolution must be one of 1m, 5m, 15m, 30m, 1h, 1d".to_string()),
+    };
+    let now = current_time_ns();
+    let today = calendar::exchange_date(now);
+    let parse = |value: Option<&str>, default: NaiveDate| match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(v) => NaiveDate::parse_from_str(v, "%Y-%m-%d").map_err(|_| format!("invalid date {:?} (YYYY-MM-DD)", v)),
+        None => Ok(default),
+    };
+    let from = match parse(q.from.as_deref(), today) {
+        Ok(d) => d,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+    let to = match parse(q.to.as_deref(), from) {
+        Ok(d) => d,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+    if to < from {
+        return error(StatusCode::BAD_REQUEST, "to is before from".to_string());
+    }
+    if to > today {
+        return error(StatusCode::BAD_REQUEST, "to is in the future".to_string());
+    }
+    let max_days = if minutes.is_some() { MAX_MINUTE_DAYS } else { MAX_DAILY_DAYS };
+    if (to - from).num_days() >= max_days {
+        return error(StatusCode::BAD_REQUEST, format!("{} bars span at most {} days per request", resolution, max_days));
+    }
+
+    let sessions: Vec<NaiveDate> = from.iter_days().take_while(|d| *d <= to).filter(|d| calendar::is_trading_day(*d)).collect();
+    let result = match minutes {
+        Some(minutes) => minute_range(&state, &store, &symbol, &sessions, today, now).await
+            .map(|(bars, fetched)| (serde_json::json!(resample(&symbol, bars, minutes)), fetched)),
+        None => daily_range(&state, &store, &symbol, &sessions, today, now).await
+            .map(|(bars, fetched)| (serde_json::json!(bars), fetched)),
+    };
+    match result {
+        Ok((bars, fetched)) => (StatusCode::OK, Json(serde_json::json!({
+            "symbol": symbol,
+            "resolution": resolution,
+            "from": from.to_string(),
+            "to": to.to_string(),
+            "sessions": sessions.len(),
+            "fetched": fetched.iter().map(|d| d.to_string()).collect::<Vec<_>>(),
+            "count": bars.as_array().map_or(0, Vec::len),
+            "bars": bars
+        }))),
+        Err(StoreError::Hist(hist::HistError::NoApiKey)) => {
+            error(StatusCode::SERVICE_UNAVAILABLE, "DATABENTO_API_KEY not configured and the range is not stored locally".to_string())
+        }
+        Err(e @ StoreError::Hist(_)) => {
+            warn!("Bar fill for {} failed: {}", symbol, e);
+            error(StatusCode::BAD_GATEWAY, e.to_string())
+        }
+        Err(e) => {
+            error!("Bar store read for {} failed: {}", symbol, e);
+            error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+
+    fn bar(start: DateTime<Utc>, close: f64, volume: u64) -> Bar {
+        Bar { symbol: "AAPL".into(), start_ns: to_ns(start), open: close, high: close + 1.0, low: close - 1.0, close, volume, trades: 1 }
+    }
+
+    #[test]
+    fn daily_bar_keeps_the_regular_session() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let (open, close) = (calendar::regular_open(date), calendar::regular_close(date));
+        let minute = chrono::Duration::minutes(1);
+        let minutes = vec![
+            bar(calendar::pre_open(date), 90.0, 10),
+            bar(open, 100.0, 50),
+            bar(close - minute, 105.0, 70),
+            bar(close, 120.0, 30),
+        ];
+        let daily = daily_bar("AAPL", date, &minutes, BAR_NS).unwrap();
+        assert_eq!((daily.bar.open, daily.bar.close, daily.bar.high, daily.bar.low), (100.0, 105.0, 106.0, 99.0));
+        assert_eq!((daily.bar.volume, daily.extended_volume), (120, 40));
+        assert_eq!(daily.bar.start_ns, session_ns(date).0);
+
+        // the 09:00 hour overlaps the open; the 16:00 one is post-market
+        let hour = chrono::Duration::hours(1);
+        let hourly = vec![bar(open - minute * 30, 98.0, 5), bar(close - hour, 104.0, 8), bar(close, 110.0, 2)];
+        let daily = daily_bar("AAPL", date, &hourly, 60 * BAR_NS).unwrap();
+        assert_eq!((daily.bar.open, daily.bar.close, daily.bar.volume, daily.extended_volume), (98.0, 104.0, 13, 2));
+    }
+
+    #[test]
+    fn daily_bar_closes_early_and_falls_back_without_regular_prints() {
+        let date = NaiveDate::from_ymd_opt(2026, 11, 27).unwrap();
+        let close = calendar::regular_close(date);
+        let minutes = vec![bar(close - chrono::Duration::minutes(1), 50.0, 5), bar(close + chrono::Duration::hours(1), 60.0, 3)];
+        let daily = daily_bar("AAPL", date, &minutes, BAR_NS).unwrap();
+        assert_eq!((daily.bar.close, daily.bar.volume, daily.extended_volume), (50.0, 5, 3));
+
+        let premarket = vec![bar(calendar::pre_open(date), 40.0, 4)];
+        let daily = daily_bar("AAPL", date, &premarket, BAR_NS).unwrap();
+        assert_eq!((daily.bar.close, daily.bar.volume, daily.extended_volume), (40.0, 4, 0));
+        assert!(daily_bar("AAPL", date, &[], BAR_NS).is_none());
+    }
+}
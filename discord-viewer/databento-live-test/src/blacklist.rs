@@ -0,0 +1,224 @@
+// In-memory copy of `ticker_blacklist` and `blacklist_patterns`. Refreshed on an
+// interval and whenever `blacklist_updates` fires (create-blacklist-trigger.sql).
+//
+// A blacklisted ticker is refused unless the caller supplies message context that
+// clears its rules: not permanent, cashtag present when required, confidence above
+// the entry's minimum, no `excluded` pattern matches and some `required` one does.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::{current_time_ns, db::Database, env, stream::StreamEvent, AppState};
+
+#[derive(Clone, Debug)]
+pub struct BlacklistEntry {
+    pub reason: Option<String>,
+    pub category: Option<String>,
+    pub min_confidence: Option<f64>,
+    pub requires_cashtag: bool,
+    pub is_permanent: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Pattern {
+    required: bool, // 'required' vs 'excluded'
+    regex: Regex,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Blocked {
+    pub symbol: String,
+    pub reason: String,
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Blacklist {
+    entries: HashMap<String, BlacklistEntry>,
+    patterns: HashMap<String, Vec<Pattern>>,
+    // Symbols blacklisted after they were subscribed; the session keeps streaming
+    // them but their trades are dropped
+    muted: HashSet<String>,
+    loaded_at_ns: u64,
+}
+
+// Patterns are regexes; anything that doesn't compile is matched literally
+fn compile(pattern: &str) -> Option<Regex> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+        .or_else(|_| RegexBuilder::new(&regex::escape(pattern)).case_insensitive(true).build())
+        .ok()
+}
+
+impl Blacklist {
+    pub async fn load(db: &Database) -> Result<Self> {
+        // v2 schema carries the disambiguation rules; the original table only has ticker/reason
+        let rows = match db.query(
+            "SELECT ticker, reason, category, min_confidence_required::float8,
+                    COALESCE(requires_cashtag, false), COALESCE(is_permanent, false)
+             FROM ticker_blacklist",
+            &[],
+        ).await {
+            Ok(rows) => rows,
+            Err(_) => db.query(
+                "SELECT ticker, reason, NULL::text, NULL::float8, false, false FROM ticker_blacklist",
+                &[],
+            ).await?,
+        };
+        let entries: HashMap<String, BlacklistEntry> = rows.iter().map(|r| {
+            (r.get::<_, String>(0).trim().to_uppercase(), BlacklistEntry {
+                reason: r.get(1),
+                category: r.get(2),
+                min_confidence: r.get(3),
+                requires_cashtag: r.get(4),
+                is_permanent: r.get(5),
+            })
+        }).collect();
+
+        let mut patterns: HashMap<String, Vec<Pattern>> = HashMap::new();
+        match db.query("SELECT ticker, pattern_type, pattern FROM blacklist_patterns", &[]).await {
+            Ok(rows) => {
+                for r in rows {
+                    let ticker: String = r.get(0);
+                    let kind: String = r.get(1);
+                    let pattern: String = r.get(2);
+                    let Some(regex) = compile(&pattern) else { continue };
+                    patterns.entry(ticker.trim().to_uppercase()).or_default().push(Pattern {
+                        required: kind.eq_ignore_ascii_case("required"),
+                        regex,
+                    });
+                }
+            }
+            Err(e) => warn!("blacklist_patterns unavailable: {}", e),
+        }
+
+        Ok(Blacklist { entries, patterns, muted: HashSet::new(), loaded_at_ns: current_time_ns() })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_muted(&self, symbol: &str) -> bool {
+        self.muted.contains(symbol)
+    }
+
+    pub fn unmute(&mut self, symbols: &[String]) {
+        for symbol in symbols {
+            self.muted.remove(symbol);
+        }
+    }
+
+    // None when `symbol` may be streamed. `context` is the message text the symbol came from.
+    pub fn check(&self, symbol: &str, context: Option<&str>, confidence: Option<f64>) -> Option<Blocked> {
+        let entry = self.entries.get(symbol)?;
+        let blocked = |reason: String| Some(Blocked {
+            symbol: symbol.to_string(),
+            reason,
+            category: entry.category.clone(),
+        });
+        let listed = entry.reason.clone().unwrap_or_else(|| "blacklisted".to_string());
+
+        if entry.is_permanent {
+            return blocked(listed);
+        }
+        let Some(text) = context else { return blocked(listed) };
+        if entry.requires_cashtag && !text.to_uppercase().contains(&format!("${}", symbol)) {
+            return blocked(format!("{} (cashtag required)", listed));
+        }
+        if let (Some(min), Some(c)) = (entry.min_confidence, confidence) {
+            if c < min {
+                return blocked(format!("{} (confidence {:.2} below {:.2})", listed, c, min));
+            }
+        }
+        let patterns = self.patterns.get(symbol).map(Vec::as_slice).unwrap_or_default();
+        if let Some(p) = patterns.iter().find(|p| !p.required && p.regex.is_match(text)) {
+            return blocked(format!("{} (matched excluded pattern {})", listed, p.regex.as_str()));
+        }
+        let mut required = patterns.iter().filter(|p| p.required).peekable();
+        if required.peek().is_none() {
+            return blocked(listed);
+        }
+        if !required.any(|p| p.regex.is_match(text)) {
+            return blocked(format!("{} (no required pattern matched)", listed));
+        }
+        None
+    }
+
+    // Split symbols into (allowed, blocked)
+    pub fn partition(&self, symbols: Vec<String>, context: Option<&str>) -> (Vec<String>, Vec<Blocked>) {
+        let mut allowed = Vec::new();
+        let mut blocked = Vec::new();
+        for symbol in symbols {
+            match self.check(&symbol, context, None) {
+                Some(b) => blocked.push(b),
+                None => allowed.push(symbol),
+            }
+        }
+        (allowed, blocked)
+    }
+}
+
+// Reload from Postgres, keeping the previous copy if the query fails. Subscribed
+// symbols that became blacklisted are dropped from prices and muted.
+pub async fn refresh(state: &AppState) {
+    let Some(db) = &state.db else { return };
+    let mut list = match Blacklist::load(db).await {
+        Ok(list) => list,
+        Err(e) => {
+            error!("Failed to load blacklist: {}", e);
+            return;
+        }
+    };
+
+    let dropped: Vec<String> = {
+        let mut current = state.blacklist.write().await;
+        list.muted = current.muted.iter().filter(|s| list.entries.contains_key(*s)).cloned().collect();
+        let subscribed = state.subscribed_symbols.read().await;
+        let dropped = subscribed.iter()
+            .filter(|s| !current.entries.contains_key(*s) && list.check(s, None, None).is_some())
+            .cloned()
+            .collect::<Vec<_>>();
+        list.muted.extend(dropped.iter().cloned());
+        info!("Loaded {} blacklisted tickers", list.len());
+        *current = list;
+        dropped
+    };
+
+    if dropped.is_empty() {
+        return;
+    }
+    warn!("Dropping newly blacklisted symbols: {:?}", dropped);
+    let mut subscribed = state.subscribed_symbols.write().await;
+    let mut prices = state.prices.write().await;
+    for symbol in &dropped {
+        subscribed.remove(symbol);
+        prices.remove(symbol);
+        state.events.publish(StreamEvent::Status {
+            symbol: Some(symbol.clone()),
+            dataset: String::new(),
+            status: "blacklisted".to_string(),
+            detail: None,
+        });
+    }
+}
+
+// Periodic refresh every BLACKLIST_REFRESH_SECS (default 300)
+pub async fn refresher(state: AppState) {
+    if state.db.is_none() {
+        return;
+    }
+    let secs = env("BLACKLIST_REFRESH_SECS", 300u64);
+    let mut ticker = tokio::time::interval(tokio::time::Duration::from_secs(secs.max(1)));
+    ticker.tick().await; // loaded at startup
+    loop {
+        ticker.tick().await;
+        let age_ns = current_time_ns().saturating_sub(state.blacklist.read().await.loaded_at_ns);
+        if age_ns >= secs * 1_000_000_000 / 2 {
+            refresh(&state).await;
+        }
+    }
+}
@@ -0,0 +1,271 @@
+// US equity trading calendar in exchange time (America/New_York): NYSE holidays,
+// early closes and the pre / regular / post session boundaries.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::America::{Chicago, New_York};
+use serde::{Deserialize, Serialize};
+
+use crate::to_dt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Session {
+    Pre,     // 04:00-09:30
+    Regular, // 09:30-16:00 (13:00 on early closes)
+    Post,    // 16:00-20:00 (13:00-17:00 on early closes)
+    Closed,
+}
+
+fn hm(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap_or_default()
+}
+
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n).unwrap_or_default()
+}
+
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, 5)
+        .unwrap_or_else(|| nth_weekday(year, month, weekday, 4))
+}
+
+// Anonymous Gregorian algorithm
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap_or_default()
+}
+
+// Saturday holidays move to Friday, Sunday holidays to Monday
+fn observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date - Duration::days(1),
+        Weekday::Sun => date + Duration::days(1),
+        _ => date,
+    }
+}
+
+fn holidays(year: i32) -> Vec<NaiveDate> {
+    let ymd = |m, d| NaiveDate::from_ymd_opt(year, m, d).unwrap_or_default();
+    let mut days = vec![
+        nth_weekday(year, 1, Weekday::Mon, 3),  // Martin Luther King Jr. Day
+        nth_weekday(year, 2, Weekday::Mon, 3),  // Washington's Birthday
+        easter(year) - Duration::days(2),       // Good Friday
+        last_weekday(year, 5, Weekday::Mon),    // Memorial Day
+        observed(ymd(7, 4)),                    // Independence Day
+        nth_weekday(year, 9, Weekday::Mon, 1),  // Labor Day
+        nth_weekday(year, 11, Weekday::Thu, 4), // Thanksgiving
+        observed(ymd(12, 25)),                  // Christmas
+    ];
+    // New Year's Day on a Saturday is not observed on the prior Friday
+    if ymd(1, 1).weekday() != Weekday::Sat {
+        days.push(observed(ymd(1, 1)));
+    }
+    if year >= 2022 {
+        days.push(observed(ymd(6, 19))); // Juneteenth
+    }
+    days
+}
+
+pub fn is_holiday(date: NaiveDate) -> bool {
+    holidays(date.year()).contains(&date)
+}
+
+pub fn is_trading_day(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !is_holiday(date)
+}
+
+// 13:00 closes: July 3rd, the day after Thanksgiving and Christmas Eve
+pub fn is_early_close(date: NaiveDate) -> bool {
+    if !is_trading_day(date) {
+        return false;
+    }
+    let year = date.year();
+    let ymd = |m, d| NaiveDate::from_ymd_opt(year, m, d).unwrap_or_default();
+    date == ymd(7, 3) || date == ymd(12, 24) || date == nth_weekday(year, 11, Weekday::Thu, 4) + Duration::days(1)
+}
+
+pub fn previous_trading_day(date: NaiveDate) -> NaiveDate {
+    let mut d = date - Duration::days(1);
+    while !is_trading_day(d) {
+        d -= Duration::days(1);
+    }
+    d
+}
+
+pub fn next_trading_day(date: NaiveDate) -> NaiveDate {
+    let mut d = date + Duration::days(1);
+    while !is_trading_day(d) {
+        d += Duration::days(1);
+    }
+    d
+}
+
+// Trading day in exchange time, used for daily resets
+pub fn exchange_date(ts_ns: u64) -> NaiveDate {
+    to_dt(ts_ns).with_timezone(&New_York).date_naive()
+}
+
+fn at(date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    New_York
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_default()
+}
+
+pub fn pre_open(date: NaiveDate) -> DateTime<Utc> {
+    at(date, hm(4, 0))
+}
+
+pub fn regular_open(date: NaiveDate) -> DateTime<Utc> {
+    at(date, hm(9, 30))
+}
+
+pub fn regular_close(date: NaiveDate) -> DateTime<Utc> {
+    at(date, if is_early_close(date) { hm(13, 0) } else { hm(16, 0) })
+}
+
+pub fn post_close(date: NaiveDate) -> DateTime<Utc> {
+    at(date, if is_early_close(date) { hm(17, 0) } else { hm(20, 0) })
+}
+
+pub fn session_at(ts: DateTime<Utc>) -> Session {
+    let date = ts.with_timezone(&New_York).date_naive();
+    if !is_trading_day(date) {
+        return Session::Closed;
+    }
+    if ts < pre_open(date) || ts >= post_close(date) {
+        Session::Closed
+    } else if ts < regular_open(date) {
+        Session::Pre
+    } else if ts < regular_close(date) {
+        Session::Regular
+    } else {
+        Session::Post
+    }
+}
+
+pub fn session_at_ns(ts_ns: u64) -> Session {
+    session_at(to_dt(ts_ns))
+}
+
+// First regular-session close after `ts_ns`
+pub fn close_after(ts_ns: u64) -> DateTime<Utc> {
+    let ts = to_dt(ts_ns);
+    let date = exchange_date(ts_ns);
+    if is_trading_day(date) && ts < regular_close(date) {
+        regular_close(date)
+    } else {
+        regular_close(next_trading_day(date))
+    }
+}
+
+// Trading day of the most recent session that had started by `ts`
+pub fn last_session_date(ts: DateTime<Utc>) -> NaiveDate {
+    let date = ts.with_timezone(&New_York).date_naive();
+    if is_trading_day(date) && ts >= pre_open(date) {
+        date
+    } else {
+        previous_trading_day(date)
+    }
+}
+
+// Midnight America/Chicago of the day containing `ts`; the day boundary the SQL uses
+pub fn chicago_day_start(ts: DateTime<Utc>) -> DateTime<Utc> {
+    let midnight = ts.with_timezone(&Chicago).date_naive().and_time(NaiveTime::MIN);
+    Chicago
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or(ts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn nyse_holidays_2026() {
+        let mut days = holidays(2026);
+        days.sort();
+        assert_eq!(days, [
+            ymd(2026, 1, 1),
+            ymd(2026, 1, 19),
+            ymd(2026, 2, 16),
+            ymd(2026, 4, 3),
+            ymd(2026, 5, 25),
+            ymd(2026, 6, 19),
+            ymd(2026, 7, 3), // July 4th is a Saturday
+            ymd(2026, 9, 7),
+            ymd(2026, 11, 26),
+            ymd(2026, 12, 25),
+        ]);
+    }
+
+    #[test]
+    fn weekend_holidays() {
+        // New Year's Day 2022 fell on a Saturday; Friday the 31st traded
+        assert!(is_trading_day(ymd(2021, 12, 31)));
+        // Christmas and Juneteenth 2027 fall on Saturdays
+        assert!(is_holiday(ymd(2027, 12, 24)));
+        assert!(is_holiday(ymd(2027, 6, 18)));
+        assert!(!is_trading_day(ymd(2026, 10, 17)));
+        // No Juneteenth before 2022
+        assert!(is_trading_day(ymd(2021, 6, 18)));
+    }
+
+    #[test]
+    fn early_closes_2026() {
+        let early: Vec<NaiveDate> = (0..365)
+            .map(|d| ymd(2026, 1, 1) + Duration::days(d))
+            .filter(|d| is_early_close(*d))
+            .collect();
+        // July 3rd is the observed holiday, so no half day that week
+        assert_eq!(early, [ymd(2026, 11, 27), ymd(2026, 12, 24)]);
+        assert!(is_early_close(ymd(2025, 7, 3)));
+    }
+
+    #[test]
+    fn half_day_sessions() {
+        let day = ymd(2026, 11, 27);
+        assert_eq!(regular_close(day), utc("2026-11-27T18:00:00Z"));
+        assert_eq!(post_close(day), utc("2026-11-27T22:00:00Z"));
+        assert_eq!(session_at(utc("2026-11-27T17:59:59Z")), Session::Regular);
+        assert_eq!(session_at(utc("2026-11-27T19:00:00Z")), Session::Post);
+        assert_eq!(session_at(utc("2026-11-27T22:30:00Z")), Session::Closed);
+        // A normal day in summer time
+        assert_eq!(regular_close(ymd(2026, 7, 2)), utc("2026-07-02T20:00:00Z"));
+        assert_eq!(session_at(utc("2026-07-02T08:00:00Z")), Session::Pre);
+    }
+
+    #[test]
+    fn trading_day_navigation() {
+        // Good Friday and the weekend are skipped
+        assert_eq!(next_trading_day(ymd(2026, 4, 2)), ymd(2026, 4, 6));
+        assert_eq!(previous_trading_day(ymd(2026, 4, 6)), ymd(2026, 4, 2));
+        // A call after Wednesday's close before Thanksgiving resolves at Friday's half-day close
+        let ts = utc("2026-11-25T22:00:00Z").timestamp_nanos_opt().unwrap() as u64;
+        assert_eq!(close_after(ts), utc("2026-11-27T18:00:00Z"));
+    }
+}
@@ -0,0 +1,401 @@
+// Condition language for alert rules. A condition is a boolean expression over
+// per-symbol fields, e.g.
+//
+//   change% > 20 and volume > 1M
+//   new_hod and after_halt
+//   spread < 1% and not halted
+//
+// Comparisons take >, >=, <, <=, == (or =) and != against a number; numbers may
+// carry a K/M/B multiplier or a trailing % (ignored, fields are already percents).
+// A bare field is true when it is non-zero. `and`/`or`/`not` (or &&, ||, !) combine
+// them, with the usual precedence and parentheses. A comparison on a field with no
+// value yet (no quote, no previous close) is false.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Field {
+    Last,
+    ChangePct,   // vs the previous regular-session close
+    Volume,      // day volume, extended hours included
+    Vwap,
+    VwapDistPct, // last vs VWAP
+    Hod,
+    NewHod,      // this print made a new high of day
+    Halted,
+    AfterHalt,   // trading again after a halt earlier today
+    SinceHaltMin,
+    Bid,
+    Ask,
+    SpreadPct,   // (ask - bid) / mid
+}
+
+impl Field {
+    pub const ALL: [Field; 13] = [
+        Field::Last,
+        Field::ChangePct,
+        Field::Volume,
+        Field::Vwap,
+        Field::VwapDistPct,
+        Field::Hod,
+        Field::NewHod,
+        Field::Halted,
+        Field::AfterHalt,
+        Field::SinceHaltMin,
+        Field::Bid,
+        Field::Ask,
+        Field::SpreadPct,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Field::Last => "last",
+            Field::ChangePct => "change_pct",
+            Field::Volume => "volume",
+            Field::Vwap => "vwap",
+            Field::VwapDistPct => "vwap_dist_pct",
+            Field::Hod => "hod",
+            Field::NewHod => "new_hod",
+            Field::Halted => "halted",
+            Field::AfterHalt => "after_halt",
+            Field::SinceHaltMin => "since_halt_min",
+            Field::Bid => "bid",
+            Field::Ask => "ask",
+            Field::SpreadPct => "spread_pct",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "last" | "price" => Field::Last,
+            "change_pct" | "change%" | "change" | "pct_change" => Field::ChangePct,
+            "volume" | "vol" => Field::Volume,
+            "vwap" => Field::Vwap,
+            "vwap_dist_pct" | "vwap_dist" | "vwap%" => Field::VwapDistPct,
+            "hod" | "high" => Field::Hod,
+            "new_hod" => Field::NewHod,
+            "halted" => Field::Halted,
+            "after_halt" => Field::AfterHalt,
+            "since_halt_min" | "mins_since_halt" => Field::SinceHaltMin,
+            "bid" => Field::Bid,
+            "ask" => Field::Ask,
+            "spread_pct" | "spread" | "spread%" => Field::SpreadPct,
+            _ => return None,
+        })
+    }
+}
+
+// Field values for one symbol at one moment; booleans are 1.0 / 0.0
+pub trait Fields {
+    fn get(&self, field: Field) -> Option<f64>;
+
+    // Every field that currently has a value, by name
+    fn values(&self) -> BTreeMap<&'static str, f64> {
+        Field::ALL.iter().filter_map(|f| self.get(*f).map(|v| (f.as_str(), v))).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+impl Op {
+    fn apply(&self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            Op::Gt => lhs > rhs,
+            Op::Ge => lhs >= rhs,
+            Op::Lt => lhs < rhs,
+            Op::Le => lhs <= rhs,
+            Op::Eq => (lhs - rhs).abs() < 1e-9,
+            Op::Ne => (lhs - rhs).abs() >= 1e-9,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp(Field, Op, f64),
+    Flag(Field),
+}
+
+impl Expr {
+    pub fn eval(&self, fields: &impl Fields) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(fields) && b.eval(fields),
+            Expr::Or(a, b) => a.eval(fields) || b.eval(fields),
+            Expr::Not(e) => !e.eval(fields),
+            Expr::Cmp(field, op, value) => fields.get(*field).is_some_and(|v| op.apply(v, *value)),
+            Expr::Flag(field) => fields.get(*field).is_some_and(|v| v != 0.0),
+        }
+    }
+
+    pub fn uses(&self, field: Field) -> bool {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => a.uses(field) || b.uses(field),
+            Expr::Not(e) => e.uses(field),
+            Expr::Cmp(f, _, _) | Expr::Flag(f) => *f == field,
+        }
+    }
+}
+
+#[derive(Debug, Error, Serialize)]
+#[error("{message} at position {pos}")]
+pub struct ParseError {
+    pub pos: usize,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Num(f64),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn err<T>(pos: usize, message: impl Into<String>) -> Result<T, ParseError> {
+    Err(ParseError { pos, message: message.into() })
+}
+
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let chars: Vec<(usize, char)> = src.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (pos, c) = chars[i];
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+            ('<', Some('=')) => (Token::Op(Op::Le), 2),
+            ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+            ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('=', _) => (Token::Op(Op::Eq), 1),
+            ('!', _) => (Token::Not, 1),
+            (c, _) if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let mut end = i + 1;
+                while end < chars.len() && (chars[end].1.is_ascii_digit() || chars[end].1 == '.') {
+                    end += 1;
+                }
+                let text: String = chars[i..end].iter().map(|(_, c)| c).collect();
+                let Ok(mut value) = text.parse::<f64>() else { return err(pos, format!("bad number '{}'", text)) };
+                match chars.get(end).map(|(_, c)| c.to_ascii_lowercase()) {
+                    Some('k') => { value *= 1e3; end += 1; }
+                    Some('m') => { value *= 1e6; end += 1; }
+                    Some('b') => { value *= 1e9; end += 1; }
+                    Some('%') => end += 1,
+                    _ => {}
+                }
+                if chars.get(end).is_some_and(|(_, c)| c.is_ascii_alphanumeric()) {
+                    return err(chars[end].0, "unexpected character after number");
+                }
+                (Token::Num(value), end - i)
+            }
+            (c, _) if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = i + 1;
+                while end < chars.len() && (chars[end].1.is_ascii_alphanumeric() || chars[end].1 == '_' || chars[end].1 == '%') {
+                    end += 1;
+                }
+                let word: String = chars[i..end].iter().map(|(_, c)| c.to_ascii_lowercase()).collect();
+                let token = match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    "true" => Token::Num(1.0),
+                    "false" => Token::Num(0.0),
+                    _ => Token::Ident(word),
+                };
+                (token, end - i)
+            }
+            (c, _) => return err(pos, format!("unexpected '{}'", c)),
+        };
+        tokens.push((pos, token));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    at: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at).map(|(_, t)| t)
+    }
+
+    fn pos(&self) -> usize {
+        self.tokens.get(self.at).map(|(p, _)| *p).unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).map(|(_, t)| t.clone());
+        self.at += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.at += 1;
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.at += 1;
+            lhs = Expr::And(Box::new(lhs), Box::new(self.not()?));
+        }
+        Ok(lhs)
+    }
+
+    fn not(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::Not) {
+            self.at += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, ParseError> {
+        let pos = self.pos();
+        match self.next() {
+            Some(Token::LParen) => {
+                let inner = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => err(pos, "unclosed '('"),
+                }
+            }
+            Some(Token::Ident(name)) => {
+                let Some(field) = Field::parse(&name) else {
+                    return err(pos, format!("unknown field '{}'", name));
+                };
+                let Some(Token::Op(op)) = self.peek().cloned() else { return Ok(Expr::Flag(field)) };
+                self.at += 1;
+                let value_pos = self.pos();
+                match self.next() {
+                    Some(Token::Num(value)) => Ok(Expr::Cmp(field, op, value)),
+                    _ => err(value_pos, "expected a number"),
+                }
+            }
+            Some(_) => err(pos, "expected a field or '('"),
+            None => err(pos, "unexpected end of condition"),
+        }
+    }
+}
+
+pub fn parse(src: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser { tokens: tokenize(src)?, at: 0, end: src.len() };
+    if parser.tokens.is_empty() {
+        return err(0, "empty condition");
+    }
+    let expr = parser.or()?;
+    if parser.at < parser.tokens.len() {
+        return err(parser.pos(), "unexpected trailing input");
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Values(Vec<(Field, f64)>);
+
+    impl Fields for Values {
+        fn get(&self, field: Field) -> Option<f64> {
+            self.0.iter().find(|(f, _)| *f == field).map(|(_, v)| *v)
+        }
+    }
+
+    fn eval(src: &str, values: &[(Field, f64)]) -> bool {
+        parse(src).unwrap().eval(&Values(values.to_vec()))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        // last > 5 or (volume > 1m and halted)
+        let expr = parse("last > 5 or volume > 1m and halted").unwrap();
+        assert!(matches!(expr, Expr::Or(_, ref rhs) if matches!(**rhs, Expr::And(_, _))));
+        assert!(eval("last > 5 or volume > 1m and halted", &[(Field::Last, 6.0), (Field::Volume, 0.0), (Field::Halted, 0.0)]));
+        assert!(!eval("last > 5 or volume > 1m and halted", &[(Field::Last, 4.0), (Field::Volume, 2e6), (Field::Halted, 0.0)]));
+        assert!(!eval("(last > 5 or volume > 1m) and halted", &[(Field::Last, 6.0), (Field::Halted, 0.0)]));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        let values = [(Field::Halted, 0.0), (Field::Last, 2.0)];
+        assert!(eval("not halted and last >= 2", &values));
+        assert!(eval("!halted && last >= 2", &values));
+        assert!(!eval("not (halted or last >= 2)", &values));
+        assert!(eval("not not last == 2", &values));
+    }
+
+    #[test]
+    fn suffixes_and_aliases() {
+        let values = [(Field::Volume, 1_500_000.0), (Field::ChangePct, 12.0)];
+        assert!(eval("vol > 1.5k and volume >= 1.5m and change% > 10%", &values));
+        assert!(!eval("volume > 1b", &values));
+        assert!(eval("Change_Pct != 11", &values));
+    }
+
+    #[test]
+    fn missing_fields_are_false() {
+        assert!(!eval("bid > 1", &[]));
+        assert!(!eval("halted", &[]));
+        assert!(eval("not halted", &[]));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let cases = [
+            ("", 0, "empty condition"),
+            ("   ", 0, "empty condition"),
+            ("bogus > 1", 0, "unknown field 'bogus'"),
+            ("last >", 6, "expected a number"),
+            ("last > vwap", 7, "expected a number"),
+            ("(last > 1", 0, "unclosed '('"),
+            ("last > 1)", 8, "unexpected trailing input"),
+            ("last > 1 and", 12, "unexpected end of condition"),
+            ("and last > 1", 0, "expected a field or '('"),
+            ("last > 1.2.3", 7, "bad number '1.2.3'"),
+            ("last > 5x", 8, "unexpected character after number"),
+            ("last # 1", 5, "unexpected '#'"),
+        ];
+        for (src, pos, message) in cases {
+            let e = parse(src).err().unwrap_or_else(|| panic!("'{}' parsed", src));
+            assert_eq!((e.pos, e.message.as_str()), (pos, message), "{}", src);
+        }
+    }
+}
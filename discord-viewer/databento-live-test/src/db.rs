@@ -0,0 +1,182 @@
+// Postgres access for the shared ChatProp database (DATABASE_URL). TLS is used
+// unless the URL says sslmode=disable, matching how the Node side connects to Neon.
+// The shared connection is replaced when it drops (server restart, idle timeout),
+// so one blip doesn't fail every query until the process restarts. Reconnecting
+// never holds the lock, so a stalled connect doesn't stall other queries.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use futures_util::stream::poll_fn;
+use futures_util::StreamExt;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio_postgres::{types::ToSql, AsyncMessage, Client, Connection, Error, NoTls, Notification, Row, ToStatement};
+use tracing::{error, info, warn};
+
+// Reconnect attempts while the database is unreachable are at least this far apart
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+
+pub type Db = Arc<Database>;
+
+pub struct Database {
+    url: String,
+    conn: Mutex<Conn>, // never held across an await
+}
+
+struct Conn {
+    client: Arc<Client>,
+    failed_at: Option<Instant>, // last failed reconnect
+    reconnecting: bool,
+}
+
+// Clears the reconnecting flag however the reconnect ends, cancellation included
+struct Reconnecting<'a>(&'a Database);
+
+impl Drop for Reconnecting<'_> {
+    fn drop(&mut self) {
+        self.0.lock().reconnecting = false;
+    }
+}
+
+impl Database {
+    fn lock(&self) -> MutexGuard<'_, Conn> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // The live client, reconnecting first if the connection has closed. The new
+    // connection is opened without holding the lock; meanwhile, and while the
+    // database is down, the closed client is handed out and queries fail fast.
+    async fn client(&self) -> Arc<Client> {
+        {
+            let mut conn = self.lock();
+            let backing_off = conn.failed_at.is_some_and(|t| t.elapsed() < RECONNECT_BACKOFF);
+            if !conn.client.is_closed() || conn.reconnecting || backing_off {
+                return conn.client.clone();
+            }
+            conn.reconnecting = true;
+        }
+        let _reconnecting = Reconnecting(self);
+        let result = open(&self.url, None).await;
+        let mut conn = self.lock();
+        match result {
+            Ok(client) => {
+                info!("Reconnected to Postgres");
+                conn.client = Arc::new(client);
+                conn.failed_at = None;
+            }
+            Err(e) => {
+                warn!("Postgres reconnect failed: {}", e);
+                conn.failed_at = Some(Instant::now());
+            }
+        }
+        conn.client.clone()
+    }
+
+    pub async fn query<T>(&self, statement: &T, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.client().await.query(statement, params).await
+    }
+
+    pub async fn query_one<T>(&self, statement: &T, params: &[&(dyn ToSql + Sync)]) -> Result<Row, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.client().await.query_one(statement, params).await
+    }
+
+    pub async fn query_opt<T>(&self, statement: &T, params: &[&(dyn ToSql + Sync)]) -> Result<Option<Row>, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.client().await.query_opt(statement, params).await
+    }
+
+    pub async fn execute<T>(&self, statement: &T, params: &[&(dyn ToSql + Sync)]) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.client().await.execute(statement, params).await
+    }
+
+    pub async fn batch_execute(&self, query: &str) -> Result<(), Error> {
+        self.client().await.batch_execute(query).await
+    }
+}
+
+fn make_tls() -> Result<tokio_postgres_rustls::MakeRustlsConnect> {
+    let roots = rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(tokio_postgres_rustls::MakeRustlsConnect::new(config))
+}
+
+// Drive a connection in the background, forwarding NOTIFY messages if asked to.
+// The notification sender is dropped when the connection closes.
+fn spawn_connection<S, T>(mut conn: Connection<S, T>, notify: Option<mpsc::UnboundedSender<Notification>>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut messages = poll_fn(move |cx| conn.poll_message(cx));
+        while let Some(msg) = messages.next().await {
+            match msg {
+                Ok(AsyncMessage::Notification(n)) => {
+                    if let Some(tx) = &notify {
+                        let _ = tx.send(n);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Postgres connection error: {}", e);
+                    break;
+                }
+            }
+        }
+        info!("Postgres connection closed");
+    });
+}
+
+async fn open(url: &str, notify: Option<mpsc::UnboundedSender<Notification>>) -> Result<Client> {
+    if url.contains("sslmode=disable") {
+        let (client, conn) = tokio_postgres::connect(url, NoTls).await?;
+        spawn_connection(conn, notify);
+        Ok(client)
+    } else {
+        let (client, conn) = tokio_postgres::connect(url, make_tls()?).await?;
+        spawn_connection(conn, notify);
+        Ok(client)
+    }
+}
+
+// Dedicated connection that LISTENs on `channels`. The receiver ends when the
+// connection drops; keep the returned client alive for as long as you listen.
+pub async fn listen(url: &str, channels: &[&str]) -> Result<(Client, mpsc::UnboundedReceiver<Notification>)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let client = open(url, Some(tx)).await?;
+    for channel in channels {
+        client.batch_execute(&format!("LISTEN {}", channel)).await?;
+    }
+    Ok((client, rx))
+}
+
+// Connect using DATABASE_URL; None when it isn't configured or the connection fails
+pub async fn connect_from_env() -> Option<Db> {
+    let url = std::env::var("DATABASE_URL").ok().filter(|u| !u.is_empty())?;
+    match open(&url, None).await {
+        Ok(client) => {
+            info!("Connected to Postgres");
+            Some(Arc::new(Database { url, conn: Mutex::new(Conn { client: Arc::new(client), failed_at: None, reconnecting: false }) }))
+        }
+        Err(e) => {
+            error!("Failed to connect to Postgres: {}", e);
+            None
+        }
+    }
+}
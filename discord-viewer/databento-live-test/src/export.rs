@@ -0,0 +1,737 @@
+// Exports for notebooks: trades, minute bars and mention-annotated minute series
+// for a set of symbols over a range of trading days, as Parquet or CSV. Rows are
+// fetched from the historical API a response chunk at a time and encoded on a
+// blocking thread as they arrive, so a full day never sits in memory; Parquet
+// output is written in row groups of ROW_GROUP_ROWS.
+//
+//   GET /api/export?kind=trades&symbols=AAPL,NVDA&from=2026-10-01&to=2026-10-16&format=parquet
+//   GET /api/export/schemas
+//   databento-live-test export --kind bars --symbols AAPL,NVDA --from 2026-10-16 [--to DATE]
+//                              [--format parquet|csv] [--out FILE]
+//
+// Schemas, one row per (TRADES, BARS and MENTIONS below describe each column):
+//   trades    trade          symbol, ts_event, ts_recv, price, size, side, publisher_id, sequence
+//   bars      minute bar     symbol, ts, open, high, low, close, volume
+//   mentions  minute with a  symbol, ts, open, high, low, close, volume, mentions, authors,
+//             bar or mention message_ids, max_confidence
+// Days run from the pre-market open to the post-market close (04:00-20:00 ET);
+// mentions outside those hours are not part of any day. Timestamps are UTC:
+// Parquet stores them as TIMESTAMP(NANOS, UTC), CSV as RFC3339 with nanoseconds.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use parquet::{
+    basic::{Compression, ZstdLevel},
+    data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::{
+    bars::BAR_NS,
+    calendar, db, hist, norm_symbol,
+    sessions::{self, RoutingRules},
+    to_ns, AppState,
+};
+
+const ROW_GROUP_ROWS: usize = 65_536;
+const BATCH_ROWS: usize = 8_192;
+const MAX_SYMBOLS: usize = 50;
+const MAX_DAYS: i64 = 92;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    String,
+    Int,
+    Float,
+    Timestamp,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Column {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub kind: ColumnType,
+    pub nullable: bool,
+    pub description: &'static str,
+}
+
+const fn col(name: &'static str, kind: ColumnType, nullable: bool, description: &'static str) -> Column {
+    Column { name, kind, nullable, description }
+}
+
+const TRADES: &[Column] = &[
+    col("symbol", ColumnType::String, false, "ticker"),
+    col("ts_event", ColumnType::Timestamp, false, "matching-engine time of the trade"),
+    col("ts_recv", ColumnType::Timestamp, true, "time Databento received it"),
+    col("price", ColumnType::Float, false, "trade price in dollars"),
+    col("size", ColumnType::Int, false, "shares"),
+    col("side", ColumnType::String, true, "aggressor: A sell into the bid, B buy from the ask, N none"),
+    col("publisher_id", ColumnType::Int, true, "Databento publisher (venue) id"),
+    col("sequence", ColumnType::Int, true, "venue message sequence number"),
+];
+
+const BARS: &[Column] = &[
+    col("symbol", ColumnType::String, false, "ticker"),
+    col("ts", ColumnType::Timestamp, false, "start of the minute"),
+    col("open", ColumnType::Float, false, "first trade price"),
+    col("high", ColumnType::Float, false, "highest trade price"),
+    col("low", ColumnType::Float, false, "lowest trade price"),
+    col("close", ColumnType::Float, false, "last trade price"),
+    col("volume", ColumnType::Int, false, "shares traded"),
+];
+
+const MENTIONS: &[Column] = &[
+    col("symbol", ColumnType::String, false, "ticker"),
+    col("ts", ColumnType::Timestamp, false, "start of the minute"),
+    col("open", ColumnType::Float, true, "first trade price; null in minutes without trades"),
+    col("high", ColumnType::Float, true, "highest trade price"),
+    col("low", ColumnType::Float, true, "lowest trade price"),
+    col("close", ColumnType::Float, true, "last trade price"),
+    col("volume", ColumnType::Int, false, "shares traded"),
+    col("mentions", ColumnType::Int, false, "Discord messages detected mentioning the symbol in the minute"),
+    col("authors", ColumnType::String, true, "comma-separated authors of those messages"),
+    col("message_ids", ColumnType::String, true, "comma-separated message ids"),
+    col("max_confidence", ColumnType::Float, true, "highest detection confidence among them"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Trades,
+    Bars,
+    Mentions,
+}
+
+impl Kind {
+    fn parse(s: &str) -> Option<Kind> {
+        match s {
+            "trades" => Some(Kind::Trades),
+            "bars" => Some(Kind::Bars),
+            "mentions" => Some(Kind::Mentions),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Trades => "trades",
+            Kind::Bars => "bars",
+            Kind::Mentions => "mentions",
+        }
+    }
+
+    pub fn columns(self) -> &'static [Column] {
+        match self {
+            Kind::Trades => TRADES,
+            Kind::Bars => BARS,
+            Kind::Mentions => MENTIONS,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Parquet,
+    Csv,
+}
+
+impl Format {
+    fn parse(s: &str) -> Option<Format> {
+        match s {
+            "parquet" => Some(Format::Parquet),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Parquet => "parquet",
+            Format::Csv => "csv",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Parquet => "application/vnd.apache.parquet",
+            Format::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportSpec {
+    pub kind: Kind,
+    pub symbols: Vec<String>,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub format: Format,
+    pub datasets: HashMap<String, String>, // symbol -> dataset its data comes from
+}
+
+impl ExportSpec {
+    fn new(kind: Kind, symbols: &str, from: NaiveDate, to: Option<NaiveDate>, format: Format) -> Result<Self, String> {
+        let mut symbols: Vec<String> = symbols.split(',').map(norm_symbol).filter(|s| !s.is_empty()).collect();
+        symbols.sort();
+        symbols.dedup();
+        if symbols.is_empty() || symbols.len() > MAX_SYMBOLS {
+            return Err(format!("symbols must list 1 to {} symbols", MAX_SYMBOLS));
+        }
+        let to = to.unwrap_or(from);
+        if to < from {
+            return Err("to is before from".to_string());
+        }
+        if (to - from).num_days() >= MAX_DAYS {
+            return Err(format!("at most {} days per export", MAX_DAYS));
+        }
+        let rules = RoutingRules::from_env();
+        let datasets = symbols.iter().map(|s| (s.clone(), rules.route(s))).collect();
+        Ok(ExportSpec { kind, symbols, from, to, format, datasets })
+    }
+
+    fn filename(&self) -> String {
+        let range = if self.from == self.to { self.from.to_string() } else { format!("{}_{}", self.from, self.to) };
+        let symbols = if self.symbols.len() <= 3 { self.symbols.join("-") } else { format!("{}-symbols", self.symbols.len()) };
+        format!("{}_{}_{}.{}", self.kind.as_str(), symbols, range, self.format.extension())
+    }
+
+    fn dataset(&self, symbol: &str) -> String {
+        self.datasets.get(symbol).cloned().unwrap_or_else(|| RoutingRules::from_env().route(symbol))
+    }
+
+    fn days(&self) -> Vec<NaiveDate> {
+        self.from.iter_days().take_while(|d| *d <= self.to).filter(|d| calendar::is_trading_day(*d)).collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Cell {
+    Null,
+    Str(String),
+    Int(i64),
+    Float(f64),
+}
+
+type Row = Vec<Cell>;
+
+fn opt_str(s: Option<String>) -> Cell {
+    s.map_or(Cell::Null, Cell::Str)
+}
+
+fn opt_int(v: Option<u64>) -> Cell {
+    v.map_or(Cell::Null, |v| Cell::Int(v as i64))
+}
+
+fn opt_float(v: Option<f64>) -> Cell {
+    v.map_or(Cell::Null, Cell::Float)
+}
+
+trait Sink {
+    fn write(&mut self, rows: Vec<Row>) -> Result<()>;
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+struct CsvSink<W: Write> {
+    writer: csv::Writer<W>,
+    columns: &'static [Column],
+}
+
+impl<W: Write> Sink for CsvSink<W> {
+    fn write(&mut self, rows: Vec<Row>) -> Result<()> {
+        for row in rows {
+            let fields = row.iter().zip(self.columns).map(|(cell, column)| match cell {
+                Cell::Null => String::new(),
+                Cell::Str(s) => s.clone(),
+                Cell::Int(v) if column.kind == ColumnType::Timestamp => {
+                    DateTime::from_timestamp_nanos(*v).to_rfc3339_opts(chrono::SecondsFormat::Nanos, true)
+                }
+                Cell::Int(v) => v.to_string(),
+                Cell::Float(v) => v.to_string(),
+            });
+            self.writer.write_record(fields)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+struct ParquetSink<W: Write + Send> {
+    writer: SerializedFileWriter<W>,
+    columns: &'static [Column],
+    pending: Vec<Row>,
+}
+
+impl<W: Write + Send> ParquetSink<W> {
+    fn new(kind: Kind, out: W) -> Result<Self> {
+        let fields: String = kind.columns().iter().map(|c| {
+            let repetition = if c.nullable { "OPTIONAL" } else { "REQUIRED" };
+            let physical = match c.kind {
+                ColumnType::String => "BYTE_ARRAY",
+                ColumnType::Int | ColumnType::Timestamp => "INT64",
+                ColumnType::Float => "DOUBLE",
+            };
+            let logical = match c.kind {
+                ColumnType::String => " (STRING)",
+                ColumnType::Timestamp => " (TIMESTAMP(NANOS,true))",
+                _ => "",
+            };
+            format!("{} {} {}{}; ", repetition, physical, c.name, logical)
+        }).collect();
+        let schema = parse_message_type(&format!("message {} {{ {}}}", kind.as_str(), fields))?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .set_max_row_group_size(ROW_GROUP_ROWS)
+            .build();
+        Ok(ParquetSink {
+            writer: SerializedFileWriter::new(out, Arc::new(schema), Arc::new(props))?,
+            columns: kind.columns(),
+            pending: Vec::new(),
+        })
+    }
+
+    fn write_group(&mut self, rows: Vec<Row>) -> Result<()> {
+        let mut group = self.writer.next_row_group()?;
+        for (i, column) in self.columns.iter().enumerate() {
+            let mut writer = group.next_column()?.ok_or_else(|| anyhow!("schema has no column {}", column.name))?;
+            let defs: Option<Vec<i16>> = column.nullable
+                .then(|| rows.iter().map(|r| i16::from(!matches!(r[i], Cell::Null))).collect());
+            match column.kind {
+                ColumnType::String => {
+                    let values: Vec<ByteArray> = rows.iter()
+                        .filter_map(|r| match &r[i] { Cell::Str(s) => Some(ByteArray::from(s.as_str())), _ => None })
+                        .collect();
+                    writer.typed::<ByteArrayType>().write_batch(&values, defs.as_deref(), None)?;
+                }
+                ColumnType::Int | ColumnType::Timestamp => {
+                    let values: Vec<i64> = rows.iter().filter_map(|r| match r[i] { Cell::Int(v) => Some(v), _ => None }).collect();
+                    writer.typed::<Int64Type>().write_batch(&values, defs.as_deref(), None)?;
+                }
+                ColumnType::Float => {
+                    let values: Vec<f64> = rows.iter().filter_map(|r| match r[i] { Cell::Float(v) => Some(v), _ => None }).collect();
+                    writer.typed::<DoubleType>().write_batch(&values, defs.as_deref(), None)?;
+                }
+            }
+            writer.close()?;
+        }
+        group.close()?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Sink for ParquetSink<W> {
+    fn write(&mut self, rows: Vec<Row>) -> Result<()> {
+        self.pending.extend(rows);
+        while self.pending.len() >= ROW_GROUP_ROWS {
+            let rest = self.pending.split_off(ROW_GROUP_ROWS);
+            let group = std::mem::replace(&mut self.pending, rest);
+            self.write_group(group)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        let rows = std::mem::take(&mut self.pending);
+        if !rows.is_empty() {
+            self.write_group(rows)?;
+        }
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+fn open_sink<W: Write + Send + 'static>(spec: &ExportSpec, out: W) -> Result<Box<dyn Sink>> {
+    Ok(match spec.format {
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(spec.kind.columns().iter().map(|c| c.name))?;
+            Box::new(CsvSink { writer, columns: spec.kind.columns() })
+        }
+        Format::Parquet => Box::new(ParquetSink::new(spec.kind, out)?),
+    })
+}
+
+// Detections per (symbol, minute start)
+#[derive(Debug, Default)]
+struct MinuteMentions {
+    authors: BTreeSet<String>,
+    message_ids: BTreeSet<String>,
+    max_confidence: Option<f64>,
+}
+
+async fn load_mentions(db: &db::Db, spec: &ExportSpec) -> Result<HashMap<(String, u64), MinuteMentions>> {
+    let rows = db.query(
+        "SELECT UPPER(td.ticker_symbol), m.discord_timestamp, td.message_id, a.username, td.confidence_score::float8
+         FROM ticker_detections td
+         JOIN messages m ON m.id = td.message_id
+         LEFT JOIN authors a ON a.id = m.author_id
+         WHERE UPPER(td.ticker_symbol) = ANY($1) AND m.discord_timestamp >= $2 AND m.discord_timestamp < $3",
+        &[&spec.symbols, &calendar::pre_open(spec.from), &calendar::post_close(spec.to)],
+    ).await.context("loading ticker detections")?;
+    let mut minutes: HashMap<(String, u64), MinuteMentions> = HashMap::new();
+    for row in rows {
+        let ts: DateTime<Utc> = row.get(1);
+        let ts_ns = to_ns(ts);
+        let minute = minutes.entry((row.get(0), ts_ns - ts_ns % BAR_NS)).or_default();
+        minute.message_ids.insert(row.get(2));
+        if let Some(author) = row.get::<_, Option<String>>(3) {
+            minute.authors.insert(author);
+        }
+        if let Some(confidence) = row.get::<_, Option<f64>>(4) {
+            minute.max_confidence = Some(minute.max_confidence.map_or(confidence, |c| c.max(confidence)));
+        }
+    }
+    Ok(minutes)
+}
+
+fn trade_row(symbol: &str, r: &serde_json::Value) -> Option<Row> {
+    let hd = r.get("hd");
+    Some(vec![
+        Cell::Str(symbol.to_string()),
+        Cell::Int(hist::as_u64(hd.and_then(|h| h.get("ts_event")))? as i64),
+        opt_int(hist::as_u64(r.get("ts_recv"))),
+        Cell::Float(hist::as_px(r.get("price"))?),
+        Cell::Int(hist::as_u64(r.get("size")).unwrap_or(0) as i64),
+        opt_str(r.get("side").and_then(|s| s.as_str()).map(str::to_string)),
+        opt_int(hist::as_u64(hd.and_then(|h| h.get("publisher_id")))),
+        opt_int(hist::as_u64(r.get("sequence"))),
+    ])
+}
+
+// Produce the rows day by day and symbol by symbol; stops quietly once the sink is gone
+async fn produce(spec: &ExportSpec, db: Option<db::Db>, tx: mpsc::Sender<Vec<Row>>) -> Result<()> {
+    let mut mentions = match spec.kind {
+        Kind::Mentions => {
+            let db = db.ok_or_else(|| anyhow!("mention series need DATABASE_URL"))?;
+            load_mentions(&db, spec).await?
+        }
+        _ => HashMap::new(),
+    };
+    for date in spec.days() {
+        let (start, end) = (calendar::pre_open(date), calendar::post_close(date));
+        for symbol in &spec.symbols {
+            let dataset = spec.dataset(symbol);
+            let mut batch: Vec<Row> = Vec::with_capacity(BATCH_ROWS);
+            match spec.kind {
+                Kind::Trades => {
+                    let mut stream = hist::open_range(&dataset, symbol, "trades", start, end, None).await?;
+                    while let Some(record) = stream.next().await? {
+                        batch.extend(trade_row(symbol, &record));
+                        if batch.len() >= BATCH_ROWS && tx.send(std::mem::take(&mut batch)).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+                Kind::Bars => {
+                    for bar in hist::minute_bars(&dataset, symbol, start, end).await? {
+                        batch.push(vec![
+                            Cell::Str(symbol.clone()),
+                            Cell::Int(bar.start_ns as i64),
+                            Cell::Float(bar.open),
+                            Cell::Float(bar.high),
+                            Cell::Float(bar.low),
+                            Cell::Float(bar.close),
+                            Cell::Int(bar.volume as i64),
+                        ]);
+                    }
+                }
+                Kind::Mentions => {
+                    let (start_ns, end_ns) = (to_ns(start), to_ns(end));
+                    let mut minutes: BTreeMap<u64, (Option<crate::bars::Bar>, Option<MinuteMentions>)> = BTreeMap::new();
+                    for bar in hist::minute_bars(&dataset, symbol, start, end).await? {
+                        let start_ns = bar.start_ns;
+                        minutes.entry(start_ns).or_default().0 = Some(bar);
+                    }
+                    let keys: Vec<(String, u64)> = mentions.keys()
+                        .filter(|(s, ts)| s == symbol && *ts >= start_ns && *ts < end_ns)
+                        .cloned()
+                        .collect();
+                    for key in keys {
+                        let minute = key.1;
+                        minutes.entry(minute).or_default().1 = mentions.remove(&key);
+                    }
+                    for (minute, (bar, mentioned)) in minutes {
+                        let mentioned = mentioned.unwrap_or_default();
+                        let join = |set: &BTreeSet<String>| (!set.is_empty()).then(|| set.iter().cloned().collect::<Vec<_>>().join(","));
+                        batch.push(vec![
+                            Cell::Str(symbol.clone()),
+                            Cell::Int(minute as i64),
+                            opt_float(bar.as_ref().map(|b| b.open)),
+                            opt_float(bar.as_ref().map(|b| b.high)),
+                            opt_float(bar.as_ref().map(|b| b.low)),
+                            opt_float(bar.as_ref().map(|b| b.close)),
+                            Cell::Int(bar.as_ref().map_or(0, |b| b.volume) as i64),
+                            Cell::Int(mentioned.message_ids.len() as i64),
+                            opt_str(join(&mentioned.authors)),
+                            opt_str(join(&mentioned.message_ids)),
+                            opt_float(mentioned.max_confidence),
+                        ]);
+                    }
+                }
+            }
+            if !batch.is_empty() && tx.send(batch).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+// Run an export into `out`, encoding on a blocking thread as rows arrive
+pub async fn export<W: Write + Send + 'static>(spec: ExportSpec, db: Option<db::Db>, out: W) -> Result<()> {
+    let (tx, mut rx) = mpsc::channel::<Vec<Row>>(4);
+    let sink_spec = spec.clone();
+    let sink = tokio::task::spawn_blocking(move || -> Result<()> {
+        let mut sink = open_sink(&sink_spec, out)?;
+        while let Some(rows) = rx.blocking_recv() {
+            sink.write(rows)?;
+        }
+        sink.finish()
+    });
+    let produced = produce(&spec, db, tx).await;
+    let written = sink.await.map_err(|e| anyhow!("export writer panicked: {}", e))?;
+    produced?;
+    written
+}
+
+// io::Write into a streamed response body
+struct BodyWriter {
+    tx: mpsc::Sender<Result<Bytes, std::io::Error>>,
+}
+
+impl Write for BodyWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx.blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "client went away"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    kind: String,
+    symbols: String,
+    from: String,
+    to: Option<String>,
+    format: Option<String>,
+}
+
+fn parse_query(q: &ExportQuery) -> Result<ExportSpec, String> {
+    let kind = Kind::parse(&q.kind).ok_or_else(|| format!("kind must be trades, bars or mentions, not '{}'", q.kind))?;
+    let format = match q.format.as_deref() {
+        Some(f) => Format::parse(f).ok_or_else(|| format!("format must be parquet or csv, not '{}'", f))?,
+        None => Format::default(),
+    };
+    let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| format!("invalid date '{}'", s));
+    let to = q.to.as_deref().map(date).transpose()?;
+    ExportSpec::new(kind, &q.symbols, date(&q.from)?, to, format)
+}
+
+// GET /api/export?kind=trades|bars|mentions&symbols=AAPL,NVDA&from=YYYY-MM-DD[&to=YYYY-MM-DD][&format=parquet|csv]
+pub async fn get_export(Query(q): Query<ExportQuery>, State(state): State<AppState>) -> Response {
+    let error = |status: http::StatusCode, error: String| (status, Json(serde_json::json!({"error": error}))).into_response();
+    let mut spec = match parse_query(&q) {
+        Ok(spec) => spec,
+        Err(e) => return error(http::StatusCode::BAD_REQUEST, e),
+    };
+    for symbol in &spec.symbols {
+        spec.datasets.insert(symbol.clone(), sessions::dataset_for(&state, symbol).await);
+    }
+    if std::env::var("DATABENTO_API_KEY").map(|v| v.is_empty()).unwrap_or(true) {
+        return error(http::StatusCode::SERVICE_UNAVAILABLE, "DATABENTO_API_KEY not set".to_string());
+    }
+    if spec.kind == Kind::Mentions && state.db.is_none() {
+        return error(http::StatusCode::SERVICE_UNAVAILABLE, "mention series need the database".to_string());
+    }
+    info!("Exporting {} for {:?} {}..{} as {:?}", spec.kind.as_str(), spec.symbols, spec.from, spec.to, spec.format);
+
+    let (tx, mut rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(16);
+    let filename = spec.filename();
+    let content_type = spec.format.content_type();
+    tokio::spawn(async move {
+        let failed = tx.clone();
+        if let Err(e) = export(spec, state.db.clone(), BodyWriter { tx }).await {
+            // Headers are out; cutting the body short is how the client learns of it
+            warn!("Export failed: {:#}", e);
+            let _ = failed.send(Err(std::io::Error::other(e.to_string()))).await;
+        }
+    });
+    let body = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx));
+    Response::builder()
+        .header(CONTENT_TYPE, content_type)
+        .header(CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
+        .body(Body::from_stream(body))
+        .unwrap_or_else(|e| error(http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// GET /api/export/schemas
+pub async fn get_schemas() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "trades": TRADES,
+        "bars": BARS,
+        "mentions": MENTIONS,
+        "timestamps": "UTC; Parquet TIMESTAMP(NANOS, UTC), CSV RFC3339 with nanoseconds",
+        "day": "04:00-20:00 America/New_York on trading days",
+    }))
+}
+
+// `databento-live-test export ...`
+pub async fn run(args: &[String]) -> Result<()> {
+    let (mut kind, mut symbols, mut from, mut to, mut format, mut out) = (None, None, None, None, Format::default(), None);
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let mut value = || it.next().cloned().ok_or_else(|| anyhow!("{} needs a value", arg));
+        let date = |s: String| NaiveDate::parse_from_str(&s, "%Y-%m-%d").with_context(|| format!("invalid date '{}'", s));
+        match arg.as_str() {
+            "--kind" => {
+                let v = value()?;
+                kind = Some(Kind::parse(&v).ok_or_else(|| anyhow!("--kind must be trades, bars or mentions, not '{}'", v))?);
+            }
+            "--symbols" => symbols = Some(value()?),
+            "--from" => from = Some(date(value()?)?),
+            "--to" => to = Some(date(value()?)?),
+            "--format" => {
+                let v = value()?;
+                format = Format::parse(&v).ok_or_else(|| anyhow!("--format must be parquet or csv, not '{}'", v))?;
+            }
+            "--out" => out = Some(value()?),
+            other => bail!("unknown export option '{}'", other),
+        }
+    }
+    let (Some(kind), Some(symbols), Some(from)) = (kind, symbols, from) else {
+        bail!("export needs --kind, --symbols and --from");
+    };
+    let spec = ExportSpec::new(kind, &symbols, from, to, format).map_err(|e| anyhow!(e))?;
+    if std::env::var("DATABENTO_API_KEY").map(|v| v.is_empty()).unwrap_or(true) {
+        bail!("export needs DATABENTO_API_KEY");
+    }
+    let db = match kind {
+        Kind::Mentions => Some(db::connect_from_env().await.ok_or_else(|| anyhow!("mention series need DATABASE_URL"))?),
+        _ => None,
+    };
+    let path = out.unwrap_or_else(|| spec.filename());
+    let file = std::fs::File::create(&path).with_context(|| format!("creating {}", path))?;
+    info!("Exporting {} for {:?} {}..{} to {}", kind.as_str(), spec.symbols, spec.from, spec.to, path);
+    export(spec, db, std::io::BufWriter::new(file)).await?;
+    info!("Wrote {}", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use parquet::{
+        basic::Repetition,
+        file::reader::{FileReader, SerializedFileReader},
+    };
+
+    use super::*;
+
+    // A sink's output, readable after the sink has taken the writer
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn spec(kind: Kind, format: Format) -> ExportSpec {
+        ExportSpec::new(kind, "aapl", NaiveDate::from_ymd_opt(2026, 10, 15).unwrap(), None, format).unwrap()
+    }
+
+    #[test]
+    fn trades_csv_follows_the_trades_columns() {
+        let record = serde_json::json!({
+            "hd": {"ts_event": "1792072800000000001", "rtype": 0, "publisher_id": 2, "instrument_id": 38},
+            "price": "150250000000",
+            "size": 100,
+            "side": "B",
+            "ts_recv": "1792072800000500000",
+            "sequence": 42
+        });
+        let full = trade_row("AAPL", &record).unwrap();
+        assert_eq!(full.len(), TRADES.len());
+        let bare = trade_row("AAPL", &serde_json::json!({"hd": {"ts_event": 1792072800000000000u64}, "price": 150000000000u64})).unwrap();
+        assert!(trade_row("AAPL", &serde_json::json!({"price": 1})).is_none());
+
+        let out = Shared::default();
+        let mut sink = open_sink(&spec(Kind::Trades, Format::Csv), out.clone()).unwrap();
+        sink.write(vec![full, bare]).unwrap();
+        sink.finish().unwrap();
+        let csv = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(csv.lines().collect::<Vec<_>>(), vec![
+            "symbol,ts_event,ts_recv,price,size,side,publisher_id,sequence",
+            "AAPL,2026-10-15T14:00:00.000000001Z,2026-10-15T14:00:00.000500000Z,150.25,100,B,2,42",
+            "AAPL,2026-10-15T14:00:00.000000000Z,,150,0,,,",
+        ]);
+        assert_eq!(spec(Kind::Trades, Format::Csv).filename(), "trades_AAPL_2026-10-15.csv");
+    }
+
+    #[test]
+    fn parquet_schema_follows_the_column_layout() {
+        for kind in [Kind::Trades, Kind::Bars, Kind::Mentions] {
+            let out = Shared::default();
+            let mut sink = open_sink(&spec(kind, Format::Parquet), out.clone()).unwrap();
+            if kind == Kind::Mentions {
+                // A minute with a mention and no trades
+                sink.write(vec![vec![
+                    Cell::Str("AAPL".into()),
+                    Cell::Int(1_792_072_800_000_000_000),
+                    Cell::Null,
+                    Cell::Null,
+                    Cell::Null,
+                    Cell::Null,
+                    Cell::Int(0),
+                    Cell::Int(1),
+                    Cell::Str("trader1".into()),
+                    Cell::Str("1296140329813020722".into()),
+                    Cell::Float(0.92),
+                ]]).unwrap();
+            }
+            sink.finish().unwrap();
+
+            let bytes = Bytes::from(out.0.lock().unwrap().clone());
+            let reader = SerializedFileReader::new(bytes).unwrap();
+            let metadata = reader.metadata().file_metadata();
+            assert_eq!(metadata.num_rows(), if kind == Kind::Mentions { 1 } else { 0 });
+            let fields = metadata.schema_descr().root_schema().get_fields().to_vec();
+            assert_eq!(fields.len(), kind.columns().len());
+            for (field, column) in fields.iter().zip(kind.columns()) {
+                let info = field.get_basic_info();
+                assert_eq!(info.name(), column.name);
+                let repetition = if column.nullable { Repetition::OPTIONAL } else { Repetition::REQUIRED };
+                assert_eq!(info.repetition(), repetition, "{}", column.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+// Live feed outages. Each disconnect is recorded as an interval; once the session
+// is back the missed trades are pulled from the historical API and folded into
+// bars, HOD and volume, so the day's aggregates don't silently skip the outage.
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::NaiveDate;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{bars, calendar, current_time_ns, env, hist, norm_symbol, to_dt, to_ns, AppState};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Outage {
+    pub id: u64,
+    pub dataset: String,
+    pub start_ns: u64,
+    pub end_ns: u64,
+    pub symbols: Vec<String>,
+    pub status: &'static str, // recovering | recovered | failed
+    pub recovered_trades: u64,
+    pub failed_symbols: Vec<String>,
+}
+
+// Outages from earlier exchange days are dropped as new ones come in, so the log
+// only holds the current day plus any recovery still running
+#[derive(Debug, Default)]
+pub struct GapLog {
+    next_id: u64,
+    outages: Vec<Outage>,
+}
+
+impl GapLog {
+    fn open(&mut self, dataset: &str, start_ns: u64, end_ns: u64, symbols: Vec<String>) -> u64 {
+        let date = calendar::exchange_date(start_ns);
+        self.outages.retain(|o| o.status == "recovering" || calendar::exchange_date(o.end_ns) >= date);
+        self.next_id += 1;
+        self.outages.push(Outage {
+            id: self.next_id,
+            dataset: dataset.to_string(),
+            start_ns,
+            end_ns,
+            symbols,
+            status: "recovering",
+            recovered_trades: 0,
+            failed_symbols: Vec::new(),
+        });
+        self.next_id
+    }
+
+    fn get_mut(&mut self, id: u64) -> Option<&mut Outage> {
+        self.outages.iter_mut().find(|o| o.id == id)
+    }
+
+    // Outages on `date` (exchange time) that affected `symbol`
+    pub fn for_symbol(&self, symbol: &str, date: NaiveDate) -> Vec<Outage> {
+        self.outages.iter()
+            .filter(|o| o.symbols.iter().any(|s| s == symbol))
+            .filter(|o| calendar::exchange_date(o.start_ns) == date || calendar::exchange_date(o.end_ns) == date)
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Coverage {
+    pub symbol: String,
+    pub date: String,
+    pub complete: bool,
+    pub backfilled: bool, // day's bars before the first live trade were loaded
+    pub first_live_ns: Option<u64>,
+    pub outages: Vec<Outage>,
+}
+
+// Whether a symbol's data for the current exchange day has no holes
+pub async fn coverage(state: &AppState, symbol: &str) -> Coverage {
+    let date = calendar::exchange_date(current_time_ns());
+    let (backfilled, first_live_ns) = state.bars.read().await
+        .get(symbol)
+        .filter(|s| s.date == Some(date))
+        .map(|s| (s.backfilled, s.first_live_ns))
+        .unwrap_or((false, None));
+    let outages = state.gaps.read().await.for_symbol(symbol, date);
+    let from_open = first_live_ns.is_some_and(|ts| ts <= to_ns(calendar::pre_open(date)));
+    let complete = (backfilled || from_open)
+        && outages.iter().all(|o| o.status == "recovered" && !o.failed_symbols.iter().any(|s| s == symbol));
+    Coverage {
+        symbol: symbol.to_string(),
+        date: date.to_string(),
+        complete,
+        backfilled,
+        first_live_ns,
+        outages,
+    }
+}
+
+// Record an outage on `dataset` and recover the missed trades for its symbols from
+// the same dataset. Historical data lags the live feed a little, so wait
+// GAP_FILL_DELAY_SECS (default 30) first.
+//
+// Recovered prints only repair the day's aggregates: bars, the scanner's HOD and
+// the rule fields. They don't go through apply_trade: the last price, the tape and
+// the SSE stream have moved on by the time they arrive, and alerts, call outcomes
+// and paper positions would otherwise react to prints minutes late.
+pub async fn recover(state: AppState, dataset: String, start_ns: u64, end_ns: u64, symbols: Vec<String>) {
+    let id = state.gaps.write().await.open(&dataset, start_ns, end_ns, symbols.clone());
+    warn!("Outage on {} for {:.1}s affecting {} symbols", dataset, (end_ns - start_ns) as f64 / 1e9, symbols.len());
+
+    let delay = env("GAP_FILL_DELAY_SECS", 30u64);
+    tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+
+    let start = to_dt(start_ns);
+    let end = to_dt(end_ns);
+    let mut recovered = 0u64;
+    let mut failed = Vec::new();
+    for symbol in &symbols {
+        let mut trades = match hist::trades(&dataset, symbol, start, end, None).await {
+            Ok(t) => t,
+            Err(e) => {
+                warn!("Gap fill failed for {} on {}: {}", symbol, dataset, e);
+                failed.push(symbol.clone());
+                continue;
+            }
+        };
+        trades.sort_by_key(|t| t.ts_event);
+        recovered += trades.len() as u64;
+        if trades.is_empty() {
+            continue;
+        }
+
+        let prints: Vec<(f64, u32, u64)> = trades.iter().map(|t| (t.price, t.size, t.ts_event)).collect();
+        let closed: Vec<bars::Bar> = {
+            let mut all_bars = state.bars.write().await;
+            let series = all_bars.entry(symbol.clone()).or_default();
+            series.apply_missed(symbol, &prints, start_ns, end_ns);
+            series.closed.iter().cloned().collect()
+        };
+        let high = prints.iter().fold(0.0f64, |hod, (px, _, _)| hod.max(*px));
+        state.scanner.write().await.resync(symbol, high, &closed);
+        state.rules.write().await.missed(symbol, &prints);
+    }
+
+    let mut gaps = state.gaps.write().await;
+    if let Some(outage) = gaps.get_mut(id) {
+        outage.status = if failed.is_empty() { "recovered" } else { "failed" };
+        outage.recovered_trades = recovered;
+        outage.failed_symbols = failed;
+    }
+    info!("Outage {} on {}: recovered {} trades", id, dataset, recovered);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoverageQuery {
+    #[serde(default)]
+    symbol: Option<String>,
+}
+
+// GET /api/live/coverage[?symbol=AAPL] - per-symbol completeness for today and outages
+pub async fn get_coverage(Query(q): Query<CoverageQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    if let Some(symbol) = q.symbol.map(|s| norm_symbol(&s)).filter(|s| !s.is_empty()) {
+        return (StatusCode::OK, Json(serde_json::json!(coverage(&state, &symbol).await)));
+    }
+    let symbols: Vec<String> = state.subscribed_symbols.read().await.iter().cloned().collect();
+    let mut symbol_coverage = Vec::with_capacity(symbols.len());
+    for symbol in &symbols {
+        symbol_coverage.push(coverage(&state, symbol).await);
+    }
+    symbol_coverage.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    let outages = state.gaps.read().await.outages.clone();
+    (StatusCode::OK, Json(serde_json::json!({
+        "symbols": symbol_coverage,
+        "outages": outages
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ns(s: &str) -> u64 {
+        chrono::DateTime::parse_from_rfc3339(s).unwrap().timestamp_nanos_opt().unwrap() as u64
+    }
+
+    #[test]
+    fn outages_roll_with_the_exchange_day() {
+        let mut log = GapLog::default();
+        let symbols = vec!["AAPL".to_string()];
+        let finished = log.open("XNAS.ITCH", ns("2026-10-15T14:00:00Z"), ns("2026-10-15T14:01:00Z"), symbols.clone());
+        log.get_mut(finished).unwrap().status = "recovered";
+        log.open("XNAS.ITCH", ns("2026-10-15T19:00:00Z"), ns("2026-10-15T19:01:00Z"), symbols.clone());
+        log.open("XNAS.ITCH", ns("2026-10-15T20:00:00Z"), ns("2026-10-15T20:01:00Z"), symbols.clone());
+        assert_eq!(log.outages.len(), 3);
+
+        // The next day's first outage drops finished ones; recoveries still running stay
+        let friday = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        log.open("XNAS.ITCH", ns("2026-10-16T14:00:00Z"), ns("2026-10-16T14:01:00Z"), symbols);
+        assert_eq!(log.outages.len(), 3);
+        assert!(log.get_mut(finished).is_none());
+        assert_eq!(log.for_symbol("AAPL", friday).len(), 1);
+        assert!(log.for_symbol("MSFT", friday).is_empty());
+    }
+}
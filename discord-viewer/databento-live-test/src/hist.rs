@@ -0,0 +1,262 @@
+// Databento historical API over plain HTTP (timeseries.get_range, JSON encoding).
+// Every query names its dataset: the one serving the symbol live (sessions::dataset_for),
+// so a symbol routed away from the primary dataset is looked up where it trades.
+
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use serde_json::Value;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{bars::Bar, calendar, to_dt, to_ns};
+
+#[derive(Debug, Error)]
+pub enum HistError {
+    #[error("DATABENTO_API_KEY not configured")]
+    NoApiKey,
+    #[error("upstream request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("upstream error {status}: {body}")]
+    Upstream { status: u16, body: String },
+}
+
+#[derive(Clone, Debug)]
+pub struct HistTrade {
+    pub price: f64,
+    pub size: u32,
+    pub ts_event: u64,
+}
+
+fn api_key() -> Result<String, HistError> {
+    match std::env::var("DATABENTO_API_KEY") {
+        Ok(v) if !v.is_empty() => Ok(v),
+        _ => Err(HistError::NoApiKey),
+    }
+}
+
+// JSON encoding may render 64-bit integers as strings
+pub fn as_u64(v: Option<&Value>) -> Option<u64> {
+    match v? {
+        Value::Number(n) => n.as_u64().or_else(|| n.as_f64().map(|f| f as u64)),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn as_px(v: Option<&Value>) -> Option<f64> {
+    let raw = match v? {
+        Value::Number(n) => n.as_f64()?,
+        Value::String(s) => s.parse::<f64>().ok()?,
+        _ => return None,
+    };
+    Some(raw / 1_000_000_000.0) // nanos to dollars
+}
+
+// Book prices use i64::MAX for an empty side
+fn book_px(v: Option<&Value>) -> Option<f64> {
+    as_px(v).filter(|px| *px < (i64::MAX / 1_000_000_000) as f64)
+}
+
+fn rfc3339(ts: DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true)
+}
+
+// Records of a get_range response, parsed one line at a time as the body arrives
+pub struct RangeStream {
+    resp: reqwest::Response,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl RangeStream {
+    pub async fn next(&mut self) -> Result<Option<Value>, HistError> {
+        loop {
+            if let Some(end) = self.buf.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.buf.drain(..=end).collect();
+                match serde_json::from_slice::<Value>(&line) {
+                    Ok(v) => return Ok(Some(v)),
+                    Err(_) => continue, // blank or malformed line
+                }
+            }
+            if self.done {
+                let rest = std::mem::take(&mut self.buf);
+                return Ok(serde_json::from_slice::<Value>(&rest).ok());
+            }
+            match self.resp.chunk().await? {
+                Some(chunk) => self.buf.extend_from_slice(&chunk),
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+// Raw JSON records for one symbol and schema over [start, end), streamed
+pub async fn open_range(
+    dataset: &str,
+    symbol: &str,
+    schema: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: Option<usize>,
+) -> Result<RangeStream, HistError> {
+    let api_key = api_key()?;
+    let mut url = format!(
+        "https://hist.databento.com/v0/timeseries.get_range?dataset={}&symbols={}&stype_in=raw_symbol&start={}&end={}&schema={}&encoding=json",
+        dataset,
+        symbol,
+        rfc3339(start),
+        rfc3339(end),
+        schema
+    );
+    if let Some(limit) = limit {
+        url.push_str(&format!("&limit={}", limit));
+    }
+    info!(symbol = %symbol, schema = %schema, start = %rfc3339(start), end = %rfc3339(end), "Databento query window");
+
+    // HTTP Basic auth with API key as username and empty password => base64("<APIKEY>:")
+    let auth_b64 = base64::engine::general_purpose::STANDARD.encode(format!("{}:", api_key));
+    let resp = reqwest::Client::new()
+        .get(&url)
+        .header(ACCEPT, "application/json")
+        .header(AUTHORIZATION, format!("Basic {}", auth_b64))
+        .send()
+        .await?;
+
+    if !resp.status().is_success() {
+        let status = resp.status().as_u16();
+        let body = resp.text().await.unwrap_or_default();
+        warn!(%status, %body, "databento non-200");
+        return Err(HistError::Upstream { status, body });
+    }
+    Ok(RangeStream { resp, buf: Vec::new(), done: false })
+}
+
+// Raw JSON records for one symbol and schema over [start, end)
+pub async fn get_range(
+    dataset: &str,
+    symbol: &str,
+    schema: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: Option<usize>,
+) -> Result<Vec<Value>, HistError> {
+    let mut stream = open_range(dataset, symbol, schema, start, end, limit).await?;
+    let mut records = Vec::new();
+    while let Some(record) = stream.next().await? {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+pub async fn trades(
+    dataset: &str,
+    symbol: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: Option<usize>,
+) -> Result<Vec<HistTrade>, HistError> {
+    let records = get_range(dataset, symbol, "trades", start, end, limit).await?;
+    Ok(records.iter().filter_map(trade).collect())
+}
+
+// A trades-schema JSON record
+pub fn trade(r: &Value) -> Option<HistTrade> {
+    Some(HistTrade {
+        price: as_px(r.get("price"))?,
+        size: as_u64(r.get("size")).unwrap_or(0) as u32,
+        ts_event: as_u64(r.get("hd").and_then(|h| h.get("ts_event")))?,
+    })
+}
+
+// One-minute OHLCV bars over [start, end), keyed by bar start
+pub async fn minute_bars(dataset: &str, symbol: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Bar>, HistError> {
+    ohlcv(dataset, symbol, "ohlcv-1m", start, end).await
+}
+
+// Bars of any ohlcv-* schema, oldest first
+pub async fn ohlcv(dataset: &str, symbol: &str, schema: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Bar>, HistError> {
+    let records = get_range(dataset, symbol, schema, start, end, None).await?;
+    let mut bars: Vec<Bar> = records.iter().filter_map(|r| {
+        Some(Bar {
+            symbol: symbol.to_string(),
+            start_ns: as_u64(r.get("hd").and_then(|h| h.get("ts_event")))?,
+            open: as_px(r.get("open"))?,
+            high: as_px(r.get("high"))?,
+            low: as_px(r.get("low"))?,
+            close: as_px(r.get("close"))?,
+            volume: as_u64(r.get("volume")).unwrap_or(0),
+            trades: 0, // not carried by ohlcv schemas
+        })
+    }).collect();
+    bars.sort_by_key(|b| b.start_ns);
+    Ok(bars)
+}
+
+// Last trade at or before `at`, looking back through the most recent sessions when
+// the market is closed: the tail of post-market first, then the regular close, and
+// for names too thin to print in those windows the last daily close.
+pub async fn last_trade(dataset: &str, symbol: &str, at: DateTime<Utc>) -> Result<Option<HistTrade>, HistError> {
+    let mut date = calendar::last_session_date(at);
+    for _ in 0..2 {
+        let post_end = calendar::post_close(date).min(at);
+        let close = calendar::regular_close(date);
+        let mut windows = vec![(post_end - chrono::Duration::minutes(10), post_end)];
+        if close < post_end - chrono::Duration::minutes(10) {
+            windows.push((close - chrono::Duration::minutes(1), close));
+        }
+        for (start, end) in windows {
+            let trades = trades(dataset, symbol, start, end, None).await?;
+            if let Some(last) = trades.into_iter().max_by_key(|t| t.ts_event) {
+                return Ok(Some(last));
+            }
+        }
+        date = calendar::previous_trading_day(date);
+    }
+    last_daily_close(dataset, symbol, at).await
+}
+
+// Close of the most recent daily bar before `at`, stamped at that session's bell
+async fn last_daily_close(dataset: &str, symbol: &str, at: DateTime<Utc>) -> Result<Option<HistTrade>, HistError> {
+    let bars = ohlcv(dataset, symbol, "ohlcv-1d", at - chrono::Duration::days(14), at).await?;
+    Ok(bars.last().map(|bar| {
+        let date = to_dt(bar.start_ns).date_naive();
+        HistTrade {
+            price: bar.close,
+            size: 0,
+            ts_event: to_ns(calendar::regular_close(date)),
+        }
+    }))
+}
+
+// Previous session's regular close: the last print before the bell on the trading
+// day before `date`
+pub async fn previous_close(dataset: &str, symbol: &str, date: chrono::NaiveDate) -> Result<Option<f64>, HistError> {
+    let close = calendar::regular_close(calendar::previous_trading_day(date));
+    for minutes in [1, 15] {
+        let trades = trades(dataset, symbol, close - chrono::Duration::minutes(minutes), close, None).await?;
+        if let Some(last) = trades.into_iter().max_by_key(|t| t.ts_event) {
+            return Ok(Some(last.price));
+        }
+    }
+    Ok(None)
+}
+
+// Status actions (see dbn StatusAction) over [start, end), oldest first
+pub async fn status(dataset: &str, symbol: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<(u16, u64)>, HistError> {
+    let records = get_range(dataset, symbol, "status", start, end, None).await?;
+    let mut actions: Vec<(u16, u64)> = records.iter().filter_map(|r| {
+        Some((as_u64(r.get("action"))? as u16, as_u64(r.get("hd").and_then(|h| h.get("ts_event")))?))
+    }).collect();
+    actions.sort_by_key(|(_, ts)| *ts);
+    Ok(actions)
+}
+
+// Top of book (bid, ask, ts_recv) from the last one-second BBO at or before `at`
+pub async fn quote_at(dataset: &str, symbol: &str, at: DateTime<Utc>) -> Result<Option<(Option<f64>, Option<f64>, u64)>, HistError> {
+    let records = get_range(dataset, symbol, "bbo-1s", at - chrono::Duration::minutes(5), at, None).await?;
+    Ok(records.iter().filter_map(|r| {
+        let level = r.get("levels")?.get(0)?;
+        Some((book_px(level.get("bid_px")), book_px(level.get("ask_px")), as_u64(r.get("ts_recv"))?))
+    }).max_by_key(|(_, _, ts)| *ts))
+}
@@ -0,0 +1,406 @@
+// Auto-subscription driven by Postgres NOTIFY. The extractor triggers publish on
+// `ticker_detected` (ticker_detections inserts) and `ticker_updates` (stocks
+// upserts); newly detected tickers that clear the confidence threshold and the
+// blacklist are subscribed. Every mention (a ticker_detected row with its message)
+// records the price at that moment and starts tracking the call's outcome (see
+// outcomes.rs); stocks upserts carry no message and only subscribe.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    blacklist, current_time_ns, db, env, hist, norm_symbol,
+    outcomes::{self, Outcome},
+    paper, resolve, sessions, start_live_subscription, to_dt, to_ns, AppState,
+};
+
+const MENTION_CHANNEL: &str = "ticker_detected";
+const DETECTION_CHANNELS: [&str; 2] = [MENTION_CHANNEL, "ticker_updates"];
+// A live price further than this from the mention is not the price at the mention
+const LIVE_PRICE_MAX_AGE_NS: u64 = 5_000_000_000;
+const BLACKLIST_CHANNEL: &str = "blacklist_updates";
+// Every message; paper strategies exit on the ones that name no ticker
+const MESSAGE_CHANNEL: &str = "new_message";
+
+const CREATE_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS ticker_detection_prices (
+  id SERIAL PRIMARY KEY,
+  ticker VARCHAR(10) NOT NULL,
+  message_id VARCHAR(50),
+  author VARCHAR(255),
+  confidence DECIMAL(3,2),
+  detected_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  price DECIMAL(18,6),
+  price_ts TIMESTAMP WITH TIME ZONE,
+  price_source VARCHAR(20),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_detection_prices_ticker ON ticker_detection_prices(ticker);
+";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DetectionPrice {
+    pub symbol: String,
+    pub channel: String,
+    pub message_id: Option<String>,
+    pub author: Option<String>,
+    pub confidence: Option<f64>,
+    pub detected_at_ns: u64,
+    pub price: Option<f64>,
+    pub price_ts_ns: Option<u64>,
+    pub price_source: Option<&'static str>, // live | historical | first_live_trade
+    pub outcome: Option<Outcome>,
+    #[serde(skip)]
+    pub row_id: Option<i32>,
+}
+
+#[derive(Debug)]
+struct Detection {
+    ticker: String,
+    confidence: Option<f64>,
+    detected_at: DateTime<Utc>,
+    message_id: Option<String>,
+    author: Option<String>,
+    is_genuine: bool,
+}
+
+fn str_field(v: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|k| v.get(*k).and_then(|x| x.as_str()).map(|s| s.to_string()))
+}
+
+fn num_field(v: &Value, keys: &[&str]) -> Option<f64> {
+    keys.iter().find_map(|k| match v.get(*k)? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+// json_build_object renders TIMESTAMP columns without a zone; the database runs in UTC
+fn parse_ts(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).map(|dt| dt.with_timezone(&Utc)).ok()
+        .or_else(|| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f").ok().map(|n| n.and_utc()))
+}
+
+// Payload shapes differ between the two triggers and between schema versions
+fn parse_payload(payload: &str) -> Option<Detection> {
+    let v: Value = serde_json::from_str(payload).ok()?;
+    let ticker = str_field(&v, &["ticker", "ticker_symbol"])?;
+    Some(Detection {
+        ticker,
+        confidence: num_field(&v, &["confidence", "detection_confidence", "confidence_score"]),
+        detected_at: str_field(&v, &["timestamp", "first_mention_timestamp", "created_at"])
+            .and_then(|s| parse_ts(&s))
+            .unwrap_or_else(Utc::now),
+        message_id: str_field(&v, &["message_id", "first_mention_message_id"]),
+        author: str_field(&v, &["first_mention_author", "author"]),
+        is_genuine: v.get("is_genuine_stock").and_then(|x| x.as_bool()).unwrap_or(true),
+    })
+}
+
+pub fn min_confidence() -> f64 {
+    env("AUTO_SUBSCRIBE_MIN_CONFIDENCE", 0.8)
+}
+
+// Blacklisted symbols get a second look with the source message as context
+async fn check_blacklist(state: &AppState, symbol: &str, d: &Detection) -> Option<blacklist::Blocked> {
+    state.blacklist.read().await.check(symbol, None, d.confidence)?;
+    let context = match (&state.db, &d.message_id) {
+        (Some(db), Some(id)) => db.query_opt("SELECT content FROM messages WHERE id = $1", &[id]).await
+            .ok()
+            .flatten()
+            .and_then(|row| row.get::<_, Option<String>>(0)),
+        _ => None,
+    };
+    state.blacklist.read().await.check(symbol, context.as_deref(), d.confidence)
+}
+
+// ticker_detected payloads don't carry the author (create-message-trigger.sql);
+// it comes from the message
+async fn message_author(state: &AppState, message_id: &str) -> Option<String> {
+    let db = state.db.as_ref()?;
+    match db.query_opt(
+        "SELECT a.username FROM messages m JOIN authors a ON a.id = m.author_id WHERE m.id = $1",
+        &[&message_id],
+    ).await {
+        Ok(row) => row.and_then(|r| r.get::<_, Option<String>>(0)),
+        Err(e) => {
+            warn!("Failed to look up the author of message {}: {}", message_id, e);
+            None
+        }
+    }
+}
+
+pub async fn create_table(state: &AppState) {
+    let Some(db) = &state.db else { return };
+    if let Err(e) = db.batch_execute(CREATE_TABLE).await {
+        error!("Failed to create ticker_detection_prices: {}", e);
+        return;
+    }
+    if let Err(e) = db.batch_execute(outcomes::COLUMNS).await {
+        error!("Failed to add outcome columns to ticker_detection_prices: {}", e);
+    }
+}
+
+// LISTEN loop; reconnects when the connection drops. Detections are ignored with
+// AUTO_SUBSCRIBE=false, blacklist changes and messages are always picked up.
+pub async fn run_listener(state: AppState) {
+    let Some(url) = std::env::var("DATABASE_URL").ok().filter(|u| !u.is_empty()) else {
+        info!("DATABASE_URL not set; auto-subscribe from NOTIFY disabled");
+        return;
+    };
+    let mut channels = vec![BLACKLIST_CHANNEL, MESSAGE_CHANNEL];
+    if std::env::var("AUTO_SUBSCRIBE").is_ok_and(|v| v == "false" || v == "0") {
+        info!("Auto-subscribe disabled");
+    } else {
+        channels.extend(DETECTION_CHANNELS);
+    }
+
+    loop {
+        match db::listen(&url, &channels).await {
+            Ok((_client, mut rx)) => {
+                info!("Listening on {:?}", channels);
+                // Changes may have been missed while disconnected
+                blacklist::refresh(&state).await;
+                while let Some(n) = rx.recv().await {
+                    if n.channel() == BLACKLIST_CHANNEL {
+                        blacklist::refresh(&state).await;
+                        continue;
+                    }
+                    if n.channel() == MESSAGE_CHANNEL {
+                        let (state, payload) = (state.clone(), n.payload().to_string());
+                        tokio::spawn(async move { paper::on_new_message(&state, &payload).await });
+                        continue;
+                    }
+                    match parse_payload(n.payload()) {
+                        Some(d) => handle_detection(&state, n.channel(), d).await,
+                        None => warn!("Unparseable {} payload: {}", n.channel(), n.payload()),
+                    }
+                }
+                warn!("LISTEN connection lost, reconnecting in 5 seconds");
+            }
+            Err(e) => error!("Failed to LISTEN: {}. Retrying in 5 seconds", e),
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+    }
+}
+
+async fn handle_detection(state: &AppState, channel: &str, d: Detection) {
+    let threshold = min_confidence();
+    if !d.is_genuine || d.confidence.is_some_and(|c| c < threshold) {
+        debug!("Skipping {} from {} (confidence={:?}, genuine={})", d.ticker, channel, d.confidence, d.is_genuine);
+        return;
+    }
+
+    let resolution = resolve::resolve_symbols(state, std::slice::from_ref(&d.ticker)).await;
+    let Some(symbol) = resolution.accepted.into_iter().next() else {
+        debug!("Skipping {}: {:?}", d.ticker, resolution.rejected);
+        return;
+    };
+    if let Some(blocked) = check_blacklist(state, &symbol, &d).await {
+        info!("Skipping blacklisted detection {}: {}", symbol, blocked.reason);
+        return;
+    }
+
+    if !state.subscribed_symbols.read().await.contains(&symbol) {
+        info!("Auto-subscribing {} from {} (confidence={:?})", symbol, channel, d.confidence);
+        if let Err(e) = start_live_subscription(vec![symbol.clone()], state.clone()).await {
+            error!("Auto-subscribe failed for {}: {}", symbol, e);
+            return;
+        }
+    }
+
+    // Stocks upserts are dated to the symbol's first ever mention; only a message
+    // makes a call
+    let Some(message_id) = d.message_id.filter(|_| channel == MENTION_CHANNEL) else {
+        debug!("Not recording {} from {}: no message", symbol, channel);
+        return;
+    };
+    let seen = state.detections.read().await.get(&symbol).is_some_and(|list| {
+        list.iter().any(|r| r.message_id.as_deref() == Some(message_id.as_str()))
+    });
+    if seen {
+        return;
+    }
+
+    let author = match d.author {
+        Some(author) => Some(author),
+        None => message_author(state, &message_id).await,
+    };
+    let detected_at_ns = to_ns(d.detected_at);
+    let mut record = DetectionPrice {
+        symbol: symbol.clone(),
+        channel: channel.to_string(),
+        message_id: Some(message_id),
+        author,
+        confidence: d.confidence,
+        detected_at_ns,
+        price: None,
+        price_ts_ns: None,
+        price_source: None,
+        outcome: None,
+        row_id: None,
+    };
+
+    // Live price if it printed around the mention, otherwise the last trade before it
+    let live = state.prices.read().await.get(&symbol)
+        .filter(|p| p.live)
+        .and_then(|p| p.price.zip(p.ts_event_ns))
+        .filter(|(_, ts)| ts.abs_diff(detected_at_ns) <= LIVE_PRICE_MAX_AGE_NS);
+    if let Some((px, ts)) = live {
+        record.price = Some(px);
+        record.price_ts_ns = Some(ts);
+        record.price_source = Some("live");
+    } else {
+        let window_start = d.detected_at - chrono::Duration::seconds(30);
+        let window_end = d.detected_at + chrono::Duration::milliseconds(1);
+        match hist::trades(&sessions::dataset_for(state, &symbol).await, &symbol, window_start, window_end, None).await {
+            Ok(trades) => {
+                if let Some(t) = trades.iter().rev().find(|t| t.ts_event <= record.detected_at_ns) {
+                    record.price = Some(t.price);
+                    record.price_ts_ns = Some(t.ts_event);
+                    record.price_source = Some("historical");
+                }
+            }
+            Err(e) => warn!("Historical price at detection unavailable for {}: {}", symbol, e),
+        }
+    }
+
+    match record.price {
+        Some(px) => record.outcome = Some(Outcome::new(px, detected_at_ns)),
+        None => info!("No price at detection for {}; will use first live trade", symbol),
+    }
+    record.row_id = insert(state, &record).await;
+    let late = record.outcome.as_ref().is_some_and(|o| !o.ready());
+    paper::on_detection(state, &record).await;
+    state.detections.write().await.entry(symbol.clone()).or_default().push(record);
+    if late {
+        tokio::spawn(outcomes::catch_up(state.clone(), symbol, detected_at_ns));
+    }
+}
+
+// Called for every live trade; completes detections still waiting for a price
+pub async fn fill_pending_detection(state: &AppState, symbol: &str, px: f64, ts_ns: u64) {
+    let pending = state.detections.read().await
+        .get(symbol)
+        .is_some_and(|list| list.iter().any(|d| d.price.is_none()));
+    if !pending {
+        return;
+    }
+    let filled: Vec<DetectionPrice> = {
+        let mut detections = state.detections.write().await;
+        let Some(list) = detections.get_mut(symbol) else { return };
+        list.iter_mut()
+            .filter(|d| d.price.is_none())
+            .map(|d| {
+                d.price = Some(px);
+                d.price_ts_ns = Some(ts_ns);
+                d.price_source = Some("first_live_trade");
+                d.outcome = Some(Outcome::new(px, ts_ns));
+                d.clone()
+            })
+            .collect()
+    };
+    for record in &filled {
+        update_price(state, record).await;
+    }
+}
+
+// Row for the detection, written straight away so it exists even without a price yet
+async fn insert(state: &AppState, record: &DetectionPrice) -> Option<i32> {
+    let db = state.db.as_ref()?;
+    let confidence = record.confidence.map(|c| c.to_string());
+    let price = record.price.map(|p| p.to_string());
+    let result = db.query_one(
+        "INSERT INTO ticker_detection_prices (ticker, message_id, author, confidence, detected_at, price, price_ts, price_source)
+         VALUES ($1, $2, $3, $4::text::numeric, $5, $6::text::numeric, $7, $8)
+         RETURNING id",
+        &[
+            &record.symbol,
+            &record.message_id,
+            &record.author,
+            &confidence,
+            &to_dt(record.detected_at_ns),
+            &price,
+            &record.price_ts_ns.map(to_dt),
+            &record.price_source,
+        ],
+    ).await;
+    match result {
+        Ok(row) => Some(row.get(0)),
+        Err(e) => {
+            error!("Failed to persist detection price for {}: {}", record.symbol, e);
+            None
+        }
+    }
+}
+
+async fn update_price(state: &AppState, record: &DetectionPrice) {
+    let (Some(db), Some(row_id)) = (&state.db, record.row_id) else { return };
+    let price = record.price.map(|p| p.to_string());
+    let price_ts = record.price_ts_ns.map(to_dt);
+    let result = db.execute(
+        "UPDATE ticker_detection_prices SET price = $2::text::numeric, price_ts = $3, price_source = $4 WHERE id = $1",
+        &[&row_id, &price, &price_ts, &record.price_source],
+    ).await;
+    if let Err(e) = result {
+        error!("Failed to persist detection price for {}: {}", record.symbol, e);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DetectionsQuery {
+    #[serde(default)]
+    symbol: Option<String>,
+}
+
+// GET /api/live/detections[?symbol=AAPL] - auto-subscriptions and their price at detection
+pub async fn get_detections(Query(q): Query<DetectionsQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let detections = state.detections.read().await;
+    let result: HashMap<String, Vec<DetectionPrice>> = match q.symbol.map(|s| norm_symbol(&s)) {
+        Some(sym) => detections.get(&sym).map(|d| (sym, d.clone())).into_iter().collect(),
+        None => detections.clone(),
+    };
+    Json(serde_json::json!({
+        "as_of_ns": current_time_ns(),
+        "detections": result
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_ticker_detected_notify() {
+        // json_build_object output of notify_ticker_detection (create-message-trigger.sql)
+        let payload = r#"{"ticker" : "NVDA", "message_id" : "1296140329813020722", "confidence" : 0.92, "timestamp" : "2026-10-16T14:31:05.123456"}"#;
+        let d = parse_payload(payload).unwrap();
+        assert_eq!(d.ticker, "NVDA");
+        assert_eq!(d.message_id.as_deref(), Some("1296140329813020722"));
+        assert_eq!(d.confidence, Some(0.92));
+        assert_eq!(d.detected_at, NaiveDateTime::parse_from_str("2026-10-16T14:31:05.123456", "%Y-%m-%dT%H:%M:%S%.f").unwrap().and_utc());
+        assert!(d.is_genuine);
+        // the author is looked up from the message
+        assert_eq!(d.author, None);
+    }
+
+    #[test]
+    fn parses_stock_upserts_and_rejects_junk() {
+        let payload = r#"{"ticker_symbol" : "AMD", "confidence_score" : "0.75", "first_mention_timestamp" : "2026-10-16T13:00:00+00:00", "first_mention_author" : "trader1", "is_genuine_stock" : false}"#;
+        let d = parse_payload(payload).unwrap();
+        assert_eq!((d.ticker.as_str(), d.confidence, d.author.as_deref(), d.is_genuine), ("AMD", Some(0.75), Some("trader1"), false));
+        assert_eq!(d.message_id, None);
+        assert!(parse_payload(r#"{"message_id" : "1"}"#).is_none());
+        assert!(parse_payload("not json").is_none());
+    }
+}
@@ -1,7 +1,8 @@
 use std::{
     collections::{HashMap, HashSet},
     net::SocketAddr,
-    time::{SystemTime, UNIX_EPOCH, Duration},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
 };
 
 use anyhow::Result;
@@ -9,29 +10,60 @@ use axum::{
     extract::{Query, State},
     http::Method,
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::{RwLock, broadcast, mpsc};
 use tower_http::cors::{Any, CorsLayer};
 use tracing::{info, warn, error};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use reqwest::header::{AUTHORIZATION, ACCEPT};
-use base64::Engine as _;
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt};
 use chrono::{DateTime, Utc, Duration as ChronoDuration};
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use futures_util::stream::StreamExt;
 use futures_util::SinkExt;
-// Databento live client
-use databento::{live::Subscription, LiveClient};
-use databento::dbn::{Schema, SType, TradeMsg};
+use databento::dbn::TradeMsg;
+
+mod alerts;
+mod asof;
+mod backfill;
+mod backtest;
+mod bars;
+mod barstore;
+mod calendar;
+mod blacklist;
+mod condition;
+mod db;
+mod export;
+mod gaps;
+mod hist;
+mod listen;
+mod options;
+mod outcomes;
+mod paper;
+mod recorder;
+mod resolve;
+mod rules;
+mod scanner;
+mod scorecards;
+mod seed;
+mod sessions;
+mod stream;
+mod tape;
+mod webhooks;
+
+use sessions::{SessionCommand, SessionInfo};
+use stream::{Envelope, StreamEvent, StreamHub};
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 struct LastPrice {
     price: Option<f64>,
     ts_event_ns: Option<u64>,
+    #[serde(default)]
+    session: Option<calendar::Session>, // session the price printed in
+    #[serde(default)]
+    live: bool, // false for historical backfills until the first live trade replaces them
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -39,16 +71,35 @@ struct PriceUpdate {
     symbol: String,
     price: f64,
     timestamp: u64,
+    session: calendar::Session,
+    live: bool,
 }
 
 #[derive(Clone)]
 struct AppState {
     prices: std::sync::Arc<RwLock<HashMap<String, LastPrice>>>,
-    live_client: std::sync::Arc<RwLock<Option<databento::LiveClient>>>,
     subscribed_symbols: std::sync::Arc<RwLock<HashSet<String>>>,
-    symbol_mapping: std::sync::Arc<RwLock<HashMap<u32, String>>>, // instrument_id -> symbol
-    price_sender: mpsc::UnboundedSender<PriceUpdate>,
-    client_sender: mpsc::UnboundedSender<Vec<String>>, // Channel to send new symbols to the single client task
+    symbol_routes: std::sync::Arc<RwLock<HashMap<String, String>>>, // symbol -> dataset serving it
+    sessions: std::sync::Arc<RwLock<HashMap<String, SessionInfo>>>, // dataset -> live session status
+    options: std::sync::Arc<RwLock<options::OptionsBook>>,
+    symbol_cache: std::sync::Arc<RwLock<HashMap<String, resolve::CachedVerdict>>>,
+    backfill_attempts: std::sync::Arc<RwLock<HashMap<String, u64>>>, // symbol -> last historical price lookup (ns)
+    db: Option<db::Db>,
+    blacklist: std::sync::Arc<RwLock<blacklist::Blacklist>>,
+    bars: std::sync::Arc<RwLock<HashMap<String, bars::BarSeries>>>,
+    tape: std::sync::Arc<RwLock<tape::Tape>>,
+    gaps: std::sync::Arc<RwLock<gaps::GapLog>>, // live feed outages and their recovery
+    scanner: std::sync::Arc<RwLock<scanner::Scanner>>,
+    alerts: std::sync::Arc<RwLock<alerts::AlertBook>>,
+    rules: std::sync::Arc<RwLock<rules::RuleBook>>,
+    detections: std::sync::Arc<RwLock<HashMap<String, Vec<listen::DetectionPrice>>>>, // auto-subscribed symbol -> price at detection
+    webhooks: std::sync::Arc<RwLock<webhooks::Webhooks>>, // outbound targets and their delivery logs
+    paper: std::sync::Arc<RwLock<paper::PaperBook>>, // follow-the-trader strategies and their positions
+    scorecards: std::sync::Arc<RwLock<scorecards::Scorecards>>, // per-author call outcomes over trailing periods
+    recorder: Option<recorder::Recorder>, // raw live records to rotating DBN files, when RECORD_DIR is set
+    barstore: Option<barstore::BarStore>, // minute and daily bars on disk, when BARSTORE_DIR is set
+    events: std::sync::Arc<StreamHub>, // Fan-out for the WebSocket broadcaster and SSE clients
+    session_sender: mpsc::UnboundedSender<SessionCommand>, // Channel to the session router
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,72 +107,171 @@ struct PricesQuery {
     symbols: String,
 }
 
-#[derive(Debug, Serialize)]
-struct PricesResponseItem {
-    symbol: String,
-    price: Option<f64>,
-    ts_event_ns: Option<u64>,
-}
-
 #[derive(Debug, Deserialize)]
 struct SubscribeBody {
     symbols: Vec<String>,
+    // Message the symbols were taken from; lets context-dependent blacklist entries through
+    #[serde(default)]
+    context: Option<String>,
 }
 
 // Helper: normalize symbol keys
 fn norm_symbol(s: &str) -> String { s.trim().to_uppercase() }
 
+fn new_state(db: Option<db::Db>, events: std::sync::Arc<StreamHub>, session_sender: mpsc::UnboundedSender<SessionCommand>) -> AppState {
+    AppState {
+        prices: std::sync::Arc::new(RwLock::new(HashMap::new())),
+        subscribed_symbols: std::sync::Arc::new(RwLock::new(HashSet::new())),
+        symbol_routes: std::sync::Arc::new(RwLock::new(HashMap::new())),
+        sessions: std::sync::Arc::new(RwLock::new(HashMap::new())),
+        options: std::sync::Arc::new(RwLock::new(options::OptionsBook::default())),
+        symbol_cache: std::sync::Arc::new(RwLock::new(HashMap::new())),
+        backfill_attempts: std::sync::Arc::new(RwLock::new(HashMap::new())),
+        db,
+        blacklist: std::sync::Arc::new(RwLock::new(blacklist::Blacklist::default())),
+        bars: std::sync::Arc::new(RwLock::new(HashMap::new())),
+        tape: std::sync::Arc::new(RwLock::new(tape::Tape::from_env())),
+        gaps: std::sync::Arc::new(RwLock::new(gaps::GapLog::default())),
+        scanner: std::sync::Arc::new(RwLock::new(scanner::Scanner::new(scanner::ScannerConfig::from_env()))),
+        alerts: std::sync::Arc::new(RwLock::new(alerts::AlertBook::default())),
+        rules: std::sync::Arc::new(RwLock::new(rules::RuleBook::default())),
+        detections: std::sync::Arc::new(RwLock::new(HashMap::new())),
+        webhooks: std::sync::Arc::new(RwLock::new(webhooks::Webhooks::new())),
+        paper: std::sync::Arc::new(RwLock::new(paper::PaperBook::default())),
+        scorecards: std::sync::Arc::new(RwLock::new(scorecards::Scorecards::new())),
+        recorder: None,
+        barstore: None,
+        events,
+        session_sender,
+    }
+}
+
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = args.first().map(String::as_str).filter(|c| matches!(*c, "backtest" | "export"));
     
-    // Create channels for price broadcasting and client communication
-    let (price_sender, price_receiver) = mpsc::unbounded_channel::<PriceUpdate>();
-    let (client_sender, client_receiver) = mpsc::unbounded_channel::<Vec<String>>();
-    
-    // Initialize state
-    let state = AppState {
-        prices: std::sync::Arc::new(RwLock::new(HashMap::new())),
-        live_client: std::sync::Arc::new(RwLock::new(None)),
-        subscribed_symbols: std::sync::Arc::new(RwLock::new(HashSet::new())),
-        symbol_mapping: std::sync::Arc::new(RwLock::new(HashMap::new())),
-        price_sender,
-        client_sender,
+    // Initialize logging FIRST; a backtest would otherwise log every replayed trade.
+    // Subcommands print their report to stdout, so their logs go to stderr.
+    let (default_filter, writer) = match command {
+        Some(command) => (format!("warn,databento_live_test::{}=info", command), BoxMakeWriter::new(std::io::stderr)),
+        None => ("info,tower_http=info".to_string(), BoxMakeWriter::new(std::io::stdout)),
     };
-    
-    // Initialize logging FIRST
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::try_from_default_env()
-            .unwrap_or_else(|_| "info,tower_http=info".into()))
-        .with(tracing_subscriber::fmt::layer())
+            .unwrap_or_else(|_| default_filter.into()))
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
         .init();
 
-    // Start single Databento client manager
+    match command {
+        Some("backtest") => return backtest::run(&args[1..]).await,
+        Some("export") => return export::run(&args[1..]).await,
+        _ => {}
+    }
+
+    // Create the event fan-out and the session router channel
+    let events = std::sync::Arc::new(StreamHub::new());
+    let (session_sender, session_receiver) = mpsc::unbounded_channel::<SessionCommand>();
+    
+    // Initialize state; only the live server records the feed and stores bars
+    let state = AppState {
+        recorder: recorder::Recorder::from_env(),
+        barstore: barstore::BarStore::from_env(),
+        ..new_state(db::connect_from_env().await, events.clone(), session_sender)
+    };
+
+    // Load the blacklist before anything subscribes, then keep it fresh
+    blacklist::refresh(&state).await;
+    tokio::spawn(blacklist::refresher(state.clone()));
+
+    // Start the session router; it spawns one live session per dataset on demand
     let state_clone = state.clone();
-    tokio::spawn(databento_client_manager(state_clone, client_receiver));
+    tokio::spawn(sessions::session_router(state_clone, session_receiver));
     
     // Start WebSocket broadcaster to Node.js server
     let websocket_url = std::env::var("NODEJS_WS_URL").unwrap_or_else(|_| "ws://localhost:3000/ws".to_string());
-    tokio::spawn(start_websocket_broadcaster(websocket_url, price_receiver));
+    tokio::spawn(start_websocket_broadcaster(websocket_url, events.subscribe()));
+
+    // Alerts, halts and scanner hits to outbound webhooks
+    webhooks::load(&state).await;
+    tokio::spawn(webhooks::dispatcher(state.clone()));
+
+    // Close minute bars for symbols that stopped trading, and keep closed ones on disk
+    tokio::spawn(bar_sweeper(state.clone()));
+    tokio::spawn(barstore::run(state.clone()));
+
+    // Subscribe to tickers as the Discord extractor detects them, and follow how each call played out
+    listen::create_table(&state).await;
+    tokio::spawn(listen::run_listener(state.clone()));
+    tokio::spawn(outcomes::tracker(state.clone()));
+    tokio::spawn(scorecards::run(state.clone()));
 
     // CORS to allow Next.js dev origin
     let cors = CorsLayer::new()
         .allow_origin("http://localhost:3000".parse::<http::HeaderValue>().unwrap())
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
         .allow_headers(Any)
         .max_age(Duration::from_secs(24 * 60 * 60));
     let app = Router::new()
         .route("/", get(|| async { "Live Test Server" }))
         .route("/api/live/prices", get(get_prices))
+        .route("/api/live/prices/at", get(asof::get_prices_at))
         .route("/api/live/ingest_hist", post(ingest_hist))
         .route("/subscribe", post(subscribe))
         .route("/api/live/all", get(get_all_prices))
+        .route("/api/live/sessions", get(get_sessions))
+        .route("/api/live/resolve", post(resolve::resolve))
+        .route("/api/live/stream", get(stream::sse_stream))
+        .route("/api/live/snapshot", get(stream::get_snapshot))
+        .route("/api/live/replay", get(stream::get_replay))
+        .route("/api/live/trades", get(tape::get_trades))
+        .route("/api/live/bars", get(bars::get_bars))
+        .route("/api/hist/bars", get(barstore::get_hist_bars))
+        .route("/api/live/coverage", get(gaps::get_coverage))
+        .route("/api/recordings", get(recorder::get_recordings))
+        .route("/api/export", get(export::get_export))
+        .route("/api/export/schemas", get(export::get_schemas))
+        .route("/api/alerts", get(alerts::list_alerts).post(alerts::create_alert))
+        .route("/api/alerts/:id", delete(alerts::cancel_alert))
+        .route("/api/rules", get(rules::list_rules).post(rules::create_rule))
+        .route("/api/rules/fields", get(rules::get_fields))
+        .route("/api/rules/:id", get(rules::get_rule).put(rules::update_rule).delete(rules::delete_rule))
+        .route("/api/webhooks", get(webhooks::list_webhooks).post(webhooks::create_webhook))
+        .route("/api/webhooks/:id", put(webhooks::update_webhook).delete(webhooks::delete_webhook))
+        .route("/api/webhooks/:id/deliveries", get(webhooks::get_deliveries))
+        .route("/api/webhooks/:id/test", post(webhooks::test_webhook))
+        .route("/api/live/movers", get(scanner::get_movers))
+        .route("/api/live/detections", get(listen::get_detections))
+        .route("/api/detections/outcomes", get(outcomes::get_outcomes))
+        .route("/api/traders/scorecards", get(scorecards::get_scorecards))
+        .route("/api/traders/scorecards/recompute", post(scorecards::post_recompute))
+        .route("/api/traders/:author/scorecard", get(scorecards::get_scorecard))
+        .route("/api/paper/strategies", get(paper::list_strategies).post(paper::create_strategy))
+        .route("/api/paper/strategies/:id", get(paper::get_strategy).put(paper::update_strategy).delete(paper::delete_strategy))
+        .route("/api/paper/strategies/:id/positions", get(paper::get_positions))
+        .route("/api/paper/strategies/:id/positions/:position_id/close", post(paper::close_position))
+        .route("/api/paper/strategies/:id/fills", get(paper::get_fills))
+        .route("/api/paper/strategies/:id/curve", get(paper::get_curve))
+        .route("/api/paper/authors/:author", get(paper::get_author))
+        .route("/api/options/subscribe", post(options::subscribe_options))
+        .route("/api/options/chain", get(options::get_chain))
+        .route("/api/options/ratios", get(options::get_ratios))
         .route("/ingest_one", post(ingest_one))
         .with_state(state.clone())
         .layer(cors);
 
-    // Start background Databento live subscriber for Trades
-    // Dataset: default to EQUS.MINI for live
-    // Don't start any hardcoded subscriptions - wait for dynamic subscriptions from the UI
+    // Restore today's subscriptions from Postgres; everything else arrives from the UI or LISTEN
+    tokio::spawn(seed::seed_from_db(state.clone()));
+
+    // Level alerts survive restarts; their symbols are watched again
+    alerts::load(&state).await;
+    rules::load(&state).await;
+    paper::load(&state).await;
+    tokio::spawn(paper::sweeper(state.clone()));
+    tokio::spawn({
+        let state = state.clone();
+        async move { outcomes::resume(&state).await }
+    });
 
     let addr: SocketAddr = "0.0.0.0:7878".parse().unwrap();
     info!(?addr, "Starting live server");
@@ -131,6 +281,30 @@ async fn main() -> Result<()> {
 
 async fn get_prices(Query(params): Query<PricesQuery>, State(app_state): State<AppState>) -> impl IntoResponse {
     let symbols: Vec<String> = params.symbols.split(',').map(|s| s.to_string()).collect();
+    
+    // Symbols with no price yet get their last historical trade (marked non-live), looked
+    // up in the background; unknown and blacklisted symbols never reach upstream
+    let missing: Vec<String> = {
+        let prices = app_state.prices.read().await;
+        symbols.iter()
+            .filter(|s| !s.is_empty() && norm_symbol(s) == **s && !prices.contains_key(*s))
+            .cloned()
+            .collect()
+    };
+    let mut lookups: Vec<String> = Vec::new();
+    if !missing.is_empty() {
+        let resolution = resolve::resolve_symbols(&app_state, &missing).await;
+        let blacklist = app_state.blacklist.read().await;
+        lookups = resolution.accepted.into_iter()
+            .filter(|s| blacklist.check(s, None, None).is_none())
+            .collect();
+    }
+    if !lookups.is_empty() {
+        let backfill_state = app_state.clone();
+        let backfill_symbols = lookups.clone();
+        tokio::spawn(async move { backfill::last_prices(&backfill_state, &backfill_symbols).await });
+    }
+    
     let prices = app_state.prices.read().await;
     
     info!("get_prices: requested symbols={:?}, available keys={:?}", symbols, prices.keys().collect::<Vec<_>>());
@@ -139,7 +313,9 @@ async fn get_prices(Query(params): Query<PricesQuery>, State(app_state): State<A
     
     for symbol in symbols {
         if let Some(price) = prices.get(&symbol) {
-            result.insert(symbol, price.clone());
+            result.insert(symbol, serde_json::json!(price));
+        } else if lookups.contains(&symbol) {
+            result.insert(symbol, serde_json::json!({ "price": null, "ts_event_ns": null, "status": "pending" }));
         }
     }
     
@@ -155,6 +331,16 @@ async fn get_all_prices(State(app_state): State<AppState>) -> impl IntoResponse
     Json(all_prices)
 }
 
+// Handler to list live sessions per dataset and which dataset serves each symbol
+async fn get_sessions(State(app_state): State<AppState>) -> impl IntoResponse {
+    let sessions = app_state.sessions.read().await.clone();
+    let routes = app_state.symbol_routes.read().await.clone();
+    Json(serde_json::json!({
+        "sessions": sessions,
+        "routes": routes,
+    }))
+}
+
 // Placeholder: accept subscription list. In a later step, wire this to Databento and start/refresh the live feed.
 async fn subscribe(
     State(state): State<AppState>,
@@ -162,17 +348,34 @@ async fn subscribe(
 ) -> impl IntoResponse {
     info!("Subscribe request for symbols: {:?}", body.symbols);
     
-    // Get API key and dataset from env
-    let api_key = match std::env::var("DATABENTO_API_KEY") {
-        Ok(key) => key,
-        Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                "error": "DATABENTO_API_KEY not set"
-            })));
-        }
+    if std::env::var("DATABENTO_API_KEY").is_err() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+            "error": "DATABENTO_API_KEY not set"
+        })));
+    }
+    
+    // Canonicalize and validate; rejected symbols never reach a live session
+    let mut resolution = resolve::resolve_symbols(&state, &body.symbols).await;
+    
+    // Blacklisted symbols are refused before they cost anything
+    let blacklisted = {
+        let mut blacklist = state.blacklist.write().await;
+        let (allowed, blocked) = blacklist.partition(std::mem::take(&mut resolution.accepted), body.context.as_deref());
+        blacklist.unmute(&allowed);
+        resolution.accepted = allowed;
+        blocked
     };
+    if !blacklisted.is_empty() {
+        info!("Refusing blacklisted symbols: {:?}", blacklisted);
+    }
     
-    let dataset = std::env::var("DATABENTO_DATASET").unwrap_or("EQUS.MINI".to_string());
+    if resolution.accepted.is_empty() && (!resolution.rejected.is_empty() || !blacklisted.is_empty()) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({
+            "error": "No valid symbols",
+            "rejected": resolution.rejected,
+            "blacklisted": blacklisted
+        })));
+    }
     
     // Filter new symbols we haven't subscribed to yet
     let mut new_symbols = Vec::new();
@@ -183,11 +386,10 @@ async fn subscribe(
     
     {
         let subscribed = state.subscribed_symbols.read().await;
-        for sym in &body.symbols {
-            let norm = norm_symbol(sym);
+        for sym in &resolution.accepted {
             // If we have no active prices, force resubscription even if in the set
-            if !has_active_prices || !subscribed.contains(&norm) {
-                new_symbols.push(norm);
+            if !has_active_prices || !subscribed.contains(sym) {
+                new_symbols.push(sym.clone());
             }
         }
     }
@@ -195,26 +397,33 @@ async fn subscribe(
     if new_symbols.is_empty() {
         return (StatusCode::OK, Json(serde_json::json!({
             "status": "ok", 
-            "message": "All symbols already subscribed"
+            "message": "All symbols already subscribed",
+            "rejected": resolution.rejected,
+            "blacklisted": blacklisted
         })));
     }
     
     info!("New symbols to subscribe: {:?}", new_symbols);
     
-    // Create a new live client for these symbols
+    // Hand the symbols to the session router
     match start_live_subscription(
-        api_key,
-        dataset,
         new_symbols.clone(),
         state.clone()
     ).await {
         Ok(actually_subscribed) => {
-            // Mark symbols as subscribed
-            let mut subscribed = state.subscribed_symbols.write().await;
-            for sym in &actually_subscribed {
-                subscribed.insert(sym.clone());
+            // Mark symbols as subscribed; released before sleeping or touching other locks
+            {
+                let mut subscribed = state.subscribed_symbols.write().await;
+                for sym in &actually_subscribed {
+                    subscribed.insert(sym.clone());
+                }
             }
             
+            // Show the last historical trade until the first live one arrives
+            let backfill_state = state.clone();
+            let backfill_symbols = actually_subscribed.clone();
+            tokio::spawn(async move { backfill::last_prices(&backfill_state, &backfill_symbols).await });
+            
             // Wait a moment to see if we get symbol mappings
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
             
@@ -227,11 +436,21 @@ async fn subscribe(
                     .count()
             };
             
+            let routes: HashMap<String, String> = {
+                let routes = state.symbol_routes.read().await;
+                actually_subscribed.iter()
+                    .filter_map(|sym| routes.get(sym).map(|ds| (sym.clone(), ds.clone())))
+                    .collect()
+            };
+            
             (StatusCode::OK, Json(serde_json::json!({
                 "status": "ok",
                 "requested": body.symbols.len(),
                 "subscribed": actually_subscribed.len(),
-                "valid": valid_count
+                "valid": valid_count,
+                "routes": routes,
+                "rejected": resolution.rejected,
+                "blacklisted": blacklisted
             })))
         }
         Err(e) => {
@@ -252,10 +471,11 @@ async fn ingest_one(
     Json(body): Json<IngestOneBody>,
 ) -> impl IntoResponse {
     let key = norm_symbol(&body.symbol);
+    let ts_event_ns = body.ts_event_ns.unwrap_or_else(current_time_ns);
     let mut map = state.prices.write().await;
     map.insert(
         key.clone(),
-        LastPrice { price: Some(body.price), ts_event_ns: body.ts_event_ns.or(Some(current_time_ns())) },
+        LastPrice { price: Some(body.price), ts_event_ns: Some(ts_event_ns), session: Some(calendar::session_at_ns(ts_event_ns)), live: true },
     );
     info!(symbol = %key, price = body.price, "ingested test price");
     (StatusCode::OK, Json(serde_json::json!({"status": "ok"})))
@@ -269,14 +489,6 @@ async fn ingest_hist(
     State(state): State<AppState>,
     Json(body): Json<IngestHistBody>,
 ) -> impl IntoResponse {
-    let api_key = match std::env::var("DATABENTO_API_KEY") {
-        Ok(v) if !v.is_empty() => v,
-        _ => {
-            warn!("DATABENTO_API_KEY not set");
-            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "DATABENTO_API_KEY not configured"})));
-        }
-    };
-
     let symbol = norm_symbol(&body.symbol);
     let ts: DateTime<Utc> = match DateTime::parse_from_rfc3339(&body.timestamp) {
         Ok(dt) => dt.with_timezone(&Utc),
@@ -288,97 +500,102 @@ async fn ingest_hist(
     // 2-second window around timestamp (1s before to 1s after)
     let start_dt = ts - ChronoDuration::seconds(1);
     let end_dt = start_dt + ChronoDuration::seconds(2);
-    
-    // Use RFC3339 with fixed offset to avoid timezone issues
-    let start_str = start_dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
-    let end_str = end_dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
-    
-    info!(symbol = %symbol, start = %start_str, end = %end_str, "Databento query window");
-
-    // Dataset: default to EQUS.MINI for US Equities
-    let dataset = std::env::var("DATABENTO_DATASET").unwrap_or_else(|_| "EQUS.MINI".to_string());
-    let url = format!(
-        "https://hist.databento.com/v0/timeseries.get_range?dataset={}&symbols={}&stype_in=raw_symbol&start={}&end={}&schema=trades&encoding=json&limit=100",
-        dataset,
-        symbol,
-        start_str,
-        end_str
-    );
 
-    // HTTP Basic auth with API key as username and empty password => base64("<APIKEY>:")
-    let userpass = format!("{}:", api_key);
-    let auth_b64 = base64::engine::general_purpose::STANDARD.encode(userpass);
-
-    let client = reqwest::Client::new();
-    let resp = match client
-        .get(&url)
-        .header(ACCEPT, "application/json")
-        .header(AUTHORIZATION, format!("Basic {}", auth_b64))
-        .send()
-        .await
-    {
-        Ok(r) => r,
+    let dataset = sessions::dataset_for(&state, &symbol).await;
+    let window = match hist::trades(&dataset, &symbol, start_dt, end_dt, Some(100)).await {
+        Ok(t) => t,
+        Err(hist::HistError::NoApiKey) => {
+            warn!("DATABENTO_API_KEY not set");
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "DATABENTO_API_KEY not configured"})));
+        }
+        Err(hist::HistError::Upstream { status, .. }) => {
+            return (StatusCode::BAD_GATEWAY, Json(serde_json::json!({"error": "upstream error", "status": status})));
+        }
         Err(e) => {
             error!(error = %e, "databento request failed");
             return (StatusCode::BAD_GATEWAY, Json(serde_json::json!({"error": "upstream request failed"})));
         }
     };
 
-    if !resp.status().is_success() {
-        let code = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        warn!(%code, %text, "databento non-200");
-        return (StatusCode::BAD_GATEWAY, Json(serde_json::json!({"error": "upstream error", "status": code.as_u16()})));
-    }
-
-    let body_text = match resp.text().await {
-        Ok(t) => t,
-        Err(e) => {
-            return (StatusCode::BAD_GATEWAY, Json(serde_json::json!({"error": format!("failed reading body: {}", e)})));
-        }
-    };
-
     let mut total_px: f64 = 0.0;
     let mut total_sz: f64 = 0.0;
     let mut min_px = f64::INFINITY;
     let mut max_px = f64::NEG_INFINITY;
     let mut trades = 0u64;
-    for line in body_text.lines() {
-        if line.trim().is_empty() { continue; }
-        if let Ok(val) = serde_json::from_str::<serde_json::Value>(line) {
-            let price_raw = val.get("price").and_then(|v| v.as_f64());
-            let size = val.get("size").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            if let Some(pnanos) = price_raw {
-                let px = pnanos / 1_000_000_000.0; // nanos to dollars
-                if size > 0.0 { total_px += px * size; total_sz += size; }
-                if px.is_finite() { if px < min_px { min_px = px; } if px > max_px { max_px = px; } }
-                trades += 1;
-            }
-        }
+    for t in &window {
+        let size = t.size as f64;
+        if size > 0.0 { total_px += t.price * size; total_sz += size; }
+        if t.price.is_finite() { min_px = min_px.min(t.price); max_px = max_px.max(t.price); }
+        trades += 1;
     }
 
     if trades == 0 {
-        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no trades in window"})));
+        // Nothing prints on weekends, holidays or overnight; use the last session instead
+        if calendar::session_at(ts) == calendar::Session::Regular {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no trades in window"})));
+        }
+        let last = match hist::last_trade(&dataset, &symbol, ts).await {
+            Ok(Some(t)) => t,
+            Ok(None) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no trades in window or last session"}))),
+            Err(e) => {
+                error!(error = %e, "databento last session request failed");
+                return (StatusCode::BAD_GATEWAY, Json(serde_json::json!({"error": "upstream request failed"})));
+            }
+        };
+        let session = calendar::session_at_ns(last.ts_event);
+        state.prices.write().await.insert(
+            symbol.clone(),
+            LastPrice { price: Some(last.price), ts_event_ns: Some(last.ts_event), session: Some(session), live: false },
+        );
+        info!(symbol = %symbol, price = last.price, "ingested last session trade from Databento");
+        return (StatusCode::OK, Json(serde_json::json!({
+            "status": "ok",
+            "symbol": symbol,
+            "price": last.price,
+            "trades": 1,
+            "fallback": "last_session",
+            "session": session,
+            "session_date": calendar::exchange_date(last.ts_event).to_string(),
+            "ts_event_ns": last.ts_event
+        })));
     }
 
     let vwap = if total_sz > 0.0 { total_px / total_sz } else { (min_px + max_px) / 2.0 };
+    let session = calendar::session_at(ts);
 
     {
         let mut map = state.prices.write().await;
-        map.insert(symbol.clone(), LastPrice { price: Some(vwap), ts_event_ns: Some(current_time_ns()) });
+        map.insert(symbol.clone(), LastPrice { price: Some(vwap), ts_event_ns: Some(current_time_ns()), session: Some(session), live: false });
     }
     info!(symbol = %symbol, price = vwap, trades, "ingested from Databento window");
-    (StatusCode::OK, Json(serde_json::json!({"status": "ok", "symbol": symbol, "price": vwap, "trades": trades})))
+    (StatusCode::OK, Json(serde_json::json!({"status": "ok", "symbol": symbol, "price": vwap, "trades": trades, "session": session})))
 }
 
+// Set by backtests so everything downstream runs on replayed event time; zero is the wall clock
+static SIM_CLOCK_NS: AtomicU64 = AtomicU64::new(0);
+
 fn current_time_ns() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+    match SIM_CLOCK_NS.load(Ordering::Relaxed) {
+        0 => SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64,
+        sim => sim,
+    }
+}
+
+fn to_ns(dt: DateTime<Utc>) -> u64 {
+    dt.timestamp_nanos_opt().unwrap_or(0) as u64
+}
+
+fn to_dt(ns: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp_nanos(ns as i64)
+}
+
+// Environment setting parsed as T, or `default` when unset or invalid
+fn env<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
 }
 
 async fn start_live_subscription(
-    _api_key: String,
-    _dataset: String,
     symbols: Vec<String>,
     state: AppState
 ) -> Result<Vec<String>> {
@@ -386,13 +603,20 @@ async fn start_live_subscription(
         return Ok(vec![]);
     }
 
-    // Send symbols to the single client manager instead of creating new connections
-    if let Err(e) = state.client_sender.send(symbols.clone()) {
-        error!("Failed to send symbols to client manager: {}", e);
-        return Err(anyhow::anyhow!("Client manager communication failed"));
+    // Send symbols to the session router instead of creating new connections
+    if let Err(e) = state.session_sender.send(SessionCommand::Subscribe(symbols.clone())) {
+        error!("Failed to send symbols to session router: {}", e);
+        return Err(anyhow::anyhow!("Session router communication failed"));
+    }
+
+    // Fill the day so far; live bars take over from the first live trade
+    for symbol in &symbols {
+        let backfill_state = state.clone();
+        let symbol = symbol.clone();
+        tokio::spawn(async move { backfill::intraday_bars(&backfill_state, &symbol).await });
     }
 
-    // Mark symbols as subscribed immediately (the client manager will handle actual subscription)
+    // Mark symbols as subscribed immediately (the dataset session will handle actual subscription)
     {
         let mut subscribed = state.subscribed_symbols.write().await;
         for sym in &symbols {
@@ -404,173 +628,83 @@ async fn start_live_subscription(
     Ok(symbols)
 }
 
-// Single Databento client manager that handles all subscriptions
-async fn databento_client_manager(
-    state: AppState,
-    mut symbol_receiver: mpsc::UnboundedReceiver<Vec<String>>
-) {
-    let api_key = match std::env::var("DATABENTO_API_KEY") {
-        Ok(key) => key,
-        Err(_) => {
-            error!("DATABENTO_API_KEY not set for client manager");
-            return;
-        }
-    };
+// Record a live trade, fold it into the minute bar and tape, and fan it out
+async fn apply_trade(state: &AppState, symbol: &str, trade: &TradeMsg) {
+    if state.blacklist.read().await.is_muted(symbol) {
+        return;
+    }
+    let px = trade.price as f64 / 1_000_000_000.0;
+    let session = calendar::session_at_ns(trade.hd.ts_event);
+    info!("Live trade: instrument_id={}, symbol={}, price=${:.4}", trade.hd.instrument_id, symbol, px);
     
-    let dataset = std::env::var("DATABENTO_DATASET").unwrap_or("EQUS.MINI".to_string());
-    info!("Starting Databento client manager with dataset: {}", dataset);
+    let print = tape::TapeTrade::from_msg(symbol, trade);
+    state.tape.write().await.push(print.clone());
+    state.events.publish(StreamEvent::Trade(print.clone()));
     
-    let mut client = match LiveClient::builder()
-        .key(&api_key)
-        .unwrap()
-        .dataset(&dataset)
-        .build()
-        .await
     {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Failed to create Databento client: {}", e);
-            return;
-        }
+        let mut map = state.prices.write().await;
+        map.insert(symbol.to_string(), LastPrice { price: Some(px), ts_event_ns: Some(trade.hd.ts_event), session: Some(session), live: true });
+    }
+    listen::fill_pending_detection(state, symbol, px, trade.hd.ts_event).await;
+    outcomes::on_trade(state, symbol, px, trade.hd.ts_event).await;
+    
+    let closed_bar = {
+        let mut bars = state.bars.write().await;
+        bars.entry(symbol.to_string()).or_default().on_trade(symbol, px, trade.size, trade.hd.ts_event)
     };
+    if let Some(bar) = closed_bar {
+        publish_bar_close(state, bar).await;
+    }
     
-    let mut subscribed_instruments: HashSet<String> = HashSet::new();
-    let mut client_started = false;
+    state.events.publish(StreamEvent::Price(PriceUpdate {
+        symbol: symbol.to_string(),
+        price: px,
+        timestamp: trade.hd.ts_event,
+        session,
+        live: true,
+    }));
+    alerts::on_trade(state, &print).await;
+    rules::on_trade(state, &print).await;
+    paper::on_trade(state, symbol, px, trade.hd.ts_event).await;
     
+    let hits = state.scanner.write().await.on_trade(symbol, px, trade.hd.ts_event);
+    for hit in hits {
+        state.events.publish(StreamEvent::Scanner(hit));
+    }
+}
+
+async fn publish_bar_close(state: &AppState, bar: bars::Bar) {
+    let hits = state.scanner.write().await.on_bar(&bar);
+    state.events.publish(StreamEvent::BarClose(bar));
+    for hit in hits {
+        state.events.publish(StreamEvent::Scanner(hit));
+    }
+}
+
+async fn bar_sweeper(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
     loop {
-        tokio::select! {
-            // Handle new symbol subscription requests
-            symbols_opt = symbol_receiver.recv() => {
-                if let Some(symbols) = symbols_opt {
-                    info!("Client manager received subscription request for: {:?}", symbols);
-                    
-                    // Filter to new symbols only
-                    let mut new_symbols = Vec::new();
-                    for sym in symbols {
-                        if !subscribed_instruments.contains(&sym) {
-                            new_symbols.push(sym.clone());
-                            subscribed_instruments.insert(sym);
-                        }
-                    }
-                    
-                    if !new_symbols.is_empty() {
-                        info!("Subscribing to new symbols: {:?}", new_symbols);
-                        
-                        // Create subscription for all new symbols at once
-                        let subscription = Subscription::builder()
-                            .schema(Schema::Trades)
-                            .stype_in(SType::RawSymbol)
-                            .symbols(new_symbols.clone())
-                            .build();
-                            
-                        match client.subscribe(&subscription).await {
-                            Ok(_) => {
-                                info!("Successfully subscribed to {} symbols", new_symbols.len());
-                                
-                                // Start the client if this is the first subscription
-                                if !client_started {
-                                    match client.start().await {
-                                        Ok(_) => {
-                                            info!("Databento client started");
-                                            client_started = true;
-                                        }
-                                        Err(e) => {
-                                            error!("Failed to start Databento client: {}", e);
-                                            // Remove symbols from subscribed set since start failed
-                                            for sym in &new_symbols {
-                                                subscribed_instruments.remove(sym);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                error!("Failed to subscribe to symbols {:?}: {}", new_symbols, e);
-                                // Remove symbols from subscribed set since subscription failed
-                                for sym in &new_symbols {
-                                    subscribed_instruments.remove(sym);
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    warn!("Symbol receiver channel closed");
-                    break;
-                }
-            }
-            
-            // Handle incoming trade data (only if client is started)
-            rec_result = client.next_record(), if client_started => {
-                match rec_result {
-                    Ok(Some(rec)) => {
-                        // Handle symbol mapping messages
-                        if let Some(mapping) = rec.get::<databento::dbn::SymbolMappingMsg>() {
-                            let raw_symbol = unsafe {
-                                std::ffi::CStr::from_ptr(mapping.stype_out_symbol.as_ptr() as *const i8)
-                                    .to_string_lossy()
-                                    .into_owned()
-                            };
-                            info!("Symbol mapping: instrument_id={} -> symbol={}", mapping.hd.instrument_id, raw_symbol);
-                            
-                            // Store the mapping
-                            {
-                                let mut mappings = state.symbol_mapping.write().await;
-                                mappings.insert(mapping.hd.instrument_id, raw_symbol.clone());
-                            }
-                        }
-                        
-                        // Handle trade messages
-                        if let Some(trade) = rec.get::<TradeMsg>() {
-                            let px = trade.price as f64 / 1_000_000_000.0;
-                            let inst = trade.hd.instrument_id;
-                            
-                            // Get the actual symbol from the mapping
-                            let symbol = {
-                                let mappings = state.symbol_mapping.read().await;
-                                mappings.get(&inst).cloned().unwrap_or_else(|| format!("INST:{}", inst))
-                            };
-                            
-                            info!("Live trade: instrument_id={}, symbol={}, price=${:.4}", inst, symbol, px);
-                            
-                            // Store price data with actual symbol
-                            {
-                                let mut map = state.prices.write().await;
-                                map.insert(symbol.clone(), LastPrice { price: Some(px), ts_event_ns: Some(trade.hd.ts_event) });
-                            }
-                            
-                            // Send price update via WebSocket with actual symbol
-                            let price_update = PriceUpdate {
-                                symbol: symbol,
-                                price: px,
-                                timestamp: trade.hd.ts_event,
-                            };
-                            
-                            if let Err(e) = state.price_sender.send(price_update) {
-                                warn!("Failed to send price update: {}", e);
-                            }
-                        }
-                    }
-                    Ok(None) => {
-                        info!("Databento stream ended");
-                        client_started = false;
-                        break;
-                    }
-                    Err(e) => {
-                        error!("Databento client error: {}", e);
-                        client_started = false;
-                        // Try to reconnect after a delay
-                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                        break;
-                    }
-                }
-            }
-        }
+        interval.tick().await;
+        close_stale_bars(&state).await;
+    }
+}
+
+async fn close_stale_bars(state: &AppState) {
+    let now = current_time_ns();
+    let today = calendar::exchange_date(now);
+    let closed: Vec<bars::Bar> = {
+        let mut bars = state.bars.write().await;
+        let closed = bars.values_mut().filter_map(|series| series.close_if_stale(now)).collect();
+        // Yesterday's session is over; today's series start empty
+        bars.values_mut().for_each(|series| series.roll(today));
+        closed
+    };
+    for bar in closed {
+        publish_bar_close(state, bar).await;
     }
-    
-    info!("Databento client manager stopped");
 }
 
-async fn start_websocket_broadcaster(url: String, mut price_receiver: mpsc::UnboundedReceiver<PriceUpdate>) {
+async fn start_websocket_broadcaster(url: String, mut event_receiver: broadcast::Receiver<std::sync::Arc<Envelope>>) {
     loop {
         info!("Attempting to connect to WebSocket at {}", url);
         match connect_async(&url).await {
@@ -581,17 +715,26 @@ async fn start_websocket_broadcaster(url: String, mut price_receiver: mpsc::Unbo
                 // Send price updates via WebSocket
                 loop {
                     tokio::select! {
-                        price_update = price_receiver.recv() => {
-                            if let Some(update) = price_update {
-                                let msg = Message::Text(serde_json::to_string(&update).unwrap());
-                                if let Err(e) = ws_sender.send(msg).await {
-                                    error!("Failed to send price update: {}", e);
+                        event = event_receiver.recv() => {
+                            match event {
+                                Ok(envelope) => {
+                                    // The Node server only understands price updates; the envelope
+                                    // adds v/seq/symbol_seq alongside the usual fields
+                                    let StreamEvent::Price(update) = &envelope.event else { continue };
+                                    let msg = Message::Text(serde_json::to_string(envelope.as_ref()).unwrap());
+                                    if let Err(e) = ws_sender.send(msg).await {
+                                        error!("Failed to send price update: {}", e);
+                                        break;
+                                    }
+                                    info!("Broadcasted price: {} @ ${:.4}", update.symbol, update.price);
+                                }
+                                Err(broadcast::error::RecvError::Lagged(n)) => {
+                                    warn!("WebSocket broadcaster lagged, dropped {} events", n);
+                                }
+                                Err(broadcast::error::RecvError::Closed) => {
+                                    warn!("Event channel closed");
                                     break;
                                 }
-                                info!("Broadcasted price: {} @ ${:.4}", update.symbol, update.price);
-                            } else {
-                                warn!("Price receiver channel closed");
-                                break;
                             }
                         }
                         ws_msg = ws_receiver.next() => {
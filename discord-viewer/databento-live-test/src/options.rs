@@ -0,0 +1,374 @@
+// Options (OPRA) support: per-contract last/bid/ask, per-underlying volume
+// aggregates, and the HTTP handlers for chain snapshots and call/put ratios.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use chrono::NaiveDate;
+use databento::dbn::{CbboMsg, TradeMsg, UNDEF_PRICE};
+
+use crate::{
+    calendar, norm_symbol,
+    resolve::{self, Resolution},
+    sessions::SessionCommand,
+    AppState,
+};
+
+// Parent-symbology suffix Databento uses for "all options on an underlying"
+const PARENT_SUFFIX: &str = ".OPT";
+
+pub fn options_dataset() -> String {
+    std::env::var("DATABENTO_OPRA_DATASET").unwrap_or_else(|_| "OPRA.PILLAR".to_string())
+}
+
+pub fn is_parent_symbol(symbol: &str) -> bool {
+    symbol.ends_with(PARENT_SUFFIX)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OptionContract {
+    pub symbol: String,
+    pub underlying: String,
+    pub expiration: String, // YYYY-MM-DD
+    pub strike: f64,
+    pub right: char, // 'C' or 'P'
+    pub last: Option<f64>,
+    pub last_size: Option<u32>,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    pub bid_size: Option<u32>,
+    pub ask_size: Option<u32>,
+    pub volume: u64,
+    pub ts_event_ns: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct UnderlyingVolume {
+    pub call_volume: u64,
+    pub put_volume: u64,
+    pub call_trades: u64,
+    pub put_trades: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct OptionsBook {
+    pub subscriptions: HashSet<String>, // "AAPL.OPT" parents and explicit OCC symbols
+    pub contracts: HashMap<String, OptionContract>,
+    pub volume: HashMap<String, UnderlyingVolume>, // underlying -> aggregate
+    session_date: Option<NaiveDate>, // trading day the volumes count
+}
+
+// Parse an OCC option symbol: root (padded to 6), YYMMDD, C/P, strike * 1000 (8 digits).
+// Accepts both the padded "AAPL  250117C00150000" and compact "AAPL250117C00150000" forms.
+pub fn parse_occ(symbol: &str) -> Option<(String, String, char, f64)> {
+    let s = symbol.trim().to_uppercase();
+    if s.len() < 16 || !s.is_ascii() {
+        return None;
+    }
+    let (root, tail) = s.split_at(s.len() - 15);
+    let root = root.trim();
+    if root.is_empty() || !root.chars().all(|c| c.is_ascii_alphanumeric() || c == '.') {
+        return None;
+    }
+    let (date, rest) = tail.split_at(6);
+    let (right, strike) = rest.split_at(1);
+    let right = right.chars().next()?;
+    if right != 'C' && right != 'P' || !date.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let expiration = format!("20{}-{}-{}", &date[0..2], &date[2..4], &date[4..6]);
+    let strike = strike.parse::<u64>().ok()? as f64 / 1000.0;
+    Some((root.to_string(), expiration, right, strike))
+}
+
+// Canonical padded OCC form as used by OPRA raw symbology
+pub fn canonical_occ(symbol: &str) -> Option<String> {
+    let (root, expiration, right, strike) = parse_occ(symbol)?;
+    let date = expiration.replace('-', "");
+    Some(format!("{:<6}{}{}{:08}", root, &date[2..], right, (strike * 1000.0).round() as u64))
+}
+
+pub fn px(raw: i64) -> Option<f64> {
+    if raw == UNDEF_PRICE {
+        None
+    } else {
+        Some(raw as f64 / 1_000_000_000.0)
+    }
+}
+
+impl OptionsBook {
+    fn contract(&mut self, symbol: &str) -> Option<&mut OptionContract> {
+        if !self.contracts.contains_key(symbol) {
+            let (underlying, expiration, right, strike) = parse_occ(symbol)?;
+            self.contracts.insert(symbol.to_string(), OptionContract {
+                symbol: symbol.to_string(),
+                underlying,
+                expiration,
+                strike,
+                right,
+                last: None,
+                last_size: None,
+                bid: None,
+                ask: None,
+                bid_size: None,
+                ask_size: None,
+                volume: 0,
+                ts_event_ns: None,
+            });
+        }
+        self.contracts.get_mut(symbol)
+    }
+
+    // Volumes are per session: the first trade of a new trading day starts them over
+    // and drops contracts that have expired
+    fn roll_session(&mut self, ts_ns: u64) {
+        let date = calendar::exchange_date(ts_ns);
+        if self.session_date.is_some_and(|d| d >= date) {
+            return;
+        }
+        self.session_date = Some(date);
+        let today = date.format("%Y-%m-%d").to_string();
+        self.contracts.retain(|_, c| c.expiration >= today);
+        for contract in self.contracts.values_mut() {
+            contract.volume = 0;
+        }
+        self.volume.clear();
+    }
+
+    pub fn apply_trade(&mut self, symbol: &str, trade: &TradeMsg) {
+        self.roll_session(trade.hd.ts_event);
+        let Some(contract) = self.contract(symbol) else { return };
+        contract.last = px(trade.price);
+        contract.last_size = Some(trade.size);
+        contract.volume += trade.size as u64;
+        contract.ts_event_ns = Some(trade.hd.ts_event);
+        let (underlying, right) = (contract.underlying.clone(), contract.right);
+
+        let agg = self.volume.entry(underlying).or_default();
+        if right == 'C' {
+            agg.call_volume += trade.size as u64;
+            agg.call_trades += 1;
+        } else {
+            agg.put_volume += trade.size as u64;
+            agg.put_trades += 1;
+        }
+    }
+
+    pub fn apply_quote(&mut self, symbol: &str, quote: &CbboMsg) {
+        let Some(contract) = self.contract(symbol) else { return };
+        let level = &quote.levels[0];
+        contract.bid = px(level.bid_px);
+        contract.ask = px(level.ask_px);
+        contract.bid_size = Some(level.bid_sz);
+        contract.ask_size = Some(level.ask_sz);
+    }
+}
+
+pub async fn apply_option_trade(state: &AppState, symbol: &str, trade: &TradeMsg) {
+    state.options.write().await.apply_trade(symbol, trade);
+}
+
+pub async fn apply_option_quote(state: &AppState, symbol: &str, quote: &CbboMsg) {
+    state.options.write().await.apply_quote(symbol, quote);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OptionsSubscribeBody {
+    #[serde(default)]
+    underlyings: Vec<String>,
+    #[serde(default)]
+    contracts: Vec<String>,
+}
+
+// POST /api/options/subscribe { underlyings: ["AAPL"], contracts: ["AAPL  250117C00150000"] }
+// Underlyings go through the same universe and blacklist checks as stock
+// subscriptions; contracts are refused when their root is blacklisted.
+pub async fn subscribe_options(
+    State(state): State<AppState>,
+    Json(body): Json<OptionsSubscribeBody>,
+) -> impl IntoResponse {
+    let underlyings: Vec<String> = body.underlyings.iter()
+        .map(|u| norm_symbol(u).trim_end_matches(PARENT_SUFFIX).to_string())
+        .filter(|u| !u.is_empty())
+        .collect();
+    let resolution = if underlyings.is_empty() { Resolution::default() } else { resolve::resolve_symbols(&state, &underlyings).await };
+
+    let mut invalid = Vec::new();
+    let mut blacklisted = Vec::new();
+    let mut symbols = Vec::new();
+    {
+        let blacklist = state.blacklist.read().await;
+        let (allowed, blocked) = blacklist.partition(resolution.accepted, None);
+        symbols.extend(allowed.into_iter().map(|u| format!("{}{}", u, PARENT_SUFFIX)));
+        blacklisted.extend(blocked);
+        for c in &body.contracts {
+            match parse_occ(c) {
+                Some((root, ..)) => match blacklist.check(&root, None, None) {
+                    Some(blocked) => blacklisted.push(blocked),
+                    None => symbols.extend(canonical_occ(c)),
+                },
+                None => invalid.push(c.clone()),
+            }
+        }
+    }
+    if !blacklisted.is_empty() {
+        info!("Refusing blacklisted option symbols: {:?}", blacklisted);
+    }
+
+    let new_symbols: Vec<String> = {
+        let mut book = state.options.write().await;
+        symbols.into_iter().filter(|s| book.subscriptions.insert(s.clone())).collect()
+    };
+
+    if !new_symbols.is_empty() {
+        info!("Options subscription for: {:?}", new_symbols);
+        if let Err(e) = state.session_sender.send(SessionCommand::SubscribeOptions(new_symbols.clone())) {
+            error!("Failed to send option symbols to session router: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Session router communication failed"
+            })));
+        }
+    }
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "status": "ok",
+        "subscribed": new_symbols,
+        "invalid": invalid,
+        "rejected": resolution.rejected,
+        "blacklisted": blacklisted,
+        "dataset": options_dataset(),
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChainQuery {
+    underlying: String,
+    #[serde(default)]
+    expiration: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct ChainRow {
+    expiration: String,
+    strike: f64,
+    call: Option<OptionContract>,
+    put: Option<OptionContract>,
+}
+
+// GET /api/options/chain?underlying=AAPL[&expiration=2025-01-17]
+pub async fn get_chain(Query(q): Query<ChainQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let underlying = norm_symbol(&q.underlying);
+    let book = state.options.read().await;
+
+    // Keyed by (expiration, strike in mills) so rows come out sorted
+    let mut rows: BTreeMap<(String, u64), ChainRow> = BTreeMap::new();
+    for c in book.contracts.values() {
+        if c.underlying != underlying || q.expiration.as_ref().is_some_and(|e| *e != c.expiration) {
+            continue;
+        }
+        let row = rows.entry((c.expiration.clone(), (c.strike * 1000.0).round() as u64)).or_insert_with(|| ChainRow {
+            expiration: c.expiration.clone(),
+            strike: c.strike,
+            ..Default::default()
+        });
+        if c.right == 'C' {
+            row.call = Some(c.clone());
+        } else {
+            row.put = Some(c.clone());
+        }
+    }
+
+    Json(serde_json::json!({
+        "underlying": underlying,
+        "volume": book.volume.get(&underlying).cloned().unwrap_or_default(),
+        "rows": rows.into_values().collect::<Vec<_>>(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RatioQuery {
+    underlyings: String,
+}
+
+// GET /api/options/ratios?underlyings=AAPL,TSLA
+pub async fn get_ratios(Query(q): Query<RatioQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let book = state.options.read().await;
+    let mut result = HashMap::new();
+    for u in q.underlyings.split(',').map(norm_symbol).filter(|s| !s.is_empty()) {
+        let v = book.volume.get(&u).cloned().unwrap_or_default();
+        let call_put = (v.put_volume > 0).then(|| v.call_volume as f64 / v.put_volume as f64);
+        let put_call = (v.call_volume > 0).then(|| v.put_volume as f64 / v.call_volume as f64);
+        result.insert(u, serde_json::json!({
+            "call_volume": v.call_volume,
+            "put_volume": v.put_volume,
+            "call_trades": v.call_trades,
+            "put_trades": v.put_trades,
+            "call_put_ratio": call_put,
+            "put_call_ratio": put_call,
+        }));
+    }
+    Json(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn occ_round_trip() {
+        for (input, padded) in [
+            ("AAPL  250117C00150000", "AAPL  250117C00150000"),
+            ("AAPL250117C00150000", "AAPL  250117C00150000"),
+            ("spy   261218p00612500", "SPY   261218P00612500"),
+            ("BRK.B 270115C00500000", "BRK.B 270115C00500000"),
+            ("GOOGL 261120P00001000", "GOOGL 261120P00001000"),
+        ] {
+            let canonical = canonical_occ(input).unwrap();
+            assert_eq!(canonical, padded);
+            assert_eq!(canonical_occ(&canonical).as_deref(), Some(padded));
+            assert_eq!(parse_occ(input), parse_occ(padded));
+        }
+        assert_eq!(
+            parse_occ("SPY   261218P00612500"),
+            Some(("SPY".to_string(), "2026-12-18".to_string(), 'P', 612.5)),
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_occ() {
+        for bad in ["", "AAPL", "250117C00150000", "AAPL  250117X00150000", "AAPL  25O117C00150000", "AAPL  250117C0015000X", "AA-PL 250117C00150000"] {
+            assert_eq!(parse_occ(bad), None, "{}", bad);
+        }
+    }
+
+    fn trade(size: u32, ts: &str) -> TradeMsg {
+        let mut msg = TradeMsg { price: 1_500_000_000, size, ..Default::default() };
+        msg.hd.ts_event = chrono::DateTime::parse_from_rfc3339(ts).unwrap().timestamp_nanos_opt().unwrap() as u64;
+        msg
+    }
+
+    #[test]
+    fn volume_resets_each_session() {
+        let mut book = OptionsBook::default();
+        book.apply_trade("AAPL  261016C00150000", &trade(10, "2026-10-15T14:00:00Z"));
+        book.apply_trade("AAPL  261120P00140000", &trade(5, "2026-10-15T15:00:00Z"));
+        assert_eq!(book.volume["AAPL"].call_volume, 10);
+        assert_eq!(book.volume["AAPL"].put_volume, 5);
+
+        // Next session: counts start over and the expired contract is gone
+        book.apply_trade("AAPL  261120P00140000", &trade(3, "2026-10-19T14:00:00Z"));
+        assert!(!book.contracts.contains_key("AAPL  261016C00150000"));
+        assert_eq!(book.contracts["AAPL  261120P00140000"].volume, 3);
+        assert_eq!(book.volume["AAPL"].call_volume, 0);
+        assert_eq!(book.volume["AAPL"].put_volume, 3);
+        assert_eq!(book.volume["AAPL"].put_trades, 1);
+    }
+}
@@ -0,0 +1,731 @@
+// Call outcomes. From the price at mention, follow each detected ticker until the
+// next regular close: max gain and max drawdown over that window, plus the price 5,
+// 15 and 60 minutes after the mention and at the close. Live trades drive it;
+// detections we learn about late (or that were open across a restart) are caught
+// up from historical minute bars. Results are stored on the detection's row, and
+// calls from earlier sessions leave memory once their outcome is final and written.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    bars::{self, Bar, BAR_NS},
+    calendar, current_time_ns, hist,
+    listen::DetectionPrice,
+    norm_symbol, sessions, start_live_subscription, to_dt, to_ns, AppState,
+};
+
+const NS_PER_MIN: u64 = 60 * 1_000_000_000;
+const HORIZONS_MIN: [u64; 3] = [5, 15, 60];
+
+// Prints reach us a little after their event time; don't freeze a horizon until
+// anything that printed before it has had time to arrive
+const FEED_LAG_NS: u64 = 2_000_000_000;
+
+// Detections older than this when tracking starts are caught up from history
+const CATCH_UP_AFTER_NS: u64 = 60_000_000_000;
+
+pub const COLUMNS: &str = "
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS max_price DOUBLE PRECISION;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS max_price_ts TIMESTAMP WITH TIME ZONE;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS min_price DOUBLE PRECISION;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS min_price_ts TIMESTAMP WITH TIME ZONE;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS max_gain_pct DOUBLE PRECISION;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS max_drawdown_pct DOUBLE PRECISION;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS price_5m DOUBLE PRECISION;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS price_15m DOUBLE PRECISION;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS price_60m DOUBLE PRECISION;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS close_price DOUBLE PRECISION;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS close_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE ticker_detection_prices ADD COLUMN IF NOT EXISTS outcome_complete BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE INDEX IF NOT EXISTS idx_detection_prices_detected_at ON ticker_detection_prices(detected_at);
+";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Outcome {
+    pub entry_price: f64,
+    pub close_at_ns: u64, // regular close the window runs to
+    pub max_price: f64,
+    pub max_price_ts_ns: u64,
+    pub min_price: f64,
+    pub min_price_ts_ns: u64,
+    pub max_gain_pct: f64,
+    pub max_drawdown_pct: f64, // <= 0
+    pub price_5m: Option<f64>,
+    pub price_15m: Option<f64>,
+    pub price_60m: Option<f64>,
+    pub close_price: Option<f64>,
+    pub complete: bool,
+    #[serde(skip)]
+    entry_ns: u64,
+    #[serde(skip)]
+    last_px: f64,
+    #[serde(skip)]
+    last_ts: u64,
+    // Live prints are ignored while history is being caught up
+    #[serde(skip)]
+    ready: bool,
+    // Changed since it was last persisted
+    #[serde(skip)]
+    dirty: bool,
+}
+
+fn pct(from: f64, to: f64) -> f64 {
+    if from > 0.0 { (to - from) / from * 100.0 } else { 0.0 }
+}
+
+impl Outcome {
+    pub fn new(entry_price: f64, entry_ns: u64) -> Self {
+        Outcome {
+            entry_price,
+            close_at_ns: to_ns(calendar::close_after(entry_ns)),
+            max_price: entry_price,
+            max_price_ts_ns: entry_ns,
+            min_price: entry_price,
+            min_price_ts_ns: entry_ns,
+            max_gain_pct: 0.0,
+            max_drawdown_pct: 0.0,
+            price_5m: None,
+            price_15m: None,
+            price_60m: None,
+            close_price: None,
+            complete: false,
+            entry_ns,
+            last_px: entry_price,
+            last_ts: entry_ns,
+            ready: current_time_ns().saturating_sub(entry_ns) < CATCH_UP_AFTER_NS,
+            dirty: true,
+        }
+    }
+
+    // False while the outcome still waits on historical catch-up
+    pub fn ready(&self) -> bool {
+        self.ready
+    }
+
+    fn horizon(&mut self, i: usize) -> &mut Option<f64> {
+        match i {
+            0 => &mut self.price_5m,
+            1 => &mut self.price_15m,
+            _ => &mut self.price_60m,
+        }
+    }
+
+    // Last window end: the close, or the furthest horizon if that comes later
+    fn end_ns(&self) -> u64 {
+        self.close_at_ns.max(self.entry_ns + HORIZONS_MIN[2] * NS_PER_MIN)
+    }
+
+    // Freeze every horizon, and the close, that lies before `ts_ns` at the last price seen
+    pub fn freeze(&mut self, ts_ns: u64) {
+        let (last, entry_ns) = (self.last_px, self.entry_ns);
+        for (i, minutes) in HORIZONS_MIN.iter().enumerate() {
+            let slot = self.horizon(i);
+            if slot.is_none() && ts_ns > entry_ns + minutes * NS_PER_MIN {
+                *slot = Some(last);
+                self.dirty = true;
+            }
+        }
+        if self.close_price.is_none() && ts_ns > self.close_at_ns {
+            self.close_price = Some(last);
+            self.dirty = true;
+        }
+        if self.close_price.is_some() && self.price_60m.is_some() && !self.complete {
+            self.complete = true;
+            self.dirty = true;
+        }
+    }
+
+    fn extend(&mut self, high: f64, low: f64, ts_ns: u64) {
+        if high > self.max_price {
+            self.max_price = high;
+            self.max_price_ts_ns = ts_ns;
+            self.max_gain_pct = pct(self.entry_price, high);
+            self.dirty = true;
+        }
+        if low < self.min_price {
+            self.min_price = low;
+            self.min_price_ts_ns = ts_ns;
+            self.max_drawdown_pct = pct(self.entry_price, low);
+            self.dirty = true;
+        }
+    }
+
+    // Same persisted columns; every one of them only ever moves forward
+    fn same_state(&self, other: &Outcome) -> bool {
+        self.max_price_ts_ns == other.max_price_ts_ns
+            && self.min_price_ts_ns == other.min_price_ts_ns
+            && self.price_5m == other.price_5m
+            && self.price_15m == other.price_15m
+            && self.price_60m == other.price_60m
+            && self.close_price == other.close_price
+            && self.complete == other.complete
+    }
+
+    pub fn observe(&mut self, px: f64, ts_ns: u64) {
+        if !self.ready || self.complete || ts_ns < self.last_ts {
+            return;
+        }
+        self.freeze(ts_ns);
+        if ts_ns <= self.close_at_ns {
+            self.extend(px, px, ts_ns);
+        }
+        self.last_px = px;
+        self.last_ts = ts_ns;
+    }
+
+    // Historical catch-up; the mention's own minute counts toward the high and low
+    fn observe_bar(&mut self, bar: &Bar) {
+        let end = bar.start_ns + BAR_NS - 1;
+        if self.complete || end < self.entry_ns {
+            return;
+        }
+        self.freeze(bar.start_ns);
+        if bar.start_ns < self.close_at_ns {
+            self.extend(bar.high, bar.low, bar.start_ns);
+        }
+        self.last_px = bar.close;
+        self.last_ts = end;
+    }
+}
+
+// Called for every live trade
+pub async fn on_trade(state: &AppState, symbol: &str, px: f64, ts_ns: u64) {
+    let tracking = state.detections.read().await
+        .get(symbol)
+        .is_some_and(|list| list.iter().any(|d| d.outcome.as_ref().is_some_and(|o| !o.complete)));
+    if !tracking {
+        return;
+    }
+    let mut detections = state.detections.write().await;
+    for outcome in detections.get_mut(symbol).into_iter().flatten().filter_map(|d| d.outcome.as_mut()) {
+        outcome.observe(px, ts_ns);
+    }
+}
+
+// Bring a late detection up to date from minute bars, then hand it to live tracking
+pub async fn catch_up(state: AppState, symbol: String, detected_at_ns: u64) {
+    let window = {
+        let detections = state.detections.read().await;
+        detections.get(&symbol)
+            .and_then(|list| list.iter().find(|d| d.detected_at_ns == detected_at_ns))
+            .and_then(|d| d.outcome.as_ref())
+            .filter(|o| !o.ready)
+            .map(|o| (o.entry_ns, o.end_ns()))
+    };
+    let Some((entry_ns, end_ns)) = window else { return };
+    let until_ns = bars::minute_start(current_time_ns()).min(bars::minute_start(end_ns) + BAR_NS);
+    let start = to_dt(bars::minute_start(entry_ns));
+    let end = to_dt(until_ns);
+
+    let history = if end > start {
+        match hist::minute_bars(&sessions::dataset_for(&state, &symbol).await, &symbol, start, end).await {
+            Ok(b) => b,
+            Err(e) => {
+                warn!("Outcome catch-up failed for {}: {}", symbol, e);
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    let mut detections = state.detections.write().await;
+    let outcome = detections.get_mut(&symbol)
+        .and_then(|list| list.iter_mut().find(|d| d.detected_at_ns == detected_at_ns))
+        .and_then(|d| d.outcome.as_mut());
+    if let Some(outcome) = outcome {
+        for bar in &history {
+            outcome.observe_bar(bar);
+        }
+        outcome.freeze(until_ns);
+        outcome.ready = true;
+        info!("Caught up outcome for {} from {} bars (complete={})", symbol, history.len(), outcome.complete);
+    }
+}
+
+// Freeze horizons as time passes, persist changed outcomes and prune finished calls
+pub async fn tracker(state: AppState) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
+    loop {
+        interval.tick().await;
+        track(&state).await;
+        let dropped = prune(&mut *state.detections.write().await, current_time_ns());
+        if dropped > 0 {
+            info!("Dropped {} finished calls from earlier sessions", dropped);
+        }
+    }
+}
+
+// Drop calls from before today's exchange date whose outcome is complete and
+// persisted, or that never got a price before their close. Returns how many went.
+fn prune(detections: &mut HashMap<String, Vec<DetectionPrice>>, now_ns: u64) -> usize {
+    let today = calendar::exchange_date(now_ns);
+    let mut dropped = 0;
+    detections.retain(|_, list| {
+        let before = list.len();
+        list.retain(|d| {
+            let finished = match &d.outcome {
+                Some(o) => o.complete && !o.dirty,
+                None => to_ns(calendar::close_after(d.detected_at_ns)) <= now_ns,
+            };
+            calendar::exchange_date(d.detected_at_ns) >= today || !finished
+        });
+        dropped += before - list.len();
+        !list.is_empty()
+    });
+    dropped
+}
+
+pub async fn track(state: &AppState) {
+    let now = current_time_ns().saturating_sub(FEED_LAG_NS);
+    let changed: Vec<DetectionPrice> = {
+        let mut detections = state.detections.write().await;
+        detections.values_mut()
+            .flatten()
+            .filter_map(|d| {
+                let outcome = d.outcome.as_mut().filter(|o| o.ready)?;
+                outcome.freeze(now);
+                outcome.dirty.then(|| d.clone())
+            })
+            .collect()
+    };
+    for record in &changed {
+        if !persist(state, record).await {
+            continue; // still dirty, retried on the next tick
+        }
+        // Only the state that was written is clean; newer prints keep it dirty
+        let mut detections = state.detections.write().await;
+        let current = detections.get_mut(&record.symbol)
+            .and_then(|list| list.iter_mut().find(|d| d.row_id == record.row_id))
+            .and_then(|d| d.outcome.as_mut());
+        if let (Some(current), Some(written)) = (current, &record.outcome) {
+            if current.same_state(written) {
+                current.dirty = false;
+            }
+        }
+    }
+}
+
+// False only when the write failed; without a database or row there is nothing to write
+async fn persist(state: &AppState, record: &DetectionPrice) -> bool {
+    let (Some(db), Some(row_id), Some(o)) = (&state.db, record.row_id, &record.outcome) else { return true };
+    let result = db.execute(
+        "UPDATE ticker_detection_prices
+         SET max_price = $2, max_price_ts = $3, min_price = $4, min_price_ts = $5,
+             max_gain_pct = $6, max_drawdown_pct = $7, price_5m = $8, price_15m = $9, price_60m = $10,
+             close_price = $11, close_at = $12, outcome_complete = $13
+         WHERE id = $1",
+        &[
+            &row_id,
+            &o.max_price,
+            &to_dt(o.max_price_ts_ns),
+            &o.min_price,
+            &to_dt(o.min_price_ts_ns),
+            &o.max_gain_pct,
+            &o.max_drawdown_pct,
+            &o.price_5m,
+            &o.price_15m,
+            &o.price_60m,
+            &o.close_price,
+            &to_dt(o.close_at_ns),
+            &o.complete,
+        ],
+    ).await;
+    match result {
+        Ok(_) => true,
+        Err(e) => {
+            error!("Failed to persist outcome for {} (row {}): {}", record.symbol, row_id, e);
+            false
+        }
+    }
+}
+
+// Pick up outcomes that were still open when the service last stopped; they are
+// recomputed from the mention price and caught up from history
+pub async fn resume(state: &AppState) {
+    let Some(db) = &state.db else { return };
+    let since = Utc::now() - chrono::Duration::days(7);
+    let rows = match db.query(
+        "SELECT id, ticker, message_id, author, confidence::float8, detected_at, price::float8, price_ts, price_source
+         FROM ticker_detection_prices
+         WHERE NOT outcome_complete AND price IS NOT NULL AND detected_at >= $1
+         ORDER BY detected_at",
+        &[&since],
+    ).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to load open outcomes: {}", e);
+            return;
+        }
+    };
+
+    let mut resumed = Vec::new();
+    {
+        let mut detections = state.detections.write().await;
+        for r in rows {
+            let detected_at: DateTime<Utc> = r.get(5);
+            let detected_at_ns = to_ns(detected_at);
+            let price: f64 = r.get(6);
+            let price_ts: Option<DateTime<Utc>> = r.get(7);
+            let price_source: Option<String> = r.get(8);
+            let symbol: String = r.get(1);
+            let list = detections.entry(symbol.clone()).or_default();
+            if list.iter().any(|d| d.detected_at_ns == detected_at_ns) {
+                continue;
+            }
+            let mut outcome = Outcome::new(price, detected_at_ns);
+            outcome.ready = false;
+            list.push(DetectionPrice {
+                symbol: symbol.clone(),
+                channel: "resumed".to_string(),
+                message_id: r.get(2),
+                author: r.get(3),
+                confidence: r.get(4),
+                detected_at_ns,
+                price: Some(price),
+                price_ts_ns: price_ts.map(to_ns),
+                price_source: match price_source.as_deref() {
+                    Some("live") => Some("live"),
+                    Some("historical") => Some("historical"),
+                    Some("first_live_trade") => Some("first_live_trade"),
+                    _ => None,
+                },
+                row_id: Some(r.get(0)),
+                outcome: Some(outcome),
+            });
+            resumed.push((symbol, detected_at_ns));
+        }
+    }
+    if resumed.is_empty() {
+        return;
+    }
+    info!("Resuming {} open call outcomes", resumed.len());
+
+    let mut symbols: Vec<String> = resumed.iter().map(|(s, _)| s.clone()).collect();
+    symbols.sort();
+    symbols.dedup();
+    for (symbol, detected_at_ns) in resumed {
+        catch_up(state.clone(), symbol, detected_at_ns).await;
+    }
+    // Anything still open needs the live feed
+    let open: Vec<String> = {
+        let detections = state.detections.read().await;
+        symbols.into_iter()
+            .filter(|s| detections.get(s).is_some_and(|list| list.iter().any(|d| d.outcome.as_ref().is_some_and(|o| !o.complete))))
+            .collect()
+    };
+    if !open.is_empty() {
+        let _ = start_live_subscription(open, state.clone()).await;
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CallOutcome {
+    pub id: Option<i32>,
+    pub symbol: String,
+    pub message_id: Option<String>,
+    pub author: Option<String>,
+    pub confidence: Option<f64>,
+    pub detected_at_ns: u64,
+    pub price: Option<f64>,
+    pub price_source: Option<String>,
+    pub max_price: Option<f64>,
+    pub max_gain_pct: Option<f64>,
+    pub max_drawdown_pct: Option<f64>,
+    pub price_5m: Option<f64>,
+    pub price_15m: Option<f64>,
+    pub price_60m: Option<f64>,
+    pub close_price: Option<f64>,
+    pub close_at_ns: Option<u64>,
+    pub close_return_pct: Option<f64>,
+    pub complete: bool,
+}
+
+impl CallOutcome {
+    pub fn from_detection(d: &DetectionPrice) -> Self {
+        let o = d.outcome.as_ref();
+        CallOutcome {
+            id: d.row_id,
+            symbol: d.symbol.clone(),
+            message_id: d.message_id.clone(),
+            author: d.author.clone(),
+            confidence: d.confidence,
+            detected_at_ns: d.detected_at_ns,
+            price: d.price,
+            price_source: d.price_source.map(str::to_string),
+            max_price: o.map(|o| o.max_price),
+            max_gain_pct: o.map(|o| o.max_gain_pct),
+            max_drawdown_pct: o.map(|o| o.max_drawdown_pct),
+            price_5m: o.and_then(|o| o.price_5m),
+            price_15m: o.and_then(|o| o.price_15m),
+            price_60m: o.and_then(|o| o.price_60m),
+            close_price: o.and_then(|o| o.close_price),
+            close_at_ns: o.map(|o| o.close_at_ns),
+            close_return_pct: o.and_then(|o| o.close_price.map(|c| pct(o.entry_price, c))),
+            complete: o.is_some_and(|o| o.complete),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutcomesQuery {
+    #[serde(default)]
+    symbol: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    // RFC3339; defaults to the last 7 days
+    #[serde(default)]
+    since: Option<String>,
+    #[serde(default)]
+    until: Option<String>,
+    #[serde(default)]
+    limit: Option<i64>,
+}
+
+pub fn parse_ts(s: Option<&str>) -> Result<Option<DateTime<Utc>>, String> {
+    s.map(|s| DateTime::parse_from_rfc3339(s).map(|dt| dt.with_timezone(&Utc)).map_err(|e| format!("invalid timestamp '{}': {}", s, e)))
+        .transpose()
+}
+
+async fn query_db(state: &AppState, q: &OutcomesQuery, symbol: Option<&String>, since: DateTime<Utc>, until: DateTime<Utc>) -> Option<Vec<CallOutcome>> {
+    let db = state.db.as_ref()?;
+    let rows = db.query(
+        "SELECT id, ticker, message_id, author, confidence::float8, detected_at, price::float8, price_source,
+                max_price, max_gain_pct, max_drawdown_pct, price_5m, price_15m, price_60m, close_price, close_at,
+                outcome_complete
+         FROM ticker_detection_prices
+         WHERE ($1::text IS NULL OR ticker = $1) AND ($2::text IS NULL OR author = $2)
+           AND detected_at >= $3 AND detected_at < $4
+         ORDER BY detected_at DESC
+         LIMIT $5",
+        &[&symbol, &q.author, &since, &until, &q.limit.unwrap_or(500)],
+    ).await;
+    match rows {
+        Ok(rows) => Some(rows.iter().map(|r| {
+            let price: Option<f64> = r.get(6);
+            let close_price: Option<f64> = r.get(14);
+            let detected_at: DateTime<Utc> = r.get(5);
+            let close_at: Option<DateTime<Utc>> = r.get(15);
+            CallOutcome {
+                id: r.get(0),
+                symbol: r.get(1),
+                message_id: r.get(2),
+                author: r.get(3),
+                confidence: r.get(4),
+                detected_at_ns: to_ns(detected_at),
+                price,
+                price_source: r.get(7),
+                max_price: r.get(8),
+                max_gain_pct: r.get(9),
+                max_drawdown_pct: r.get(10),
+                price_5m: r.get(11),
+                price_15m: r.get(12),
+                price_60m: r.get(13),
+                close_price,
+                close_at_ns: close_at.map(to_ns),
+                close_return_pct: price.zip(close_price).map(|(p, c)| pct(p, c)),
+                complete: r.get(16),
+            }
+        }).collect()),
+        Err(e) => {
+            error!("Failed to query call outcomes: {}", e);
+            None
+        }
+    }
+}
+
+// Averages over a set of call outcomes
+pub fn summarize(outcomes: &[CallOutcome]) -> serde_json::Value {
+    let avg = |f: fn(&CallOutcome) -> Option<f64>| {
+        let values: Vec<f64> = outcomes.iter().filter_map(f).collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    let closed: Vec<f64> = outcomes.iter().filter_map(|c| c.close_return_pct).collect();
+    serde_json::json!({
+        "count": outcomes.len(),
+        "complete": outcomes.iter().filter(|c| c.complete).count(),
+        "avg_max_gain_pct": avg(|c| c.max_gain_pct),
+        "avg_max_drawdown_pct": avg(|c| c.max_drawdown_pct),
+        "avg_close_return_pct": avg(|c| c.close_return_pct),
+        "green_at_close": closed.iter().filter(|r| **r > 0.0).count(),
+        "closed": closed.len()
+    })
+}
+
+// GET /api/detections/outcomes[?symbol=AAPL][&author=][&since=RFC3339][&until=RFC3339][&limit=500]
+// Per-detection call outcomes, newest first, with averages over the selection.
+// Served from Postgres when configured, otherwise from this process's detections.
+pub async fn get_outcomes(Query(q): Query<OutcomesQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let (since, until) = match (parse_ts(q.since.as_deref()), parse_ts(q.until.as_deref())) {
+        (Ok(since), Ok(until)) => (
+            since.unwrap_or_else(|| Utc::now() - chrono::Duration::days(7)),
+            until.unwrap_or_else(|| Utc::now() + chrono::Duration::minutes(1)),
+        ),
+        (Err(e), _) | (_, Err(e)) => return (http::StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": e}))),
+    };
+    let symbol = q.symbol.as_deref().map(norm_symbol).filter(|s| !s.is_empty());
+
+    let outcomes = match query_db(&state, &q, symbol.as_ref(), since, until).await {
+        Some(outcomes) => outcomes,
+        None => {
+            let detections = state.detections.read().await;
+            let mut outcomes: Vec<CallOutcome> = detections.values()
+                .flatten()
+                .filter(|d| symbol.as_ref().is_none_or(|s| *s == d.symbol))
+                .filter(|d| q.author.is_none() || d.author == q.author)
+                .map(CallOutcome::from_detection)
+                .filter(|c| c.detected_at_ns >= to_ns(since) && c.detected_at_ns < to_ns(until))
+                .collect();
+            outcomes.sort_by_key(|c| std::cmp::Reverse(c.detected_at_ns));
+            outcomes.truncate(q.limit.unwrap_or(500).max(0) as usize);
+            outcomes
+        }
+    };
+
+    let summary = summarize(&outcomes);
+    (http::StatusCode::OK, Json(serde_json::json!({
+        "since": since.to_rfc3339(),
+        "until": until.to_rfc3339(),
+        "summary": summary,
+        "outcomes": outcomes
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: u64 = NS_PER_MIN;
+
+    fn ns(s: &str) -> u64 {
+        DateTime::parse_from_rfc3339(s).unwrap().timestamp_nanos_opt().unwrap() as u64
+    }
+
+    // A call at 10:00 ET on a Thursday; the close is at 16:00 ET
+    fn outcome(entry_price: f64) -> (Outcome, u64) {
+        let entry = ns("2026-10-15T14:00:00Z");
+        let mut o = Outcome::new(entry_price, entry);
+        o.ready = true;
+        (o, entry)
+    }
+
+    #[test]
+    fn horizons_take_the_last_price_before_them() {
+        let (mut o, entry) = outcome(10.0);
+        assert_eq!(o.close_at_ns, ns("2026-10-15T20:00:00Z"));
+        o.observe(11.0, entry + 2 * MIN);
+        o.observe(12.0, entry + 4 * MIN);
+        assert_eq!(o.price_5m, None);
+        // The first print past five minutes freezes 5m at the print before it
+        o.observe(9.0, entry + 6 * MIN);
+        assert_eq!(o.price_5m, Some(12.0));
+        assert_eq!(o.price_15m, None);
+        assert_eq!((o.max_price, o.max_price_ts_ns), (12.0, entry + 4 * MIN));
+        assert_eq!((o.min_price, o.min_price_ts_ns), (9.0, entry + 6 * MIN));
+        assert!((o.max_gain_pct - 20.0).abs() < 1e-9);
+        assert!((o.max_drawdown_pct + 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn freeze_without_prints_uses_the_last_price() {
+        let (mut o, entry) = outcome(10.0);
+        o.observe(10.5, entry + MIN);
+        o.freeze(entry + 20 * MIN);
+        assert_eq!((o.price_5m, o.price_15m, o.price_60m), (Some(10.5), Some(10.5), None));
+        o.freeze(entry + 61 * MIN);
+        assert_eq!(o.price_60m, Some(10.5));
+        assert!(!o.complete);
+    }
+
+    #[test]
+    fn completes_at_the_close() {
+        let (mut o, entry) = outcome(10.0);
+        o.observe(10.2, entry + 90 * MIN);
+        o.observe(10.4, o.close_at_ns - MIN);
+        // After-hours prints neither move the extremes nor the close
+        o.observe(15.0, o.close_at_ns + MIN);
+        assert_eq!(o.close_price, Some(10.4));
+        assert_eq!(o.max_price, 10.4);
+        assert_eq!(o.price_60m, Some(10.0));
+        assert!(o.complete);
+        o.observe(1.0, o.close_at_ns + 2 * MIN);
+        assert_eq!(o.min_price, 10.0);
+    }
+
+    #[test]
+    fn late_calls_wait_for_the_last_horizon() {
+        // 15:30 ET: the close comes before the 60 minute mark
+        let entry = ns("2026-10-15T19:30:00Z");
+        let mut o = Outcome::new(5.0, entry);
+        o.ready = true;
+        o.freeze(o.close_at_ns + MIN);
+        assert_eq!(o.close_price, Some(5.0));
+        assert!(!o.complete);
+        assert_eq!(o.end_ns(), entry + 60 * MIN);
+        o.freeze(entry + 61 * MIN);
+        assert!(o.complete);
+    }
+
+    #[test]
+    fn ignores_prints_until_ready_and_out_of_order() {
+        let (mut o, entry) = outcome(10.0);
+        o.ready = false;
+        o.observe(20.0, entry + MIN);
+        assert_eq!(o.max_price, 10.0);
+        o.ready = true;
+        o.observe(11.0, entry + 3 * MIN);
+        o.observe(30.0, entry + 2 * MIN);
+        assert_eq!(o.max_price, 11.0);
+    }
+
+    fn call(symbol: &str, detected_at_ns: u64, outcome: Option<Outcome>) -> DetectionPrice {
+        DetectionPrice {
+            symbol: symbol.into(),
+            channel: "general".into(),
+            message_id: None,
+            author: None,
+            confidence: None,
+            detected_at_ns,
+            price: outcome.as_ref().map(|o| o.entry_price),
+            price_ts_ns: None,
+            price_source: None,
+            outcome,
+            row_id: None,
+        }
+    }
+
+    #[test]
+    fn prunes_finished_calls_from_earlier_sessions() {
+        let (mut done, entry) = outcome(10.0);
+        done.freeze(done.close_at_ns + 61 * MIN);
+        assert!(done.complete);
+        let mut unwritten = done.clone();
+        done.dirty = false;
+        unwritten.dirty = true;
+        let (open, _) = outcome(10.0);
+        let mut detections = HashMap::new();
+        detections.insert("AAPL".to_string(), vec![call("AAPL", entry, Some(done.clone())), call("AAPL", entry, Some(unwritten))]);
+        detections.insert("MSFT".to_string(), vec![call("MSFT", entry, Some(done)), call("MSFT", entry, None)]);
+        detections.insert("TSLA".to_string(), vec![call("TSLA", entry, Some(open))]);
+
+        // Nothing goes on the day of the calls
+        assert_eq!(prune(&mut detections, ns("2026-10-15T23:00:00Z")), 0);
+
+        // Next session: the written outcome and the call that never got a price go;
+        // unwritten and unfinished outcomes stay
+        assert_eq!(prune(&mut detections, ns("2026-10-16T14:00:00Z")), 3);
+        assert_eq!(detections["AAPL"].len(), 1);
+        assert!(detections["AAPL"][0].outcome.as_ref().unwrap().dirty);
+        assert!(!detections.contains_key("MSFT"));
+        assert_eq!(detections["TSLA"].len(), 1);
+    }
+}
@@ -0,0 +1,1426 @@
+// Paper trading for "follow this trader" strategies. A strategy follows a set of
+// authors: their detected mentions open hypothetical long positions, sized by the
+// strategy, and filled off the live quote with slippage proportional to the spread.
+// Positions close on the author's exit messages (one naming no ticker closes their
+// latest position), stop and target levels, a holding time limit or the regular
+// close, and are marked to market on every live trade.
+// Strategies, positions and fills live in Postgres; P&L curves are kept per
+// strategy and per followed author.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::OnceLock;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::DateTime;
+use http::StatusCode;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{calendar, current_time_ns, env, listen::DetectionPrice, norm_symbol, start_live_subscription, to_dt, to_ns, AppState};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+const CURVE_LEN: usize = 5000;
+// Fills kept in memory, newest last; older ones stay in paper_fills
+const FILLS_LEN: usize = 20_000;
+
+const CREATE_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS paper_strategies (
+  id BIGINT PRIMARY KEY,
+  name VARCHAR(255) NOT NULL,
+  config JSONB NOT NULL,
+  enabled BOOLEAN NOT NULL DEFAULT TRUE,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+CREATE TABLE IF NOT EXISTS paper_positions (
+  id BIGINT PRIMARY KEY,
+  strategy_id BIGINT NOT NULL REFERENCES paper_strategies(id) ON DELETE CASCADE,
+  author VARCHAR(255) NOT NULL,
+  symbol VARCHAR(10) NOT NULL,
+  message_id VARCHAR(50),
+  status VARCHAR(10) NOT NULL,
+  qty DOUBLE PRECISION NOT NULL,
+  signal_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  entry_price DOUBLE PRECISION,
+  entry_at TIMESTAMP WITH TIME ZONE,
+  stop_price DOUBLE PRECISION,
+  target_price DOUBLE PRECISION,
+  exit_price DOUBLE PRECISION,
+  exit_at TIMESTAMP WITH TIME ZONE,
+  exit_reason VARCHAR(20),
+  exit_message_id VARCHAR(50),
+  realized_pnl DOUBLE PRECISION
+);
+CREATE INDEX IF NOT EXISTS idx_paper_positions_strategy ON paper_positions(strategy_id, status);
+CREATE TABLE IF NOT EXISTS paper_fills (
+  id BIGINT PRIMARY KEY,
+  position_id BIGINT NOT NULL REFERENCES paper_positions(id) ON DELETE CASCADE,
+  strategy_id BIGINT NOT NULL,
+  author VARCHAR(255) NOT NULL,
+  symbol VARCHAR(10) NOT NULL,
+  side VARCHAR(4) NOT NULL,
+  qty DOUBLE PRECISION NOT NULL,
+  price DOUBLE PRECISION NOT NULL,
+  ref_price DOUBLE PRECISION NOT NULL,
+  reason VARCHAR(20) NOT NULL,
+  filled_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+";
+
+// Mentions older than this when they reach us are history, not signals
+fn max_signal_age_ns() -> u64 {
+    env("PAPER_MAX_SIGNAL_AGE_SECS", 300u64) * NS_PER_SEC
+}
+
+// How long the extractor gets to find a ticker in a message before it counts as
+// having none
+fn ticker_grace() -> tokio::time::Duration {
+    let secs = env("PAPER_TICKER_GRACE_SECS", 10u64);
+    tokio::time::Duration::from_secs(secs)
+}
+
+fn pattern(var: &str, default: &str) -> Regex {
+    let src = std::env::var(var).unwrap_or_else(|_| default.to_string());
+    RegexBuilder::new(&src).case_insensitive(true).build().unwrap_or_else(|e| {
+        warn!("Invalid {} ({}); using the default", var, e);
+        RegexBuilder::new(default).case_insensitive(true).build().expect("default pattern")
+    })
+}
+
+fn exit_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| pattern(
+        "PAPER_EXIT_PATTERN",
+        r"\b(sold|selling|sell(ing)? all|out of|i'?m out|all out|exit(ed|ing)?|closed (it|out|my|the|position)|took profits?|stopped out|cut (it|loss))\b",
+    ))
+}
+
+fn entry_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| pattern(
+        "PAPER_ENTRY_PATTERN",
+        r"\b(i'?m in|got in|in (here|at|on)|entry|entered|bought|buying|went long|starter|added|adding|grabbed)\b",
+    ))
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Sizing {
+    Notional(f64),  // dollars per position
+    Shares(f64),
+    EquityPct(f64), // percent of current equity
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StrategyConfig {
+    pub authors: Vec<String>,
+    pub sizing: Sizing,
+    pub starting_equity: f64,
+    #[serde(default)]
+    pub stop_pct: Option<f64>,   // below entry
+    #[serde(default)]
+    pub target_pct: Option<f64>, // above entry
+    #[serde(default)]
+    pub max_hold_mins: Option<u64>,
+    #[serde(default = "default_true")]
+    pub flat_at_close: bool,
+    #[serde(default)]
+    pub max_open: Option<usize>,
+    // Fill price relative to the mid, in spreads: 0.5 fills at the touch, more pays through it
+    #[serde(default = "default_slippage_spreads")]
+    pub slippage_spreads: f64,
+    // Applied to the last trade when there is no quote
+    #[serde(default = "default_fallback_bps")]
+    pub fallback_slippage_bps: f64,
+    // Only open on mentions that read like an entry ("I'm in", "bought", "starter", ...)
+    #[serde(default)]
+    pub require_entry_keyword: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_slippage_spreads() -> f64 {
+    0.5
+}
+
+fn default_fallback_bps() -> f64 {
+    10.0
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Strategy {
+    pub id: u64,
+    pub name: String,
+    pub config: StrategyConfig,
+    pub enabled: bool,
+    pub created_at_ns: u64,
+}
+
+impl Strategy {
+    fn follows(&self, author: &str) -> bool {
+        self.enabled && self.config.authors.iter().any(|a| a.eq_ignore_ascii_case(author))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionStatus {
+    Pending, // waiting for a first live price
+    Open,
+    Closed,
+}
+
+impl PositionStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PositionStatus::Pending => "pending",
+            PositionStatus::Open => "open",
+            PositionStatus::Closed => "closed",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(PositionStatus::Pending),
+            "open" => Some(PositionStatus::Open),
+            "closed" => Some(PositionStatus::Closed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Position {
+    pub id: u64,
+    pub strategy_id: u64,
+    pub author: String,
+    pub symbol: String,
+    pub message_id: Option<String>,
+    pub status: PositionStatus,
+    pub qty: f64,
+    pub signal_ns: u64,
+    pub entry_price: Option<f64>,
+    pub entry_ns: Option<u64>,
+    pub stop_price: Option<f64>,
+    pub target_price: Option<f64>,
+    pub exit_price: Option<f64>,
+    pub exit_ns: Option<u64>,
+    pub exit_reason: Option<String>, // exit_message | stop | target | time | close | manual
+    pub exit_message_id: Option<String>,
+    pub mark_price: Option<f64>,
+    pub unrealized_pnl: f64,
+    pub realized_pnl: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Fill {
+    pub id: u64,
+    pub position_id: u64,
+    pub strategy_id: u64,
+    pub author: String,
+    pub symbol: String,
+    pub side: &'static str, // buy | sell
+    pub qty: f64,
+    pub price: f64,
+    pub ref_price: f64, // mid (or last trade) the slippage was applied to
+    pub reason: String,
+    pub ts_ns: u64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CurvePoint {
+    pub ts_ns: u64,
+    pub realized: f64,
+    pub unrealized: f64,
+    pub pnl: f64,
+}
+
+#[derive(Debug, Default)]
+struct Curve(Vec<CurvePoint>);
+
+impl Curve {
+    // Marks within a minute collapse into one point; fills always add one
+    fn push(&mut self, ts_ns: u64, realized: f64, unrealized: f64) {
+        let point = CurvePoint { ts_ns, realized, unrealized, pnl: realized + unrealized };
+        if let Some(last) = self.0.last_mut() {
+            if (last.realized - realized).abs() < 1e-9 && ts_ns / (60 * NS_PER_SEC) == last.ts_ns / (60 * NS_PER_SEC) {
+                *last = point;
+                return;
+            }
+        }
+        self.0.push(point);
+        if self.0.len() > CURVE_LEN {
+            self.0.remove(0);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PaperBook {
+    next_strategy_id: u64,
+    next_position_id: u64,
+    next_fill_id: u64,
+    strategies: BTreeMap<u64, Strategy>,
+    positions: BTreeMap<u64, Position>,
+    fills: VecDeque<Fill>,
+    curves: HashMap<(u64, Option<String>), Curve>, // (strategy, author) ; None for the whole strategy
+    totals: HashMap<(u64, Option<String>), (f64, f64)>, // same keys: running (realized, unrealized)
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Summary {
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub pnl: f64,
+    pub open: usize,
+    pub pending: usize,
+    pub closed: usize,
+    pub wins: usize,
+    pub win_rate: Option<f64>,
+}
+
+// Fill price for a market order: off the quote when there is one, else the last trade
+fn fill_price(config: &StrategyConfig, buy: bool, quote: Option<(f64, f64)>, last: Option<f64>) -> Option<(f64, f64)> {
+    let sign = if buy { 1.0 } else { -1.0 };
+    match (quote, last) {
+        (Some((bid, ask)), _) => {
+            let mid = (bid + ask) / 2.0;
+            Some((mid + sign * config.slippage_spreads * (ask - bid), mid))
+        }
+        (None, Some(last)) => Some((last * (1.0 + sign * config.fallback_slippage_bps / 10_000.0), last)),
+        (None, None) => None,
+    }
+}
+
+impl PaperBook {
+    fn summary(&self, strategy_id: u64, author: Option<&str>) -> Summary {
+        let mut s = Summary::default();
+        let positions = self.positions.values()
+            .filter(|p| p.strategy_id == strategy_id && author.is_none_or(|a| p.author.eq_ignore_ascii_case(a)));
+        for p in positions {
+            s.realized_pnl += p.realized_pnl;
+            s.unrealized_pnl += p.unrealized_pnl;
+            match p.status {
+                PositionStatus::Pending => s.pending += 1,
+                PositionStatus::Open => s.open += 1,
+                PositionStatus::Closed => {
+                    s.closed += 1;
+                    if p.realized_pnl > 0.0 {
+                        s.wins += 1;
+                    }
+                }
+            }
+        }
+        s.pnl = s.realized_pnl + s.unrealized_pnl;
+        s.win_rate = (s.closed > 0).then(|| s.wins as f64 / s.closed as f64);
+        s
+    }
+
+    fn equity(&self, strategy: &Strategy) -> f64 {
+        let (realized, unrealized) = self.totals.get(&(strategy.id, None)).copied().unwrap_or_default();
+        strategy.config.starting_equity + realized + unrealized
+    }
+
+    // Apply a P&L change of one of the author's positions to the running totals
+    fn adjust(&mut self, strategy_id: u64, author: &str, realized: f64, unrealized: f64) {
+        for key in [(strategy_id, None), (strategy_id, Some(author.to_lowercase()))] {
+            let total = self.totals.entry(key).or_default();
+            total.0 += realized;
+            total.1 += unrealized;
+        }
+    }
+
+    // Mark an open position at `px`
+    fn mark(&mut self, id: u64, px: f64) {
+        let Some(p) = self.positions.get_mut(&id) else { return };
+        let before = p.unrealized_pnl;
+        p.mark_price = Some(px);
+        p.unrealized_pnl = p.qty * (px - p.entry_price.unwrap_or(px));
+        let (strategy_id, author, delta) = (p.strategy_id, p.author.clone(), p.unrealized_pnl - before);
+        self.adjust(strategy_id, &author, 0.0, delta);
+    }
+
+    // Cash tied up in open positions
+    fn invested(&self, strategy_id: u64) -> f64 {
+        self.positions.values()
+            .filter(|p| p.strategy_id == strategy_id && p.status == PositionStatus::Open)
+            .map(|p| p.qty * p.entry_price.unwrap_or(0.0))
+            .sum()
+    }
+
+    fn record_curves(&mut self, strategy_id: u64, author: &str, ts_ns: u64) {
+        for key in [(strategy_id, None), (strategy_id, Some(author.to_lowercase()))] {
+            let (realized, unrealized) = self.totals.get(&key).copied().unwrap_or_default();
+            self.curves.entry(key).or_default().push(ts_ns, realized, unrealized);
+        }
+    }
+
+    fn push_fill(&mut self, fill: Fill) {
+        self.fills.push_back(fill);
+        if self.fills.len() > FILLS_LEN {
+            self.fills.pop_front();
+        }
+    }
+
+    fn add_fill(&mut self, p: &Position, side: &'static str, price: f64, ref_price: f64, reason: &str, ts_ns: u64) -> Fill {
+        self.next_fill_id += 1;
+        let fill = Fill {
+            id: self.next_fill_id,
+            position_id: p.id,
+            strategy_id: p.strategy_id,
+            author: p.author.clone(),
+            symbol: p.symbol.clone(),
+            side,
+            qty: p.qty,
+            price,
+            ref_price,
+            reason: reason.to_string(),
+            ts_ns,
+        };
+        self.push_fill(fill.clone());
+        fill
+    }
+
+    // Buy a pending position; None if it can't be sized or afforded
+    fn open(&mut self, id: u64, quote: Option<(f64, f64)>, last: Option<f64>, ts_ns: u64) -> Option<Fill> {
+        let p = self.positions.get(&id)?.clone();
+        let strategy = self.strategies.get(&p.strategy_id)?.clone();
+        let (price, ref_price) = fill_price(&strategy.config, true, quote, last)?;
+        let qty = match strategy.config.sizing {
+            Sizing::Notional(dollars) => (dollars / price).floor(),
+            Sizing::Shares(shares) => shares.floor(),
+            Sizing::EquityPct(pct) => (self.equity(&strategy) * pct / 100.0 / price).floor(),
+        };
+        let cash = self.equity(&strategy) - self.invested(strategy.id);
+        if qty < 1.0 || qty * price > cash {
+            info!("Paper strategy {} skips {} for {}: size {} at {:.4} with {:.2} cash", strategy.id, p.symbol, p.author, qty, price, cash);
+            self.positions.remove(&id);
+            return None;
+        }
+        let p = self.positions.get_mut(&id)?;
+        p.status = PositionStatus::Open;
+        p.qty = qty;
+        p.entry_price = Some(price);
+        p.entry_ns = Some(ts_ns);
+        p.stop_price = strategy.config.stop_pct.map(|pct| price * (1.0 - pct / 100.0));
+        p.target_price = strategy.config.target_pct.map(|pct| price * (1.0 + pct / 100.0));
+        self.mark(id, last.unwrap_or(ref_price));
+        let p = self.positions.get(&id)?.clone();
+        let fill = self.add_fill(&p, "buy", price, ref_price, "entry", ts_ns);
+        self.record_curves(p.strategy_id, &p.author, ts_ns);
+        Some(fill)
+    }
+
+    fn close(&mut self, id: u64, quote: Option<(f64, f64)>, last: Option<f64>, reason: &str, ts_ns: u64) -> Option<Fill> {
+        let p = self.positions.get(&id)?.clone();
+        if p.status != PositionStatus::Open {
+            return None;
+        }
+        let strategy = self.strategies.get(&p.strategy_id)?;
+        let (price, ref_price) = fill_price(&strategy.config, false, quote, last.or(p.mark_price))?;
+        let p = self.positions.get_mut(&id)?;
+        p.status = PositionStatus::Closed;
+        p.exit_price = Some(price);
+        p.exit_ns = Some(ts_ns);
+        p.exit_reason = Some(reason.to_string());
+        p.realized_pnl = p.qty * (price - p.entry_price.unwrap_or(price));
+        let unrealized = std::mem::take(&mut p.unrealized_pnl);
+        let p = p.clone();
+        self.adjust(p.strategy_id, &p.author, p.realized_pnl, -unrealized);
+        let fill = self.add_fill(&p, "sell", price, ref_price, reason, ts_ns);
+        self.record_curves(p.strategy_id, &p.author, ts_ns);
+        Some(fill)
+    }
+
+    // Exit on the author's message: a pending entry is dropped, an open position sold.
+    // True when the position changed.
+    fn exit(&mut self, id: u64, quote: Option<(f64, f64)>, last: Option<f64>, message_id: Option<&String>, ts_ns: u64, fills: &mut Vec<Fill>) -> bool {
+        if self.positions.get(&id).is_some_and(|p| p.status == PositionStatus::Pending) {
+            self.positions.remove(&id);
+            return true;
+        }
+        let Some(fill) = self.close(id, quote, last, "exit_message", ts_ns) else { return false };
+        if let Some(p) = self.positions.get_mut(&id) {
+            p.exit_message_id = message_id.cloned();
+        }
+        fills.push(fill);
+        true
+    }
+
+    pub fn add_strategy(&mut self, name: &str, config: StrategyConfig, enabled: bool) -> Result<Strategy, &'static str> {
+        let config = normalize(config);
+        if let Some(e) = bad_config(&config) {
+            return Err(e);
+        }
+        self.next_strategy_id += 1;
+        let strategy = Strategy {
+            id: self.next_strategy_id,
+            name: name.trim().to_string(),
+            config,
+            enabled,
+            created_at_ns: current_time_ns(),
+        };
+        self.insert_strategy(strategy.clone());
+        Ok(strategy)
+    }
+
+    // Every strategy with its summary, positions and fills
+    pub fn report(&self) -> serde_json::Value {
+        let strategies: Vec<serde_json::Value> = self.strategies.values()
+            .map(|s| {
+                let mut json = strategy_json(self, s);
+                let positions: Vec<&Position> = self.positions.values().filter(|p| p.strategy_id == s.id).collect();
+                let fills: Vec<&Fill> = self.fills.iter().filter(|f| f.strategy_id == s.id).collect();
+                let curve: &[CurvePoint] = self.curves.get(&(s.id, None)).map(|c| c.0.as_slice()).unwrap_or(&[]);
+                json["positions"] = serde_json::json!(positions);
+                json["fills"] = serde_json::json!(fills);
+                json["curve"] = serde_json::json!(curve);
+                json
+            })
+            .collect();
+        serde_json::json!(strategies)
+    }
+
+    fn insert_strategy(&mut self, strategy: Strategy) {
+        self.next_strategy_id = self.next_strategy_id.max(strategy.id);
+        self.strategies.insert(strategy.id, strategy);
+    }
+}
+
+// Last trade and top of book for `symbol`
+async fn market(state: &AppState, symbol: &str) -> (Option<(f64, f64)>, Option<f64>) {
+    let quote = state.rules.read().await.quote(symbol);
+    let last = state.prices.read().await.get(symbol).filter(|p| p.live).and_then(|p| p.price);
+    (quote, last)
+}
+
+async fn message_content(state: &AppState, message_id: Option<&String>) -> Option<String> {
+    let (Some(db), Some(id)) = (&state.db, message_id) else { return None };
+    db.query_opt("SELECT content FROM messages WHERE id = $1", &[id]).await
+        .ok()
+        .flatten()
+        .and_then(|row| row.get::<_, Option<String>>(0))
+}
+
+// A detected mention from an author some strategy follows: an exit message closes
+// that author's open positions in the symbol, anything else may open one
+pub async fn on_detection(state: &AppState, record: &DetectionPrice) {
+    let Some(author) = record.author.as_deref() else { return };
+    if !state.paper.read().await.strategies.values().any(|s| s.follows(author)) {
+        return;
+    }
+    let content = message_content(state, record.message_id.as_ref()).await;
+    on_message(state, record, content.as_deref()).await;
+}
+
+// Same, with the message text already in hand
+pub async fn on_message(state: &AppState, record: &DetectionPrice, content: Option<&str>) {
+    let Some(author) = record.author.as_deref().filter(|a| !a.is_empty()) else { return };
+    let following: Vec<Strategy> = state.paper.read().await.strategies.values().filter(|s| s.follows(author)).cloned().collect();
+    if following.is_empty() {
+        return;
+    }
+    let now = current_time_ns();
+    if now.saturating_sub(record.detected_at_ns) > max_signal_age_ns() {
+        info!("Ignoring stale paper signal {} from {}", record.symbol, author);
+        return;
+    }
+    let is_exit = content.is_some_and(|c| exit_pattern().is_match(c));
+    let is_entry = content.is_some_and(|c| entry_pattern().is_match(c));
+    let (quote, last) = market(state, &record.symbol).await;
+
+    let mut changed = Vec::new();
+    let mut fills = Vec::new();
+    {
+        let mut book = state.paper.write().await;
+        for strategy in &following {
+            let mine: Vec<&Position> = book.positions.values()
+                .filter(|p| p.strategy_id == strategy.id && p.status != PositionStatus::Closed)
+                .collect();
+            let held: Vec<u64> = mine.iter()
+                .filter(|p| p.symbol == record.symbol && p.author.eq_ignore_ascii_case(author))
+                .map(|p| p.id)
+                .collect();
+            if is_exit {
+                for id in held {
+                    if book.exit(id, quote, last, record.message_id.as_ref(), now, &mut fills) {
+                        changed.push(id);
+                    }
+                }
+                continue;
+            }
+            if !held.is_empty() || (strategy.config.require_entry_keyword && !is_entry) {
+                continue;
+            }
+            if strategy.config.max_open.is_some_and(|max| mine.len() >= max) {
+                info!("Paper strategy {} at max open positions; skipping {}", strategy.id, record.symbol);
+                continue;
+            }
+            book.next_position_id += 1;
+            let id = book.next_position_id;
+            book.positions.insert(id, Position {
+                id,
+                strategy_id: strategy.id,
+                author: author.to_string(),
+                symbol: record.symbol.clone(),
+                message_id: record.message_id.clone(),
+                status: PositionStatus::Pending,
+                qty: 0.0,
+                signal_ns: record.detected_at_ns,
+                entry_price: None,
+                entry_ns: None,
+                stop_price: None,
+                target_price: None,
+                exit_price: None,
+                exit_ns: None,
+                exit_reason: None,
+                exit_message_id: None,
+                mark_price: None,
+                unrealized_pnl: 0.0,
+                realized_pnl: 0.0,
+            });
+            if quote.is_some() || last.is_some() {
+                match book.open(id, quote, last, now) {
+                    Some(fill) => {
+                        fills.push(fill);
+                        changed.push(id);
+                    }
+                    None => continue,
+                }
+            } else {
+                info!("Paper position {} for {} waits for a live price", id, record.symbol);
+                changed.push(id);
+            }
+        }
+    }
+    save(state, &changed, &fills).await;
+}
+
+#[derive(Debug, Deserialize)]
+struct NewMessage {
+    id: serde_json::Value,
+    author_id: Option<serde_json::Value>,
+    content: Option<String>,
+}
+
+fn json_text(v: &serde_json::Value) -> String {
+    v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string())
+}
+
+// A `new_message` NOTIFY (create-message-trigger.sql). An exit message that names no
+// ticker ("sold", "I'm out") closes the author's most recent position in every
+// strategy following them; one that names a ticker is handled by on_detection.
+pub async fn on_new_message(state: &AppState, payload: &str) {
+    let Ok(msg) = serde_json::from_str::<NewMessage>(payload) else { return };
+    let Some(content) = msg.content.filter(|c| exit_pattern().is_match(c)) else { return };
+    let (Some(db), Some(author_id)) = (&state.db, msg.author_id.as_ref().map(json_text)) else { return };
+    if state.paper.read().await.strategies.values().all(|s| !s.enabled) {
+        return;
+    }
+    let author = match db.query_opt("SELECT username FROM authors WHERE id::text = $1", &[&author_id]).await {
+        Ok(Some(row)) => row.get::<_, Option<String>>(0),
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Failed to look up author {}: {}", author_id, e);
+            None
+        }
+    };
+    let Some(author) = author else { return };
+    let now = current_time_ns();
+    let targets = latest_positions(state, &author).await;
+    if targets.is_empty() || targets.iter().any(|(_, symbol)| names(&content, symbol)) {
+        return;
+    }
+    // Prices as of the message; the fill only waits on the ticker check
+    let mut markets = HashMap::new();
+    for (_, symbol) in &targets {
+        if !markets.contains_key(symbol) {
+            markets.insert(symbol.clone(), market(state, symbol).await);
+        }
+    }
+
+    let message_id = json_text(&msg.id);
+    tokio::time::sleep(ticker_grace()).await;
+    match db.query_opt("SELECT 1 FROM ticker_detections WHERE message_id::text = $1 LIMIT 1", &[&message_id]).await {
+        Ok(None) => {}
+        Ok(Some(_)) => return,
+        Err(e) => {
+            warn!("Failed to check message {} for tickers: {}", message_id, e);
+            return;
+        }
+    }
+    let mut changed = Vec::new();
+    let mut fills = Vec::new();
+    {
+        let mut book = state.paper.write().await;
+        for (id, symbol) in &targets {
+            let (quote, last) = markets.get(symbol).copied().unwrap_or_default();
+            if book.exit(*id, quote, last, Some(&message_id), now, &mut fills) {
+                info!("Paper position {} in {} closed on {}'s exit message", id, symbol, author);
+                changed.push(*id);
+            }
+        }
+    }
+    save(state, &changed, &fills).await;
+}
+
+// The author's most recent position still pending or open, per following strategy
+async fn latest_positions(state: &AppState, author: &str) -> Vec<(u64, String)> {
+    let book = state.paper.read().await;
+    book.strategies.values()
+        .filter(|s| s.follows(author))
+        .filter_map(|s| {
+            book.positions.values()
+                .rev()
+                .find(|p| p.strategy_id == s.id && p.status != PositionStatus::Closed && p.author.eq_ignore_ascii_case(author))
+                .map(|p| (p.id, p.symbol.clone()))
+        })
+        .collect()
+}
+
+// Whether the message names the symbol, in capitals or as a cashtag
+fn names(content: &str, symbol: &str) -> bool {
+    content.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '$'))
+        .map(|word| word.trim_end_matches('.'))
+        .any(|word| match word.strip_prefix('$') {
+            Some(tag) => tag.eq_ignore_ascii_case(symbol),
+            None => word == symbol,
+        })
+}
+
+// Called for every live trade: fills pending entries, marks open positions and
+// checks stops and targets
+pub async fn on_trade(state: &AppState, symbol: &str, px: f64, ts_ns: u64) {
+    let active = state.paper.read().await.positions.values()
+        .any(|p| p.symbol == symbol && p.status != PositionStatus::Closed);
+    if !active {
+        return;
+    }
+    let quote = state.rules.read().await.quote(symbol);
+    let mut changed = Vec::new();
+    let mut fills = Vec::new();
+    {
+        let mut book = state.paper.write().await;
+        let ids: Vec<u64> = book.positions.values()
+            .filter(|p| p.symbol == symbol && p.status != PositionStatus::Closed)
+            .map(|p| p.id)
+            .collect();
+        for id in ids {
+            if book.positions.get(&id).is_some_and(|p| p.status == PositionStatus::Pending) {
+                if let Some(fill) = book.open(id, quote, Some(px), ts_ns) {
+                    fills.push(fill);
+                }
+                changed.push(id);
+                continue;
+            }
+            book.mark(id, px);
+            let Some(p) = book.positions.get(&id) else { continue };
+            let reason = if p.stop_price.is_some_and(|s| px <= s) {
+                Some("stop")
+            } else if p.target_price.is_some_and(|t| px >= t) {
+                Some("target")
+            } else {
+                None
+            };
+            let (strategy_id, author) = (p.strategy_id, p.author.clone());
+            match reason {
+                Some(reason) => {
+                    if let Some(fill) = book.close(id, quote, Some(px), reason, ts_ns) {
+                        fills.push(fill);
+                        changed.push(id);
+                    }
+                }
+                None => book.record_curves(strategy_id, &author, ts_ns),
+            }
+        }
+    }
+    save(state, &changed, &fills).await;
+}
+
+// Time limits and the flat-at-close rule; checked every few seconds. Entries that
+// never saw a live price are dropped once the signal is stale.
+pub async fn sweeper(state: AppState) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
+    loop {
+        interval.tick().await;
+        sweep(&state).await;
+    }
+}
+
+pub async fn sweep(state: &AppState) {
+    let now = current_time_ns();
+    let expired: Vec<u64> = {
+        let mut book = state.paper.write().await;
+        let expired: Vec<u64> = book.positions.values()
+            .filter(|p| p.status == PositionStatus::Pending && now.saturating_sub(p.signal_ns) > max_signal_age_ns())
+            .map(|p| p.id)
+            .collect();
+        for id in &expired {
+            book.positions.remove(id);
+        }
+        expired
+    };
+    if !expired.is_empty() {
+        info!("Dropped {} paper entries that never got a live price", expired.len());
+        save(state, &expired, &[]).await;
+    }
+    let due: Vec<(u64, String, &'static str)> = {
+        let book = state.paper.read().await;
+        book.positions.values()
+            .filter(|p| p.status == PositionStatus::Open)
+            .filter_map(|p| {
+                let config = &book.strategies.get(&p.strategy_id)?.config;
+                let entry_ns = p.entry_ns?;
+                if config.max_hold_mins.is_some_and(|m| now >= entry_ns + m * 60 * NS_PER_SEC) {
+                    return Some((p.id, p.symbol.clone(), "time"));
+                }
+                let close_ns = to_ns(calendar::close_after(entry_ns));
+                (config.flat_at_close && now >= close_ns).then(|| (p.id, p.symbol.clone(), "close"))
+            })
+            .collect()
+    };
+    for (id, symbol, reason) in due {
+        let (quote, last) = market(state, &symbol).await;
+        // Past the close the quote is extended hours; exit off the last print
+        let quote = if reason == "close" { None } else { quote };
+        let fill = state.paper.write().await.close(id, quote, last, reason, now);
+        if let Some(fill) = fill {
+            save(state, &[id], std::slice::from_ref(&fill)).await;
+        }
+    }
+}
+
+// Create the tables, load strategies, positions and fills, and rebuild the curves
+pub async fn load(state: &AppState) {
+    let Some(db) = &state.db else { return };
+    if let Err(e) = db.batch_execute(CREATE_TABLES).await {
+        error!("Failed to create paper trading tables: {}", e);
+        return;
+    }
+    let strategies = db.query("SELECT id, name, config, enabled, created_at FROM paper_strategies ORDER BY id", &[]).await;
+    let positions = db.query(
+        "SELECT id, strategy_id, author, symbol, message_id, status, qty, signal_at, entry_price, entry_at,
+                stop_price, target_price, exit_price, exit_at, exit_reason, exit_message_id, realized_pnl
+         FROM paper_positions ORDER BY id",
+        &[],
+    ).await;
+    let fills = db.query(
+        "SELECT id, position_id, strategy_id, author, symbol, side, qty, price, ref_price, reason, filled_at
+         FROM paper_fills ORDER BY filled_at, id",
+        &[],
+    ).await;
+    let (strategies, positions, fills) = match (strategies, positions, fills) {
+        (Ok(s), Ok(p), Ok(f)) => (s, p, f),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            error!("Failed to load paper trading state: {}", e);
+            return;
+        }
+    };
+
+    let mut book = state.paper.write().await;
+    for r in strategies {
+        let id = r.get::<_, i64>(0) as u64;
+        let config: serde_json::Value = r.get(2);
+        let config = match serde_json::from_value(config) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Paper strategy {} has an invalid config: {}", id, e);
+                continue;
+            }
+        };
+        book.insert_strategy(Strategy {
+            id,
+            name: r.get(1),
+            config,
+            enabled: r.get(3),
+            created_at_ns: to_ns(r.get(4)),
+        });
+    }
+    for r in positions {
+        let id = r.get::<_, i64>(0) as u64;
+        let status: String = r.get(5);
+        let Some(status) = PositionStatus::parse(&status) else { continue };
+        book.next_position_id = book.next_position_id.max(id);
+        book.positions.insert(id, Position {
+            id,
+            strategy_id: r.get::<_, i64>(1) as u64,
+            author: r.get(2),
+            symbol: r.get(3),
+            message_id: r.get(4),
+            status,
+            qty: r.get(6),
+            signal_ns: to_ns(r.get(7)),
+            entry_price: r.get(8),
+            entry_ns: r.get::<_, Option<DateTime<chrono::Utc>>>(9).map(to_ns),
+            stop_price: r.get(10),
+            target_price: r.get(11),
+            exit_price: r.get(12),
+            exit_ns: r.get::<_, Option<DateTime<chrono::Utc>>>(13).map(to_ns),
+            exit_reason: r.get(14),
+            exit_message_id: r.get(15),
+            mark_price: None,
+            unrealized_pnl: 0.0,
+            realized_pnl: r.get::<_, Option<f64>>(16).unwrap_or(0.0),
+        });
+    }
+
+    // Realized P&L curves from the fill history
+    let mut realized: HashMap<(u64, Option<String>), f64> = HashMap::new();
+    for r in fills {
+        let id = r.get::<_, i64>(0) as u64;
+        let position_id = r.get::<_, i64>(1) as u64;
+        let side: String = r.get(5);
+        let fill = Fill {
+            id,
+            position_id,
+            strategy_id: r.get::<_, i64>(2) as u64,
+            author: r.get(3),
+            symbol: r.get(4),
+            side: if side == "buy" { "buy" } else { "sell" },
+            qty: r.get(6),
+            price: r.get(7),
+            ref_price: r.get(8),
+            reason: r.get(9),
+            ts_ns: to_ns(r.get(10)),
+        };
+        book.next_fill_id = book.next_fill_id.max(id);
+        if fill.side == "sell" {
+            let entry = book.positions.get(&position_id).and_then(|p| p.entry_price).unwrap_or(fill.price);
+            let pnl = fill.qty * (fill.price - entry);
+            for key in [(fill.strategy_id, None), (fill.strategy_id, Some(fill.author.to_lowercase()))] {
+                let total = realized.entry(key.clone()).or_default();
+                *total += pnl;
+                book.curves.entry(key).or_default().push(fill.ts_ns, *total, 0.0);
+            }
+        }
+        book.push_fill(fill);
+    }
+    // Realized totals from the closed positions; nothing is marked until the first print
+    let closed: Vec<(u64, String, f64)> = book.positions.values()
+        .filter(|p| p.status == PositionStatus::Closed)
+        .map(|p| (p.strategy_id, p.author.clone(), p.realized_pnl))
+        .collect();
+    for (strategy_id, author, pnl) in closed {
+        book.adjust(strategy_id, &author, pnl, 0.0);
+    }
+
+    let mut symbols: Vec<String> = book.positions.values()
+        .filter(|p| p.status != PositionStatus::Closed)
+        .map(|p| p.symbol.clone())
+        .collect();
+    symbols.sort();
+    symbols.dedup();
+    info!("Loaded {} paper strategies, {} positions ({} symbols active)", book.strategies.len(), book.positions.len(), symbols.len());
+    drop(book);
+    if !symbols.is_empty() {
+        let _ = start_live_subscription(symbols, state.clone()).await;
+    }
+}
+
+async fn persist_strategy(state: &AppState, strategy: &Strategy) {
+    let Some(db) = &state.db else { return };
+    let config = serde_json::to_value(&strategy.config).unwrap_or_default();
+    let result = db.execute(
+        "INSERT INTO paper_strategies (id, name, config, enabled, created_at)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, config = EXCLUDED.config, enabled = EXCLUDED.enabled",
+        &[&(strategy.id as i64), &strategy.name, &config, &strategy.enabled, &to_dt(strategy.created_at_ns)],
+    ).await;
+    if let Err(e) = result {
+        error!("Failed to persist paper strategy {}: {}", strategy.id, e);
+    }
+}
+
+// Write changed positions (removed ones are deleted) and new fills
+async fn save(state: &AppState, ids: &[u64], fills: &[Fill]) {
+    let Some(db) = &state.db else { return };
+    if ids.is_empty() && fills.is_empty() {
+        return;
+    }
+    let positions: Vec<(u64, Option<Position>)> = {
+        let book = state.paper.read().await;
+        ids.iter().map(|id| (*id, book.positions.get(id).cloned())).collect()
+    };
+    for (id, position) in positions {
+        let result = match position {
+            Some(p) => db.execute(
+                "INSERT INTO paper_positions (id, strategy_id, author, symbol, message_id, status, qty, signal_at,
+                    entry_price, entry_at, stop_price, target_price, exit_price, exit_at, exit_reason, exit_message_id, realized_pnl)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+                 ON CONFLICT (id) DO UPDATE SET status = EXCLUDED.status, qty = EXCLUDED.qty,
+                    entry_price = EXCLUDED.entry_price, entry_at = EXCLUDED.entry_at,
+                    stop_price = EXCLUDED.stop_price, target_price = EXCLUDED.target_price,
+                    exit_price = EXCLUDED.exit_price, exit_at = EXCLUDED.exit_at, exit_reason = EXCLUDED.exit_reason,
+                    exit_message_id = EXCLUDED.exit_message_id, realized_pnl = EXCLUDED.realized_pnl",
+                &[
+                    &(p.id as i64),
+                    &(p.strategy_id as i64),
+                    &p.author,
+                    &p.symbol,
+                    &p.message_id,
+                    &p.status.as_str(),
+                    &p.qty,
+                    &to_dt(p.signal_ns),
+                    &p.entry_price,
+                    &p.entry_ns.map(to_dt),
+                    &p.stop_price,
+                    &p.target_price,
+                    &p.exit_price,
+                    &p.exit_ns.map(to_dt),
+                    &p.exit_reason,
+                    &p.exit_message_id,
+                    &p.realized_pnl,
+                ],
+            ).await,
+            None => db.execute("DELETE FROM paper_positions WHERE id = $1", &[&(id as i64)]).await,
+        };
+        if let Err(e) = result {
+            error!("Failed to persist paper position {}: {}", id, e);
+        }
+    }
+    for f in fills {
+        let result = db.execute(
+            "INSERT INTO paper_fills (id, position_id, strategy_id, author, symbol, side, qty, price, ref_price, reason, filled_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+             ON CONFLICT (id) DO NOTHING",
+            &[
+                &(f.id as i64),
+                &(f.position_id as i64),
+                &(f.strategy_id as i64),
+                &f.author,
+                &f.symbol,
+                &f.side,
+                &f.qty,
+                &f.price,
+                &f.ref_price,
+                &f.reason,
+                &to_dt(f.ts_ns),
+            ],
+        ).await;
+        if let Err(e) = result {
+            error!("Failed to persist paper fill {}: {}", f.id, e);
+        }
+    }
+}
+
+fn bad_config(config: &StrategyConfig) -> Option<&'static str> {
+    let size = match config.sizing {
+        Sizing::Notional(v) | Sizing::Shares(v) | Sizing::EquityPct(v) => v,
+    };
+    if config.authors.iter().all(|a| a.trim().is_empty()) {
+        Some("authors must name at least one author")
+    } else if !size.is_finite() || size <= 0.0 || matches!(config.sizing, Sizing::EquityPct(p) if p > 100.0) {
+        Some("sizing value must be positive (equity_pct at most 100)")
+    } else if !config.starting_equity.is_finite() || config.starting_equity <= 0.0 {
+        Some("starting_equity must be positive")
+    } else if config.stop_pct.is_some_and(|p| p <= 0.0 || p >= 100.0) || config.target_pct.is_some_and(|p| p <= 0.0) {
+        Some("stop_pct must be in (0, 100) and target_pct positive")
+    } else if config.slippage_spreads < 0.0 || config.fallback_slippage_bps < 0.0 {
+        Some("slippage must not be negative")
+    } else {
+        None
+    }
+}
+
+fn normalize(mut config: StrategyConfig) -> StrategyConfig {
+    config.authors = config.authors.iter()
+        .map(|a| a.trim().trim_start_matches('@').to_string())
+        .filter(|a| !a.is_empty())
+        .collect();
+    config
+}
+
+fn strategy_json(book: &PaperBook, strategy: &Strategy) -> serde_json::Value {
+    let summary = book.summary(strategy.id, None);
+    let authors: BTreeMap<&str, Summary> = strategy.config.authors.iter()
+        .map(|a| (a.as_str(), book.summary(strategy.id, Some(a))))
+        .collect();
+    serde_json::json!({
+        "strategy": strategy,
+        "equity": strategy.config.starting_equity + summary.pnl,
+        "summary": summary,
+        "authors": authors
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateStrategyBody {
+    name: String,
+    #[serde(flatten)]
+    config: StrategyConfig,
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+// POST /api/paper/strategies { name, authors: [...], sizing: {kind: notional|shares|equity_pct, value},
+//   starting_equity, stop_pct?, target_pct?, max_hold_mins?, flat_at_close?, max_open?,
+//   slippage_spreads?, fallback_slippage_bps?, require_entry_keyword?, enabled? }
+pub async fn create_strategy(State(state): State<AppState>, Json(body): Json<CreateStrategyBody>) -> impl IntoResponse {
+    let (strategy, json) = {
+        let mut book = state.paper.write().await;
+        let strategy = match book.add_strategy(&body.name, body.config, body.enabled.unwrap_or(true)) {
+            Ok(strategy) => strategy,
+            Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": e}))),
+        };
+        let json = strategy_json(&book, &strategy);
+        (strategy, json)
+    };
+    persist_strategy(&state, &strategy).await;
+    info!("Paper strategy {} created following {:?}", strategy.id, strategy.config.authors);
+    (StatusCode::CREATED, Json(json))
+}
+
+// GET /api/paper/strategies
+pub async fn list_strategies(State(state): State<AppState>) -> impl IntoResponse {
+    let book = state.paper.read().await;
+    let strategies: Vec<serde_json::Value> = book.strategies.values().map(|s| strategy_json(&book, s)).collect();
+    Json(serde_json::json!({ "strategies": strategies }))
+}
+
+// GET /api/paper/strategies/:id
+pub async fn get_strategy(Path(id): Path<u64>, State(state): State<AppState>) -> impl IntoResponse {
+    let book = state.paper.read().await;
+    match book.strategies.get(&id) {
+        Some(strategy) => (StatusCode::OK, Json(strategy_json(&book, strategy))),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such strategy"}))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateStrategyBody {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    config: Option<StrategyConfig>,
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+// PUT /api/paper/strategies/:id { name?, config?, enabled? }
+// A new config applies to positions opened from then on.
+pub async fn update_strategy(
+    Path(id): Path<u64>,
+    State(state): State<AppState>,
+    Json(body): Json<UpdateStrategyBody>,
+) -> impl IntoResponse {
+    let config = body.config.map(normalize);
+    if let Some(e) = config.as_ref().and_then(bad_config) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": e})));
+    }
+    let (strategy, json) = {
+        let mut book = state.paper.write().await;
+        let Some(strategy) = book.strategies.get_mut(&id) else {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such strategy"})));
+        };
+        if let Some(name) = body.name.filter(|n| !n.trim().is_empty()) {
+            strategy.name = name.trim().to_string();
+        }
+        if let Some(config) = config {
+            strategy.config = config;
+        }
+        if let Some(enabled) = body.enabled {
+            strategy.enabled = enabled;
+        }
+        let strategy = strategy.clone();
+        let json = strategy_json(&book, &strategy);
+        (strategy, json)
+    };
+    persist_strategy(&state, &strategy).await;
+    (StatusCode::OK, Json(json))
+}
+
+// DELETE /api/paper/strategies/:id - drops its positions and fills too
+pub async fn delete_strategy(Path(id): Path<u64>, State(state): State<AppState>) -> impl IntoResponse {
+    {
+        let mut book = state.paper.write().await;
+        if book.strategies.remove(&id).is_none() {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such strategy"})));
+        }
+        book.positions.retain(|_, p| p.strategy_id != id);
+        book.fills.retain(|f| f.strategy_id != id);
+        book.curves.retain(|(s, _), _| *s != id);
+        book.totals.retain(|(s, _), _| *s != id);
+    }
+    if let Some(db) = &state.db {
+        if let Err(e) = db.execute("DELETE FROM paper_strategies WHERE id = $1", &[&(id as i64)]).await {
+            error!("Failed to delete paper strategy {}: {}", id, e);
+        }
+    }
+    info!("Paper strategy {} deleted", id);
+    (StatusCode::OK, Json(serde_json::json!({"deleted": id})))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaperQuery {
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    symbol: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+impl PaperQuery {
+    fn author(&self) -> Option<String> {
+        self.author.as_deref().map(|a| a.trim().trim_start_matches('@').to_string()).filter(|a| !a.is_empty())
+    }
+
+    fn matches(&self, author: &str, symbol: &str) -> bool {
+        self.author().is_none_or(|a| a.eq_ignore_ascii_case(author))
+            && self.symbol.as_deref().map(norm_symbol).is_none_or(|s| s == symbol)
+    }
+}
+
+// GET /api/paper/strategies/:id/positions[?status=open|pending|closed][&author=][&symbol=][&limit=500]
+pub async fn get_positions(Path(id): Path<u64>, Query(q): Query<PaperQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let book = state.paper.read().await;
+    if !book.strategies.contains_key(&id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such strategy"})));
+    }
+    let status = q.status.as_deref().and_then(PositionStatus::parse);
+    let mut positions: Vec<&Position> = book.positions.values()
+        .filter(|p| p.strategy_id == id && q.matches(&p.author, &p.symbol))
+        .filter(|p| status.is_none_or(|s| p.status == s))
+        .collect();
+    positions.reverse();
+    positions.truncate(q.limit.unwrap_or(500));
+    (StatusCode::OK, Json(serde_json::json!({
+        "strategy_id": id,
+        "summary": book.summary(id, q.author().as_deref()),
+        "positions": positions
+    })))
+}
+
+// GET /api/paper/strategies/:id/fills[?author=][&symbol=][&limit=500] - newest first
+pub async fn get_fills(Path(id): Path<u64>, Query(q): Query<PaperQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let book = state.paper.read().await;
+    if !book.strategies.contains_key(&id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such strategy"})));
+    }
+    let fills: Vec<&Fill> = book.fills.iter().rev()
+        .filter(|f| f.strategy_id == id && q.matches(&f.author, &f.symbol))
+        .take(q.limit.unwrap_or(500))
+        .collect();
+    (StatusCode::OK, Json(serde_json::json!({ "strategy_id": id, "fills": fills })))
+}
+
+// GET /api/paper/strategies/:id/curve[?author=] - P&L over time for the strategy or one of its authors
+pub async fn get_curve(Path(id): Path<u64>, Query(q): Query<PaperQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let book = state.paper.read().await;
+    let Some(strategy) = book.strategies.get(&id) else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such strategy"})));
+    };
+    let author = q.author().map(|a| a.to_lowercase());
+    let points: &[CurvePoint] = book.curves.get(&(id, author.clone())).map(|c| c.0.as_slice()).unwrap_or(&[]);
+    (StatusCode::OK, Json(serde_json::json!({
+        "strategy_id": id,
+        "author": author,
+        "starting_equity": strategy.config.starting_equity,
+        "points": points
+    })))
+}
+
+// GET /api/paper/authors/:author - how following this author did in every strategy that does
+pub async fn get_author(Path(author): Path<String>, State(state): State<AppState>) -> impl IntoResponse {
+    let author = author.trim_start_matches('@').to_string();
+    let book = state.paper.read().await;
+    let strategies: Vec<serde_json::Value> = book.strategies.values()
+        .filter(|s| s.config.authors.iter().any(|a| a.eq_ignore_ascii_case(&author)))
+        .map(|s| {
+            let points: &[CurvePoint] = book.curves.get(&(s.id, Some(author.to_lowercase()))).map(|c| c.0.as_slice()).unwrap_or(&[]);
+            let positions: Vec<&Position> = book.positions.values()
+                .filter(|p| p.strategy_id == s.id && p.author.eq_ignore_ascii_case(&author))
+                .collect();
+            serde_json::json!({
+                "strategy_id": s.id,
+                "strategy": s.name,
+                "summary": book.summary(s.id, Some(&author)),
+                "positions": positions,
+                "curve": points
+            })
+        })
+        .collect();
+    if strategies.is_empty() {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no strategy follows author", "author": author})));
+    }
+    (StatusCode::OK, Json(serde_json::json!({ "author": author, "strategies": strategies })))
+}
+
+// POST /api/paper/strategies/:id/positions/:position_id/close - exit now at the market
+pub async fn close_position(Path((id, position_id)): Path<(u64, u64)>, State(state): State<AppState>) -> impl IntoResponse {
+    let symbol = {
+        let book = state.paper.read().await;
+        match book.positions.get(&position_id).filter(|p| p.strategy_id == id) {
+            Some(p) if p.status == PositionStatus::Closed => {
+                return (StatusCode::CONFLICT, Json(serde_json::json!({"error": "position already closed"})));
+            }
+            Some(p) => p.symbol.clone(),
+            None => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such position"}))),
+        }
+    };
+    let (quote, last) = market(&state, &symbol).await;
+    let (fill, position) = {
+        let mut book = state.paper.write().await;
+        if book.positions.get(&position_id).is_some_and(|p| p.status == PositionStatus::Pending) {
+            book.positions.remove(&position_id);
+            (None, None)
+        } else {
+            let fill = book.close(position_id, quote, last, "manual", current_time_ns());
+            (fill, book.positions.get(&position_id).cloned())
+        }
+    };
+    if position.as_ref().is_some_and(|p| p.status != PositionStatus::Closed) {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"error": "no price to exit at", "symbol": symbol})));
+    }
+    save(&state, &[position_id], fill.as_slice()).await;
+    (StatusCode::OK, Json(serde_json::json!({ "position": position, "fill": fill })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close_to(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn follow_book(sizing: Sizing) -> PaperBook {
+        let mut book = PaperBook::default();
+        let config = StrategyConfig {
+            authors: vec!["@Trader1 ".to_string()],
+            sizing,
+            starting_equity: 10_000.0,
+            stop_pct: Some(5.0),
+            target_pct: None,
+            max_hold_mins: None,
+            flat_at_close: true,
+            max_open: None,
+            slippage_spreads: 0.5,
+            fallback_slippage_bps: 10.0,
+            require_entry_keyword: false,
+        };
+        book.add_strategy("follow", config, true).unwrap();
+        book
+    }
+
+    fn pending(book: &mut PaperBook, symbol: &str) -> u64 {
+        book.next_position_id += 1;
+        let id = book.next_position_id;
+        book.positions.insert(id, Position {
+            id,
+            strategy_id: 1,
+            author: "Trader1".to_string(),
+            symbol: symbol.to_string(),
+            message_id: Some("m1".to_string()),
+            status: PositionStatus::Pending,
+            qty: 0.0,
+            signal_ns: 0,
+            entry_price: None,
+            entry_ns: None,
+            stop_price: None,
+            target_price: None,
+            exit_price: None,
+            exit_ns: None,
+            exit_reason: None,
+            exit_message_id: None,
+            mark_price: None,
+            unrealized_pnl: 0.0,
+            realized_pnl: 0.0,
+        });
+        id
+    }
+
+    #[test]
+    fn signal_patterns() {
+        for exit in ["Sold half", "I'm out", "im out of NVDA", "stopped out", "took profits here", "closed my position"] {
+            assert!(exit_pattern().is_match(exit), "{}", exit);
+        }
+        for other in ["soldier", "checkout the chart", "closed above vwap", "without a doubt"] {
+            assert!(!exit_pattern().is_match(other), "{}", other);
+        }
+        for entry in ["I'm in NVDA", "starter here", "bought calls", "in at 4.20"] {
+            assert!(entry_pattern().is_match(entry), "{}", entry);
+        }
+        for other in ["insider selling", "within range", "watching"] {
+            assert!(!entry_pattern().is_match(other), "{}", other);
+        }
+    }
+
+    #[test]
+    fn names_needs_capitals_or_a_cashtag() {
+        assert!(names("sold NVDA.", "NVDA"));
+        assert!(names("out of $nvda, thanks", "NVDA"));
+        assert!(names("trimmed BRK.B", "BRK.B"));
+        assert!(!names("sold nvda", "NVDA"));
+        assert!(!names("sold NVDAX", "NVDA"));
+        assert!(!names("I'm out", "NVDA"));
+    }
+
+    #[test]
+    fn sizing_and_affordability() {
+        // ask side of a 99.90 x 100.10 quote with half a spread of slippage
+        let quote = Some((99.9, 100.1));
+        for (sizing, qty) in [(Sizing::Notional(1_000.0), 9.0), (Sizing::Shares(10.7), 10.0), (Sizing::EquityPct(50.0), 49.0)] {
+            let mut book = follow_book(sizing);
+            let id = pending(&mut book, "NVDA");
+            let fill = book.open(id, quote, None, 1).unwrap();
+            assert!(close_to(fill.price, 100.1) && close_to(fill.ref_price, 100.0));
+            assert_eq!(fill.qty, qty, "{:?}", sizing);
+            let p = &book.positions[&id];
+            assert_eq!(p.status, PositionStatus::Open);
+            assert!(close_to(p.stop_price.unwrap(), 100.1 * 0.95));
+        }
+
+        let mut book = follow_book(Sizing::Shares(1_000.0));
+        let id = pending(&mut book, "NVDA");
+        assert!(book.open(id, quote, None, 1).is_none());
+        assert!(!book.positions.contains_key(&id));
+
+        let mut book = follow_book(Sizing::Notional(50.0));
+        let id = pending(&mut book, "NVDA");
+        assert!(book.open(id, quote, None, 1).is_none());
+    }
+
+    #[test]
+    fn fills_and_pnl_accounting() {
+        let mut book = follow_book(Sizing::Shares(10.0));
+        let strategy = book.strategies[&1].clone();
+        let id = pending(&mut book, "NVDA");
+        book.open(id, Some((99.9, 100.1)), Some(100.0), 1).unwrap();
+        // marked at the last trade straight away
+        assert!(close_to(book.positions[&id].unrealized_pnl, -1.0));
+        book.mark(id, 105.0);
+        assert!(close_to(book.totals[&(1, None)].1, 49.0));
+        assert!(close_to(book.totals[&(1, Some("trader1".to_string()))].1, 49.0));
+        assert!(close_to(book.equity(&strategy), 10_049.0));
+
+        // no quote: the last trade less 10 bps
+        let mut fills = Vec::new();
+        assert!(book.exit(id, None, Some(110.0), Some(&"m2".to_string()), 2, &mut fills));
+        let p = &book.positions[&id];
+        assert_eq!((p.status, p.exit_reason.as_deref(), p.exit_message_id.as_deref()), (PositionStatus::Closed, Some("exit_message"), Some("m2")));
+        assert!(close_to(fills[0].price, 109.89));
+        assert!(close_to(p.realized_pnl, 10.0 * (109.89 - 100.1)));
+        let (realized, unrealized) = book.totals[&(1, None)];
+        assert!(close_to(realized, p.realized_pnl) && close_to(unrealized, 0.0));
+        assert!(close_to(book.equity(&strategy), 10_000.0 + p.realized_pnl));
+        assert_eq!(book.fills.iter().map(|f| f.side).collect::<Vec<_>>(), ["buy", "sell"]);
+        let summary = book.summary(1, Some("trader1"));
+        assert_eq!((summary.closed, summary.wins, summary.win_rate), (1, 1, Some(1.0)));
+
+        // closing again is a no-op; a pending entry is just dropped
+        assert!(!book.exit(id, None, Some(120.0), None, 3, &mut fills));
+        let pending_id = pending(&mut book, "AMD");
+        assert!(book.exit(pending_id, None, None, None, 4, &mut fills));
+        assert!(!book.positions.contains_key(&pending_id));
+        assert_eq!(fills.len(), 1);
+    }
+}
@@ -0,0 +1,609 @@
+// Raw live-feed recorder: every record a LiveClient returns, symbol mappings,
+// gateway errors and heartbeats included, is written unchanged to zstd-compressed
+// DBN files, so what the feed delivered can be audited or replayed exactly later.
+//
+// Files live under RECORD_DIR/<exchange date>/<dataset>-<HHMMSS>.dbn.zst, one
+// series per dataset, starting a new file on each connection, at the day
+// boundary and once RECORD_ROTATE_MB of raw records have been written. A file
+// opened by rotation starts with the session's metadata and the symbol mappings
+// seen so far on the connection (counted as carried_mappings), so it decodes on
+// its own. index.jsonl lists each closed file with its time range and per-symbol
+// record counts; files left open by a crash are indexed by reading them back.
+//
+//   RECORD_DIR             enables the recorder (unset: off)
+//   RECORD_ROTATE_MB       raw MB per file before rotating (default 256)
+//   RECORD_RETENTION_DAYS  delete files older than this many days (default 30)
+//   RECORD_MAX_GB          delete the oldest files beyond this total (default 50)
+//   RECORD_FLUSH_SECS      how often open files are flushed to disk (default 5)
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::NaiveDate;
+use databento::dbn::{
+    decode::{DbnDecoder, DbnMetadata, DecodeRecordRef},
+    encode::{DbnMetadataEncoder, DynWriter},
+    rtype, Compression, Metadata, RecordRef, SymbolMappingMsg,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+
+use crate::{calendar, norm_symbol, env, outcomes::parse_ts, to_dt, AppState};
+
+const INDEX_FILE: &str = "index.jsonl";
+const EXTENSION: &str = ".dbn.zst";
+const PRUNE_EVERY: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Debug)]
+pub struct RecorderConfig {
+    pub dir: PathBuf,
+    pub rotate_bytes: u64,
+    pub retention_days: u32,
+    pub max_bytes: u64,
+    pub flush: Duration,
+}
+
+impl RecorderConfig {
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("RECORD_DIR").ok().filter(|d| !d.trim().is_empty())?;
+        Some(RecorderConfig {
+            dir: PathBuf::from(dir.trim()),
+            rotate_bytes: env("RECORD_ROTATE_MB", 256u64).max(1) << 20,
+            retention_days: env("RECORD_RETENTION_DAYS", 30),
+            max_bytes: (env("RECORD_MAX_GB", 50.0f64).max(0.0) * (1u64 << 30) as f64) as u64,
+            flush: Duration::from_secs(env("RECORD_FLUSH_SECS", 5u64).max(1)),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SymbolSpan {
+    pub records: u64,
+    pub first_ts_ns: u64,
+    pub last_ts_ns: u64,
+}
+
+// One capture file; closed_at_ns is None while it is being written
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: String, // relative to RECORD_DIR
+    pub dataset: String,
+    pub date: String,
+    pub opened_at_ns: u64,
+    pub closed_at_ns: Option<u64>,
+    pub close_reason: Option<String>, // day | size | the disconnect reason | unindexed
+    pub first_ts_ns: Option<u64>,
+    pub last_ts_ns: Option<u64>,
+    pub records: u64,
+    pub carried_mappings: u64,
+    pub raw_bytes: u64,
+    pub bytes: u64, // on disk
+    pub symbols: BTreeMap<String, SymbolSpan>,
+}
+
+impl FileEntry {
+    fn note(&mut self, symbol: Option<&str>, ts_ns: u64, len: u64) {
+        self.records += 1;
+        self.raw_bytes += len;
+        self.first_ts_ns.get_or_insert(ts_ns);
+        self.last_ts_ns = Some(self.last_ts_ns.map_or(ts_ns, |t| t.max(ts_ns)));
+        if let Some(symbol) = symbol {
+            let span = self.symbols.entry(symbol.to_string()).or_insert_with(|| SymbolSpan { first_ts_ns: ts_ns, ..Default::default() });
+            span.records += 1;
+            span.last_ts_ns = span.last_ts_ns.max(ts_ns);
+        }
+    }
+
+    fn overlaps(&self, since_ns: u64, until_ns: u64) -> bool {
+        let first = self.first_ts_ns.unwrap_or(self.opened_at_ns);
+        let last = self.last_ts_ns.or(self.closed_at_ns).unwrap_or(u64::MAX);
+        first < until_ns && last >= since_ns
+    }
+}
+
+enum Command {
+    Start { dataset: String, metadata: Box<Metadata> },
+    Record { dataset: String, bytes: Vec<u8>, symbol: Option<String>, recv_ns: u64 },
+    Stop { dataset: String, reason: String },
+}
+
+// Handle held in AppState; the files are written on a thread of their own
+#[derive(Clone)]
+pub struct Recorder {
+    tx: mpsc::Sender<Command>,
+    pub dir: PathBuf,
+    pub index: Arc<RwLock<Vec<FileEntry>>>,
+}
+
+impl Recorder {
+    pub fn from_env() -> Option<Recorder> {
+        let config = RecorderConfig::from_env()?;
+        if let Err(e) = fs::create_dir_all(&config.dir) {
+            error!("Recorder disabled, cannot create {}: {}", config.dir.display(), e);
+            return None;
+        }
+        let index = Arc::new(RwLock::new(load_index(&config.dir)));
+        let (tx, rx) = mpsc::channel();
+        let writer = Writer { config: config.clone(), index: index.clone(), open: HashMap::new() };
+        if let Err(e) = std::thread::Builder::new().name("recorder".into()).spawn(move || writer.run(rx)) {
+            error!("Recorder disabled, cannot start its thread: {}", e);
+            return None;
+        }
+        info!(
+            "Recording the live feed to {} (rotate {} MB, keep {} days / {} GB)",
+            config.dir.display(), config.rotate_bytes >> 20, config.retention_days, config.max_bytes >> 30,
+        );
+        Some(Recorder { tx, dir: config.dir, index })
+    }
+
+    // A connection started streaming; opens a new file with its metadata
+    pub fn start(&self, dataset: &str, metadata: &Metadata) {
+        let _ = self.tx.send(Command::Start { dataset: dataset.to_string(), metadata: Box::new(metadata.clone()) });
+    }
+
+    pub fn record(&self, dataset: &str, rec: &RecordRef, mapping: &HashMap<u32, String>) {
+        let bytes = rec.as_ref().to_vec();
+        let symbol = instrument_id(&bytes).and_then(|id| mapping.get(&id)).cloned();
+        let _ = self.tx.send(Command::Record {
+            dataset: dataset.to_string(),
+            bytes,
+            symbol,
+            recv_ns: crate::current_time_ns(),
+        });
+    }
+
+    // The connection ended; closes its file with the reason
+    pub fn stop(&self, dataset: &str, reason: impl Into<String>) {
+        let _ = self.tx.send(Command::Stop { dataset: dataset.to_string(), reason: reason.into() });
+    }
+}
+
+// Every DBN record starts with length, rtype, publisher_id (u16), instrument_id (u32), ts_event (u64)
+fn instrument_id(bytes: &[u8]) -> Option<u32> {
+    bytes.get(4..8).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn ts_event(bytes: &[u8]) -> Option<u64> {
+    bytes.get(8..16).map(|b| u64::from_le_bytes(b.try_into().unwrap())).filter(|ts| *ts != 0 && *ts != u64::MAX)
+}
+
+struct Capture {
+    writer: DynWriter<'static, BufWriter<File>>,
+    metadata: Box<Metadata>,
+    date: NaiveDate,
+    mappings: HashMap<u32, (Vec<u8>, Option<String>)>, // instrument_id -> latest mapping record on this connection
+    entry: FileEntry,
+}
+
+struct Writer {
+    config: RecorderConfig,
+    index: Arc<RwLock<Vec<FileEntry>>>,
+    open: HashMap<String, Capture>,
+}
+
+impl Writer {
+    fn run(mut self, rx: mpsc::Receiver<Command>) {
+        self.prune();
+        let (mut last_flush, mut last_prune) = (Instant::now(), Instant::now());
+        loop {
+            match rx.recv_timeout(self.config.flush) {
+                Ok(Command::Start { dataset, metadata }) => {
+                    self.close(&dataset, "restarted");
+                    self.open(&dataset, metadata, HashMap::new());
+                }
+                Ok(Command::Record { dataset, bytes, symbol, recv_ns }) => self.write(&dataset, bytes, symbol, recv_ns),
+                Ok(Command::Stop { dataset, reason }) => self.close(&dataset, &reason),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if last_flush.elapsed() >= self.config.flush {
+                self.flush();
+                last_flush = Instant::now();
+            }
+            if last_prune.elapsed() >= PRUNE_EVERY {
+                self.prune();
+                last_prune = Instant::now();
+            }
+        }
+        for dataset in self.open.keys().cloned().collect::<Vec<_>>() {
+            self.close(&dataset, "shutdown");
+        }
+    }
+
+    fn open(&mut self, dataset: &str, metadata: Box<Metadata>, mappings: HashMap<u32, (Vec<u8>, Option<String>)>) {
+        let now = crate::current_time_ns();
+        let date = calendar::exchange_date(now);
+        let day_dir = self.config.dir.join(date.to_string());
+        let stem = format!("{}-{}", dataset, to_dt(now).format("%H%M%S"));
+        let mut name = format!("{}{}", stem, EXTENSION);
+        for n in 1.. {
+            if !day_dir.join(&name).exists() {
+                break;
+            }
+            name = format!("{}.{}{}", stem, n, EXTENSION);
+        }
+        let path = day_dir.join(&name);
+        let opened = fs::create_dir_all(&day_dir)
+            .and_then(|_| File::create(&path))
+            .map_err(|e| e.to_string())
+            .and_then(|file| DynWriter::new(BufWriter::new(file), Compression::ZStd).map_err(|e| e.to_string()))
+            .and_then(|mut writer| DbnMetadataEncoder::new(&mut writer).encode(&metadata).map(|_| writer).map_err(|e| e.to_string()));
+        let writer = match opened {
+            Ok(writer) => writer,
+            Err(e) => {
+                error!("Recorder cannot open {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let mut capture = Capture {
+            writer,
+            metadata,
+            date,
+            mappings: HashMap::new(),
+            entry: FileEntry {
+                path: format!("{}/{}", date, name),
+                dataset: dataset.to_string(),
+                date: date.to_string(),
+                opened_at_ns: now,
+                ..Default::default()
+            },
+        };
+        for (bytes, symbol) in mappings.into_values() {
+            if let Err(e) = capture.writer.write_all(&bytes) {
+                error!("Recorder write to {} failed: {}", path.display(), e);
+                break;
+            }
+            capture.entry.carried_mappings += 1;
+            capture.entry.note(symbol.as_deref(), now, bytes.len() as u64);
+            if let Some(id) = instrument_id(&bytes) {
+                capture.mappings.insert(id, (bytes, symbol));
+            }
+        }
+        info!("Recording {} to {}", dataset, path.display());
+        self.index.blocking_write().push(capture.entry.clone());
+        self.open.insert(dataset.to_string(), capture);
+    }
+
+    fn write(&mut self, dataset: &str, bytes: Vec<u8>, symbol: Option<String>, recv_ns: u64) {
+        let rotate = match self.open.get(dataset) {
+            Some(capture) if capture.date != calendar::exchange_date(recv_ns) => Some("day"),
+            Some(capture) if capture.entry.raw_bytes >= self.config.rotate_bytes => Some("size"),
+            Some(_) => None,
+            None => return,
+        };
+        if let Some(reason) = rotate {
+            if let Some(capture) = self.open.get(dataset) {
+                let (metadata, mappings) = (capture.metadata.clone(), capture.mappings.clone());
+                self.close(dataset, reason);
+                self.open(dataset, metadata, mappings);
+            }
+        }
+        let Some(capture) = self.open.get_mut(dataset) else { return };
+        if let Err(e) = capture.writer.write_all(&bytes) {
+            error!("Recorder write for {} failed, closing {}: {}", dataset, capture.entry.path, e);
+            self.close(dataset, &format!("write error: {}", e));
+            return;
+        }
+        capture.entry.note(symbol.as_deref(), ts_event(&bytes).unwrap_or(recv_ns), bytes.len() as u64);
+        if bytes.get(1) == Some(&rtype::SYMBOL_MAPPING) {
+            if let Some(id) = instrument_id(&bytes) {
+                capture.mappings.insert(id, (bytes, symbol));
+            }
+        }
+    }
+
+    fn close(&mut self, dataset: &str, reason: &str) {
+        let Some(mut capture) = self.open.remove(dataset) else { return };
+        if let Err(e) = capture.writer.flush() {
+            error!("Recorder flush of {} failed: {}", capture.entry.path, e);
+        }
+        drop(capture.writer); // finishes the zstd frame
+        let entry = &mut capture.entry;
+        entry.closed_at_ns = Some(crate::current_time_ns());
+        entry.close_reason = Some(reason.to_string());
+        entry.bytes = fs::metadata(self.config.dir.join(&entry.path)).map(|m| m.len()).unwrap_or(0);
+        info!("Closed recording {} ({} records, {}): {}", entry.path, entry.records, human_bytes(entry.bytes), reason);
+        self.update(entry);
+        let line = serde_json::to_string(entry).unwrap_or_default();
+        let appended = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.config.dir.join(INDEX_FILE))
+            .and_then(|mut f| writeln!(f, "{}", line));
+        if let Err(e) = appended {
+            error!("Recorder cannot append to {}: {}", INDEX_FILE, e);
+        }
+        self.prune();
+    }
+
+    fn flush(&mut self) {
+        let mut entries = Vec::new();
+        for capture in self.open.values_mut() {
+            if let Err(e) = capture.writer.flush() {
+                warn!("Recorder flush of {} failed: {}", capture.entry.path, e);
+            }
+            capture.entry.bytes = fs::metadata(self.config.dir.join(&capture.entry.path)).map(|m| m.len()).unwrap_or(0);
+            entries.push(capture.entry.clone());
+        }
+        for entry in &entries {
+            self.update(entry);
+        }
+    }
+
+    fn update(&self, entry: &FileEntry) {
+        let mut index = self.index.blocking_write();
+        match index.iter_mut().find(|e| e.path == entry.path) {
+            Some(existing) => *existing = entry.clone(),
+            None => index.push(entry.clone()),
+        }
+    }
+
+    // Enforce retention on closed files: age first, then total size, oldest first
+    fn prune(&mut self) {
+        let cutoff = calendar::exchange_date(crate::current_time_ns()) - chrono::Duration::days(self.config.retention_days as i64);
+        let mut index = self.index.blocking_write();
+        index.sort_by_key(|e| e.opened_at_ns);
+        let mut total: u64 = index.iter().map(|e| e.bytes).sum();
+        let mut removed = Vec::new();
+        index.retain(|e| {
+            if e.closed_at_ns.is_none() {
+                return true;
+            }
+            let expired = NaiveDate::parse_from_str(&e.date, "%Y-%m-%d").is_ok_and(|d| d < cutoff);
+            if expired || total > self.config.max_bytes {
+                total -= e.bytes;
+                removed.push(e.path.clone());
+                return false;
+            }
+            true
+        });
+        if removed.is_empty() {
+            return;
+        }
+        for path in &removed {
+            let path = self.config.dir.join(path);
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Recorder cannot delete {}: {}", path.display(), e);
+            }
+            if let Some(day_dir) = path.parent() {
+                let _ = fs::remove_dir(day_dir); // only succeeds once the day is empty
+            }
+        }
+        info!("Recorder retention removed {} files", removed.len());
+        let closed: Vec<&FileEntry> = index.iter().filter(|e| e.closed_at_ns.is_some()).collect();
+        if let Err(e) = write_index(&self.config.dir, &closed) {
+            error!("Recorder cannot rewrite {}: {}", INDEX_FILE, e);
+        }
+    }
+}
+
+fn write_index(dir: &Path, entries: &[&FileEntry]) -> std::io::Result<()> {
+    let tmp = dir.join(format!("{}.tmp", INDEX_FILE));
+    let mut f = BufWriter::new(File::create(&tmp)?);
+    for entry in entries {
+        writeln!(f, "{}", serde_json::to_string(entry).unwrap_or_default())?;
+    }
+    f.flush()?;
+    drop(f);
+    fs::rename(tmp, dir.join(INDEX_FILE))
+}
+
+// index.jsonl, minus files that are gone, plus files a crash left out of it
+fn load_index(dir: &Path) -> Vec<FileEntry> {
+    let mut entries: Vec<FileEntry> = fs::read_to_string(dir.join(INDEX_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .filter(|e: &FileEntry| dir.join(&e.path).exists())
+        .collect();
+    let known: HashSet<String> = entries.iter().map(|e| e.path.clone()).collect();
+    let mut found = Vec::new();
+    for day in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let Ok(date) = NaiveDate::parse_from_str(&day.file_name().to_string_lossy(), "%Y-%m-%d") else { continue };
+        for file in fs::read_dir(day.path()).into_iter().flatten().flatten() {
+            let name = file.file_name().to_string_lossy().to_string();
+            let path = format!("{}/{}", date, name);
+            if name.ends_with(EXTENSION) && !known.contains(&path) {
+                found.push(scan(dir, path, date));
+            }
+        }
+    }
+    if !found.is_empty() {
+        warn!("Recorder indexed {} files that were not closed cleanly", found.len());
+        entries.extend(found);
+        entries.sort_by_key(|e| e.opened_at_ns);
+        let all: Vec<&FileEntry> = entries.iter().collect();
+        if let Err(e) = write_index(dir, &all) {
+            error!("Recorder cannot rewrite {}: {}", INDEX_FILE, e);
+        }
+    }
+    entries
+}
+
+// Read back a file that was never closed, up to where it was last flushed
+fn scan(dir: &Path, path: String, date: NaiveDate) -> FileEntry {
+    let full = dir.join(&path);
+    let modified_ns = fs::metadata(&full).ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64);
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let mut entry = FileEntry {
+        dataset: name.rsplit_once('-').map_or("", |(dataset, _)| dataset).to_string(),
+        path,
+        date: date.to_string(),
+        closed_at_ns: Some(modified_ns),
+        close_reason: Some("unindexed".to_string()),
+        bytes: fs::metadata(&full).map(|m| m.len()).unwrap_or(0),
+        ..Default::default()
+    };
+    let Ok(mut decoder) = DbnDecoder::from_zstd_file(&full) else { return entry };
+    entry.dataset.clone_from(&decoder.metadata().dataset);
+    entry.opened_at_ns = decoder.metadata().start;
+    let mut mapping: HashMap<u32, String> = HashMap::new();
+    while let Ok(Some(rec)) = decoder.decode_record_ref() {
+        let bytes = rec.as_ref();
+        if let Some(msg) = rec.get::<SymbolMappingMsg>() {
+            mapping.insert(msg.hd.instrument_id, msg.stype_out_symbol().unwrap_or_default().to_string());
+        }
+        let symbol = instrument_id(bytes).and_then(|id| mapping.get(&id));
+        entry.note(symbol.map(String::as_str), ts_event(bytes).unwrap_or(modified_ns), bytes.len() as u64);
+    }
+    entry
+}
+
+fn human_bytes(n: u64) -> String {
+    match n {
+        n if n >= 1 << 30 => format!("{:.1} GB", n as f64 / (1u64 << 30) as f64),
+        n if n >= 1 << 20 => format!("{:.1} MB", n as f64 / (1u64 << 20) as f64),
+        n => format!("{:.1} KB", n as f64 / 1024.0),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecordingsQuery {
+    dataset: Option<String>,
+    symbol: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+}
+
+// GET /api/recordings[?dataset=EQUS.MINI][&symbol=AAPL][&since=RFC3339][&until=RFC3339]
+// Capture files whose records overlap the window, oldest first; files still being
+// written have no closed_at_ns. With a symbol, only files that carry it.
+pub async fn get_recordings(Query(q): Query<RecordingsQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let Some(recorder) = &state.recorder else {
+        return (http::StatusCode::OK, Json(serde_json::json!({"enabled": false, "files": []})));
+    };
+    let (since_ns, until_ns) = match (parse_ts(q.since.as_deref()), parse_ts(q.until.as_deref())) {
+        (Ok(since), Ok(until)) => (
+            since.and_then(|t| t.timestamp_nanos_opt()).map_or(0, |t| t as u64),
+            until.and_then(|t| t.timestamp_nanos_opt()).map_or(u64::MAX, |t| t as u64),
+        ),
+        (Err(e), _) | (_, Err(e)) => return (http::StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": e}))),
+    };
+    let symbol = q.symbol.as_deref().map(norm_symbol).filter(|s| !s.is_empty());
+    let index = recorder.index.read().await;
+    let mut files: Vec<&FileEntry> = index.iter()
+        .filter(|e| q.dataset.as_ref().is_none_or(|d| *d == e.dataset))
+        .filter(|e| symbol.as_ref().is_none_or(|s| e.symbols.contains_key(s)))
+        .filter(|e| e.overlaps(since_ns, until_ns))
+        .collect();
+    files.sort_by_key(|e| e.opened_at_ns);
+    (http::StatusCode::OK, Json(serde_json::json!({
+        "enabled": true,
+        "dir": recorder.dir.display().to_string(),
+        "total_bytes": files.iter().map(|e| e.bytes).sum::<u64>(),
+        "files": files,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use databento::dbn::{SType, Schema, TradeMsg};
+
+    use super::*;
+
+    fn writer(name: &str, rotate_bytes: u64) -> Writer {
+        let dir = std::env::temp_dir().join(format!("recorder-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let config = RecorderConfig { dir, rotate_bytes, retention_days: 30, max_bytes: u64::MAX, flush: Duration::from_secs(5) };
+        Writer { config, index: Arc::new(RwLock::new(Vec::new())), open: HashMap::new() }
+    }
+
+    fn metadata() -> Box<Metadata> {
+        Box::new(Metadata::builder()
+            .dataset("XNAS.ITCH".to_string())
+            .schema(Some(Schema::Trades))
+            .start(1)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .build())
+    }
+
+    fn mapping(id: u32, symbol: &str, ts_ns: u64) -> Vec<u8> {
+        let msg = SymbolMappingMsg::new(id, ts_ns, SType::RawSymbol, symbol, SType::RawSymbol, symbol, 0, u64::MAX).unwrap();
+        msg.as_ref().to_vec()
+    }
+
+    fn trade(id: u32, ts_ns: u64) -> Vec<u8> {
+        let mut msg = TradeMsg { price: 10_000_000_000, size: 100, ..Default::default() };
+        (msg.hd.instrument_id, msg.hd.ts_event) = (id, ts_ns);
+        msg.as_ref().to_vec()
+    }
+
+    // Writes go through the day's file; stamping them with its open time keeps them on that day
+    fn recv_ns(w: &Writer) -> u64 {
+        w.open["XNAS.ITCH"].entry.opened_at_ns
+    }
+
+    #[test]
+    fn rotation_carries_mappings_and_indexes_each_file() {
+        let map = mapping(7, "AAPL", 100);
+        let rotate_bytes = (map.len() + trade(7, 0).len()) as u64;
+        let mut w = writer("rotation", rotate_bytes);
+        w.open("XNAS.ITCH", metadata(), HashMap::new());
+        let now = recv_ns(&w);
+        w.write("XNAS.ITCH", map, Some("AAPL".into()), now);
+        w.write("XNAS.ITCH", trade(7, 200), Some("AAPL".into()), now);
+        // The file is full: the next record starts a new one
+        w.write("XNAS.ITCH", trade(7, 300), Some("AAPL".into()), now);
+        w.close("XNAS.ITCH", "stopped");
+
+        let index = w.index.blocking_read().clone();
+        assert_eq!(index.len(), 2);
+        let (first, second) = (&index[0], &index[1]);
+        assert_eq!((first.close_reason.as_deref(), first.records, first.carried_mappings), (Some("size"), 2, 0));
+        assert_eq!((first.first_ts_ns, first.last_ts_ns), (Some(100), Some(200)));
+        assert_eq!((second.close_reason.as_deref(), second.records, second.carried_mappings), (Some("stopped"), 2, 1));
+        assert_eq!(second.symbols["AAPL"].records, 2);
+        assert!(first.path != second.path && second.bytes > 0);
+
+        // The rotated file decodes on its own: metadata, the carried mapping, then the trade
+        let mut decoder = DbnDecoder::from_zstd_file(w.config.dir.join(&second.path)).unwrap();
+        assert_eq!(decoder.metadata().dataset, "XNAS.ITCH");
+        let rec = decoder.decode_record_ref().unwrap().unwrap();
+        assert_eq!(rec.get::<SymbolMappingMsg>().and_then(|m| m.stype_out_symbol().ok()), Some("AAPL"));
+        let rec = decoder.decode_record_ref().unwrap().unwrap();
+        assert_eq!(rec.get::<TradeMsg>().map(|t| t.hd.ts_event), Some(300));
+        assert!(decoder.decode_record_ref().unwrap().is_none());
+
+        let reloaded = load_index(&w.config.dir);
+        assert_eq!(reloaded.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), vec![first.path.as_str(), second.path.as_str()]);
+        fs::remove_dir_all(&w.config.dir).unwrap();
+    }
+
+    #[test]
+    fn files_missing_from_the_index_are_read_back() {
+        let mut w = writer("unindexed", u64::MAX);
+        w.open("XNAS.ITCH", metadata(), HashMap::new());
+        let now = recv_ns(&w);
+        w.write("XNAS.ITCH", mapping(7, "AAPL", 100), Some("AAPL".into()), now);
+        w.write("XNAS.ITCH", trade(7, 200), Some("AAPL".into()), now);
+        w.write("XNAS.ITCH", trade(8, 300), None, now);
+        w.close("XNAS.ITCH", "stopped");
+        // As if the process died before the index was written
+        fs::remove_file(w.config.dir.join(INDEX_FILE)).unwrap();
+
+        let index = load_index(&w.config.dir);
+        assert_eq!(index.len(), 1);
+        let entry = &index[0];
+        assert_eq!((entry.dataset.as_str(), entry.close_reason.as_deref()), ("XNAS.ITCH", Some("unindexed")));
+        assert_eq!((entry.records, entry.first_ts_ns, entry.last_ts_ns), (3, Some(100), Some(300)));
+        assert_eq!(entry.symbols.keys().collect::<Vec<_>>(), vec!["AAPL"]);
+        assert!(w.config.dir.join(INDEX_FILE).exists());
+        fs::remove_dir_all(&w.config.dir).unwrap();
+    }
+}
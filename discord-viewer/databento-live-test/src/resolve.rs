@@ -0,0 +1,317 @@
+// Symbol resolution before subscribing: canonicalize share-class forms
+// (BRK.B / BRK-B / BRK B -> BRK.B, extractor-logic.md §3) and check the result
+// against a reference universe, either the listed_securities table or
+// Databento's symbology.resolve endpoint (in every dataset the router could use
+// for the symbol).
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use axum::{extract::State, response::IntoResponse, Json};
+use base64::Engine as _;
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    current_time_ns,
+    sessions::{self, RoutingRules},
+    AppState,
+};
+
+// Cached verdicts are re-checked after this long
+const CACHE_TTL_NS: u64 = 6 * 60 * 60 * 1_000_000_000;
+
+// Exchanges we stream (§2 hard filters)
+const ALLOWED_EXCHANGES: [&str; 3] = ["NASDAQ", "NYSE", "AMEX"];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Rejection {
+    pub input: String,
+    pub canonical: Option<String>,
+    pub code: &'static str, // malformed | unknown | inactive | exchange
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Resolution {
+    pub accepted: Vec<String>,
+    pub rejected: Vec<Rejection>,
+    pub source: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct CachedVerdict {
+    rejection: Option<(&'static str, String)>,
+    checked_at_ns: u64,
+}
+
+// Which reference universe to check against (SYMBOL_UNIVERSE=db|databento|none).
+// Defaults to the database when connected, else Databento when a key is set.
+fn universe_source(state: &AppState) -> &'static str {
+    match std::env::var("SYMBOL_UNIVERSE").unwrap_or_default().to_lowercase().as_str() {
+        "db" if state.db.is_some() => "db",
+        "databento" => "databento",
+        "none" | "off" => "none",
+        _ if state.db.is_some() => "db",
+        _ if std::env::var("DATABENTO_API_KEY").is_ok() => "databento",
+        _ => "none",
+    }
+}
+
+// Canonical form: uppercase, no '$', trailing punctuation trimmed, share-class
+// separators ('-', ' ', '/') mapped to '.'. Returns None when the result isn't
+// shaped like a US equity ticker.
+pub fn canonical_symbol(raw: &str) -> Option<String> {
+    let s = raw.trim().trim_start_matches('$').trim_end_matches(|c: char| !c.is_ascii_alphanumeric());
+    let s = s.to_uppercase();
+    let mut parts = s.split(['.', '-', ' ', '/']).filter(|p| !p.is_empty());
+    let root = parts.next()?;
+    let class = parts.next();
+    if parts.next().is_some() {
+        return None;
+    }
+    if root.is_empty() || root.len() > 5 || !root.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    match class {
+        None => Some(root.to_string()),
+        Some(c) if c.len() <= 3 && c.chars().all(|ch| ch.is_ascii_alphanumeric()) => Some(format!("{}.{}", root, c)),
+        Some(_) => None,
+    }
+}
+
+// Separator variants a reference table might store for a canonical symbol
+fn variants(canonical: &str) -> Vec<String> {
+    match canonical.split_once('.') {
+        Some((root, class)) => vec![
+            canonical.to_string(),
+            format!("{}-{}", root, class),
+            format!("{} {}", root, class),
+            format!("{}/{}", root, class),
+        ],
+        None => vec![canonical.to_string()],
+    }
+}
+
+// Look up canonical symbols in listed_securities; returns symbol -> rejection (None = ok)
+async fn check_db(state: &AppState, symbols: &[String]) -> anyhow::Result<HashMap<String, Option<(&'static str, String)>>> {
+    let Some(db) = &state.db else {
+        anyhow::bail!("database not connected");
+    };
+    let all_variants: Vec<String> = symbols.iter().flat_map(|s| variants(s)).collect();
+    let rows = db.query(
+        "SELECT ticker, exchange, is_active FROM listed_securities WHERE UPPER(ticker) = ANY($1)",
+        &[&all_variants],
+    ).await?;
+
+    let mut found: HashMap<String, (String, bool)> = HashMap::new();
+    for row in rows {
+        let ticker: String = row.get(0);
+        let exchange: String = row.get(1);
+        let active: Option<bool> = row.get(2);
+        if let Some(canonical) = canonical_symbol(&ticker) {
+            // Prefer an active listing if several variants exist
+            let entry = found.entry(canonical).or_insert((exchange.clone(), false));
+            if active.unwrap_or(true) {
+                *entry = (exchange, true);
+            }
+        }
+    }
+
+    Ok(symbols.iter().map(|sym| {
+        let verdict = match found.get(sym) {
+            None => Some(("unknown", "not in listed_securities".to_string())),
+            Some((_, false)) => Some(("inactive", "listing is not active".to_string())),
+            Some((exchange, true)) if !ALLOWED_EXCHANGES.contains(&exchange.to_uppercase().as_str()) => {
+                Some(("exchange", format!("listed on {}, not a supported exchange", exchange)))
+            }
+            Some(_) => None,
+        };
+        (sym.clone(), verdict)
+    }).collect())
+}
+
+#[derive(Debug, Deserialize)]
+struct ResolveResponse {
+    #[serde(default)]
+    not_found: Vec<String>,
+}
+
+// Symbols (of `symbols`) that don't resolve in `dataset` over the last week
+async fn not_found_in(api_key: &str, dataset: &str, symbols: &[String]) -> anyhow::Result<HashSet<String>> {
+    let end = chrono::Utc::now().date_naive();
+    let start = end - chrono::Duration::days(7);
+
+    let auth_b64 = base64::engine::general_purpose::STANDARD.encode(format!("{}:", api_key));
+    let resp = reqwest::Client::new()
+        .post("https://hist.databento.com/v0/symbology.resolve")
+        .header(ACCEPT, "application/json")
+        .header(AUTHORIZATION, format!("Basic {}", auth_b64))
+        .form(&[
+            ("dataset", dataset),
+            ("symbols", &symbols.join(",")),
+            ("stype_in", "raw_symbol"),
+            ("stype_out", "instrument_id"),
+            ("start_date", &start.to_string()),
+            ("end_date", &end.to_string()),
+        ])
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("symbology.resolve on {} returned {}", dataset, resp.status());
+    }
+    let body: ResolveResponse = resp.json().await?;
+    Ok(body.not_found.into_iter().map(|s| s.to_uppercase()).collect())
+}
+
+// Datasets that may serve a symbol, in the order the router tries them: its route,
+// then the primary and the fallback chain
+fn candidate_datasets(rules: &RoutingRules, routed: String) -> Vec<String> {
+    let mut chain = vec![routed];
+    for dataset in std::iter::once(&rules.primary).chain(&rules.fallbacks) {
+        if !chain.contains(dataset) {
+            chain.push(dataset.clone());
+        }
+    }
+    chain
+}
+
+// Ask Databento which symbols resolve in a dataset that could serve them; a symbol
+// is unknown only when none of them lists it
+async fn check_databento(state: &AppState, symbols: &[String]) -> anyhow::Result<HashMap<String, Option<(&'static str, String)>>> {
+    let api_key = std::env::var("DATABENTO_API_KEY")?;
+    let rules = RoutingRules::from_env();
+    let mut chains: HashMap<String, Vec<String>> = HashMap::new();
+    for sym in symbols {
+        chains.insert(sym.clone(), candidate_datasets(&rules, sessions::dataset_for(state, sym).await));
+    }
+
+    let mut verdicts = HashMap::new();
+    let mut pending: Vec<String> = symbols.to_vec();
+    for round in 0.. {
+        if pending.is_empty() {
+            break;
+        }
+        let mut by_dataset: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for sym in pending.drain(..) {
+            match chains[&sym].get(round) {
+                Some(dataset) => by_dataset.entry(dataset.clone()).or_default().push(sym),
+                None => {
+                    let reason = format!("not found in {}", chains[&sym].join(", "));
+                    verdicts.insert(sym, Some(("unknown", reason)));
+                }
+            }
+        }
+        for (dataset, group) in by_dataset {
+            let not_found = not_found_in(&api_key, &dataset, &group).await?;
+            for sym in group {
+                if not_found.contains(&sym) {
+                    pending.push(sym);
+                } else {
+                    verdicts.insert(sym, None);
+                }
+            }
+        }
+    }
+    Ok(verdicts)
+}
+
+// Canonicalize and validate raw symbols. Symbols that can't be checked because the
+// reference source is unavailable are accepted rather than blocking subscriptions.
+pub async fn resolve_symbols(state: &AppState, raw: &[String]) -> Resolution {
+    let source = universe_source(state);
+    let mut resolution = Resolution { source: source.to_string(), ..Default::default() };
+
+    let mut to_check: Vec<String> = Vec::new();
+    let mut order: Vec<(String, String)> = Vec::new(); // (input, canonical)
+    for input in raw {
+        match canonical_symbol(input) {
+            Some(canonical) => {
+                if !order.iter().any(|(_, c)| *c == canonical) {
+                    order.push((input.clone(), canonical.clone()));
+                    to_check.push(canonical);
+                }
+            }
+            None => resolution.rejected.push(Rejection {
+                input: input.clone(),
+                canonical: None,
+                code: "malformed",
+                reason: "not a valid ticker symbol".to_string(),
+            }),
+        }
+    }
+
+    // Serve from cache where possible
+    let now = current_time_ns();
+    let mut verdicts: HashMap<String, Option<(&'static str, String)>> = HashMap::new();
+    {
+        let cache = state.symbol_cache.read().await;
+        for sym in &to_check {
+            if let Some(v) = cache.get(sym).filter(|v| now.saturating_sub(v.checked_at_ns) < CACHE_TTL_NS) {
+                verdicts.insert(sym.clone(), v.rejection.clone());
+            }
+        }
+    }
+    let uncached: Vec<String> = to_check.iter().filter(|s| !verdicts.contains_key(*s)).cloned().collect();
+
+    if !uncached.is_empty() && source != "none" {
+        let checked = match source {
+            "db" => check_db(state, &uncached).await,
+            _ => check_databento(state, &uncached).await,
+        };
+        match checked {
+            Ok(checked) => {
+                let mut cache = state.symbol_cache.write().await;
+                for (sym, rejection) in checked {
+                    cache.insert(sym.clone(), CachedVerdict { rejection: rejection.clone(), checked_at_ns: now });
+                    verdicts.insert(sym, rejection);
+                }
+            }
+            Err(e) => warn!("Symbol universe check via {} failed, accepting unchecked: {}", source, e),
+        }
+    }
+
+    for (input, canonical) in order {
+        match verdicts.get(&canonical).cloned().flatten() {
+            Some((code, reason)) => resolution.rejected.push(Rejection {
+                input,
+                canonical: Some(canonical),
+                code,
+                reason,
+            }),
+            None => resolution.accepted.push(canonical),
+        }
+    }
+
+    if !resolution.rejected.is_empty() {
+        info!("Rejected symbols: {:?}", resolution.rejected.iter().map(|r| &r.input).collect::<Vec<_>>());
+    }
+    resolution
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveBody {
+    symbols: Vec<String>,
+}
+
+// POST /api/live/resolve { symbols: [...] } - dry run of the subscribe-time check
+pub async fn resolve(State(state): State<AppState>, Json(body): Json<ResolveBody>) -> impl IntoResponse {
+    Json(resolve_symbols(&state, &body.symbols).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates_follow_the_route_then_the_fallback_chain() {
+        let rules = RoutingRules {
+            primary: "EQUS.MINI".into(),
+            routes: HashMap::from([("SPY".to_string(), "ARCX.PILLAR".to_string())]),
+            fallbacks: vec!["XNAS.ITCH".into(), "EQUS.MINI".into()],
+            options: "OPRA.PILLAR".into(),
+        };
+        assert_eq!(candidate_datasets(&rules, rules.route("SPY")), ["ARCX.PILLAR", "EQUS.MINI", "XNAS.ITCH"]);
+        assert_eq!(candidate_datasets(&rules, rules.route("AAPL")), ["EQUS.MINI", "XNAS.ITCH"]);
+    }
+}
@@ -0,0 +1,710 @@
+// Alert rules: conditions in the rule language (see condition.rs) evaluated against
+// per-symbol live fields on every trade, quote and trading status change. A rule
+// fires when its condition becomes true for a symbol, at most once per cooldown,
+// and the hit is pushed on the stream as a `rule` event.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, NaiveDate};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use databento::dbn::{BboMsg, Schema, StatusAction, StatusMsg};
+
+use crate::{
+    bars::{Bar, BAR_NS},
+    calendar,
+    condition::{self, Expr, Field, Fields},
+    current_time_ns, env, hist, norm_symbol, options, resolve, sessions, start_live_subscription,
+    stream::StreamEvent,
+    tape::TapeTrade,
+    to_dt, to_ns, AppState,
+};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+const CREATE_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS alert_rules (
+  id BIGINT PRIMARY KEY,
+  name VARCHAR(255) NOT NULL,
+  condition TEXT NOT NULL,
+  symbols TEXT[] NOT NULL DEFAULT '{}',
+  cooldown_secs BIGINT NOT NULL,
+  enabled BOOLEAN NOT NULL DEFAULT TRUE,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  fire_count BIGINT NOT NULL DEFAULT 0,
+  last_fired JSONB NOT NULL DEFAULT '{}'
+);
+";
+
+fn default_cooldown_secs() -> u64 {
+    env("RULE_COOLDOWN_SECS", 300)
+}
+
+fn pct(from: f64, to: f64) -> Option<f64> {
+    (from > 0.0).then(|| (to - from) / from * 100.0)
+}
+
+// Live per-symbol state the rule fields are computed from; day aggregates reset
+// with the exchange trading day
+#[derive(Clone, Debug, Default)]
+pub struct SymbolFields {
+    day: Option<NaiveDate>,
+    last: Option<f64>,
+    ts_event_ns: u64,
+    prev_close: Option<f64>,
+    prev_close_requested: bool,
+    volume: u64,
+    notional: f64, // sum of price * size, for VWAP
+    hod: Option<f64>,
+    new_hod: bool,
+    bid: Option<f64>,
+    ask: Option<f64>,
+    halted: bool,
+    halt_end_ns: Option<u64>, // when trading resumed after the latest halt today
+}
+
+impl SymbolFields {
+    fn reset_if_new_day(&mut self, ts_ns: u64) {
+        let day = calendar::exchange_date(ts_ns);
+        if self.day != Some(day) {
+            // Quotes and an open halt carry across the date line; day aggregates don't
+            *self = SymbolFields {
+                day: Some(day),
+                bid: self.bid,
+                ask: self.ask,
+                halted: self.halted,
+                ..Default::default()
+            };
+        }
+    }
+
+    pub fn on_trade(&mut self, px: f64, size: u32, ts_ns: u64) {
+        self.reset_if_new_day(ts_ns);
+        self.new_hod = self.hod.is_some_and(|hod| px > hod);
+        self.hod = Some(self.hod.map_or(px, |hod| hod.max(px)));
+        self.volume += size as u64;
+        self.notional += px * size as f64;
+        if ts_ns >= self.ts_event_ns {
+            self.last = Some(px);
+            self.ts_event_ns = ts_ns;
+        }
+    }
+
+    // Historical bars that precede the live data; VWAP uses each bar's typical price
+    pub fn add_bars(&mut self, bars: &[Bar]) {
+        let (Some(first), Some(last)) = (bars.first(), bars.last()) else { return };
+        self.reset_if_new_day(first.start_ns);
+        for bar in bars {
+            self.volume += bar.volume;
+            self.notional += (bar.high + bar.low + bar.close) / 3.0 * bar.volume as f64;
+            self.hod = Some(self.hod.map_or(bar.high, |hod| hod.max(bar.high)));
+        }
+        if self.last.is_none() {
+            self.last = Some(last.close);
+            self.ts_event_ns = last.start_ns + BAR_NS - 1;
+        }
+    }
+
+    pub fn on_quote(&mut self, bid: Option<f64>, ask: Option<f64>, ts_ns: u64) {
+        self.reset_if_new_day(ts_ns);
+        self.bid = bid;
+        self.ask = ask;
+        self.new_hod = false;
+    }
+
+    // False when the symbol was already in that state
+    pub fn on_status(&mut self, halted: bool, ts_ns: u64) -> bool {
+        self.reset_if_new_day(ts_ns);
+        if self.halted == halted {
+            return false;
+        }
+        self.halted = halted;
+        self.new_hod = false;
+        if !halted {
+            self.halt_end_ns = Some(ts_ns);
+        }
+        true
+    }
+
+    pub fn set_prev_close(&mut self, close: f64) {
+        self.prev_close = Some(close);
+    }
+
+    // Last trade price and its event time
+    pub fn last_trade(&self) -> Option<(f64, u64)> {
+        self.last.map(|px| (px, self.ts_event_ns))
+    }
+
+    // Trades recovered after a feed outage
+    fn add_prints(&mut self, prints: &[(f64, u32, u64)]) {
+        let Some((_, _, first_ts)) = prints.first() else { return };
+        self.reset_if_new_day(*first_ts);
+        for &(px, size, _) in prints {
+            self.volume += size as u64;
+            self.notional += px * size as f64;
+            self.hod = Some(self.hod.map_or(px, |hod| hod.max(px)));
+        }
+    }
+
+    fn vwap(&self) -> Option<f64> {
+        (self.volume > 0).then(|| self.notional / self.volume as f64)
+    }
+}
+
+impl Fields for SymbolFields {
+    fn get(&self, field: Field) -> Option<f64> {
+        let flag = |b: bool| Some(if b { 1.0 } else { 0.0 });
+        match field {
+            Field::Last => self.last,
+            Field::ChangePct => pct(self.prev_close?, self.last?),
+            Field::Volume => self.day.map(|_| self.volume as f64),
+            Field::Vwap => self.vwap(),
+            Field::VwapDistPct => pct(self.vwap()?, self.last?),
+            Field::Hod => self.hod,
+            Field::NewHod => flag(self.new_hod),
+            Field::Halted => flag(self.halted),
+            Field::AfterHalt => flag(!self.halted && self.halt_end_ns.is_some()),
+            Field::SinceHaltMin => self.halt_end_ns
+                .filter(|_| !self.halted)
+                .map(|t| self.ts_event_ns.saturating_sub(t) as f64 / (60 * NS_PER_SEC) as f64),
+            Field::Bid => self.bid,
+            Field::Ask => self.ask,
+            Field::SpreadPct => {
+                let (bid, ask) = (self.bid?, self.ask?);
+                let mid = (bid + ask) / 2.0;
+                (bid > 0.0 && ask >= bid).then(|| (ask - bid) / mid * 100.0)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Rule {
+    pub id: u64,
+    pub name: String,
+    pub condition: String,
+    pub symbols: Vec<String>, // empty applies to every live symbol
+    pub cooldown_secs: u64,
+    pub enabled: bool,
+    pub created_at_ns: u64,
+    pub updated_at_ns: u64,
+    pub fire_count: u64,
+    pub last_fired: HashMap<String, u64>, // symbol -> ts_event of the last hit
+    #[serde(skip)]
+    expr: Option<Expr>,
+    // Symbols whose condition held at the last evaluation; a rule only fires on the way in
+    #[serde(skip)]
+    matching: HashSet<String>,
+}
+
+impl Rule {
+    fn applies(&self, symbol: &str) -> bool {
+        self.enabled && (self.symbols.is_empty() || self.symbols.iter().any(|s| s == symbol))
+    }
+
+    fn uses(&self, field: Field) -> bool {
+        self.expr.as_ref().is_some_and(|e| e.uses(field))
+    }
+
+    // Quote and halt fields stay empty unless the equity sessions take those schemas
+    fn warn_unfed(&self) {
+        let schemas = sessions::equity_schemas();
+        let fed = [
+            (schemas.iter().any(|s| matches!(s, Schema::Bbo1S | Schema::Bbo1M)), "bbo-1s", [Field::Bid, Field::Ask, Field::SpreadPct]),
+            (schemas.contains(&Schema::Status), "status", [Field::Halted, Field::AfterHalt, Field::SinceHaltMin]),
+        ];
+        for (subscribed, schema, fields) in fed {
+            if self.enabled && !subscribed && fields.iter().any(|f| self.uses(*f)) {
+                warn!("Rule {} reads fields fed by {}, which DATABENTO_EQUITY_SCHEMAS doesn't subscribe", self.id, schema);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RuleHit {
+    pub rule_id: u64,
+    pub rule_name: String,
+    pub condition: String,
+    pub symbol: String,
+    pub trigger: &'static str, // trade | quote | status
+    pub ts_event_ns: u64,
+    pub fields: BTreeMap<&'static str, f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct RuleBook {
+    next_id: u64,
+    rules: BTreeMap<u64, Rule>,
+    fields: HashMap<String, SymbolFields>,
+}
+
+impl RuleBook {
+    // Evaluate every rule that applies to `symbol`; returns the ones that fired
+    fn evaluate(&mut self, symbol: &str, trigger: &'static str, ts_ns: u64) -> Vec<RuleHit> {
+        let Some(fields) = self.fields.get(symbol) else { return Vec::new() };
+        let mut hits = Vec::new();
+        for rule in self.rules.values_mut().filter(|r| r.applies(symbol)) {
+            let Some(expr) = &rule.expr else { continue };
+            if !expr.eval(fields) {
+                rule.matching.remove(symbol);
+                continue;
+            }
+            if !rule.matching.insert(symbol.to_string()) {
+                continue; // still true from before
+            }
+            let cooled = rule.last_fired.get(symbol)
+                .is_none_or(|t| ts_ns.saturating_sub(*t) >= rule.cooldown_secs * NS_PER_SEC);
+            if !cooled {
+                continue;
+            }
+            rule.last_fired.insert(symbol.to_string(), ts_ns);
+            rule.fire_count += 1;
+            hits.push(RuleHit {
+                rule_id: rule.id,
+                rule_name: rule.name.clone(),
+                condition: rule.condition.clone(),
+                symbol: symbol.to_string(),
+                trigger,
+                ts_event_ns: ts_ns,
+                fields: fields.values(),
+            });
+        }
+        hits
+    }
+
+    // Whether to look up the previous close for `symbol`; true at most once per day
+    fn wants_prev_close(&mut self, symbol: &str) -> Option<NaiveDate> {
+        let used = self.rules.values().any(|r| r.applies(symbol) && r.uses(Field::ChangePct));
+        let fields = self.fields.get_mut(symbol)?;
+        if !used || fields.prev_close.is_some() || fields.prev_close_requested {
+            return None;
+        }
+        fields.prev_close_requested = true;
+        fields.day
+    }
+
+    // Seed day volume, VWAP and HOD from historical bars. Never fires rules.
+    pub fn backfill(&mut self, symbol: &str, bars: &[Bar]) {
+        self.fields.entry(symbol.to_string()).or_default().add_bars(bars);
+    }
+
+    // Fold trades recovered after an outage into the day aggregates. Never fires rules.
+    pub fn missed(&mut self, symbol: &str, prints: &[(f64, u32, u64)]) {
+        self.fields.entry(symbol.to_string()).or_default().add_prints(prints);
+    }
+
+    // Last top of book for `symbol` from the quote feed
+    pub fn quote(&self, symbol: &str) -> Option<(f64, f64)> {
+        let fields = self.fields.get(symbol)?;
+        fields.bid.zip(fields.ask).filter(|(bid, ask)| *bid > 0.0 && ask >= bid)
+    }
+
+    fn create(&mut self, expr: Expr, condition: &str, name: Option<String>, symbols: Vec<String>, cooldown_secs: u64, enabled: bool) -> Rule {
+        let condition = condition.trim().to_string();
+        let now = current_time_ns();
+        self.next_id += 1;
+        let rule = Rule {
+            id: self.next_id,
+            name: name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| condition.clone()),
+            condition,
+            symbols,
+            cooldown_secs,
+            enabled,
+            created_at_ns: now,
+            updated_at_ns: now,
+            fire_count: 0,
+            last_fired: HashMap::new(),
+            expr: Some(expr),
+            matching: HashSet::new(),
+        };
+        self.insert(rule.clone());
+        rule
+    }
+
+    // Drop every symbol's fields, keeping the rules
+    pub fn reset_fields(&mut self) {
+        self.fields.clear();
+    }
+
+    // Add an enabled rule over every symbol, with the default cooldown
+    pub fn add(&mut self, condition: &str, name: Option<String>) -> Result<Rule, condition::ParseError> {
+        let expr = condition::parse(condition)?;
+        Ok(self.create(expr, condition, name, Vec::new(), default_cooldown_secs(), true))
+    }
+
+    fn insert(&mut self, rule: Rule) {
+        self.next_id = self.next_id.max(rule.id);
+        self.rules.insert(rule.id, rule);
+    }
+
+    // Symbols named by enabled rules
+    fn symbols(&self) -> Vec<String> {
+        let set: HashSet<&String> = self.rules.values().filter(|r| r.enabled).flat_map(|r| r.symbols.iter()).collect();
+        set.into_iter().cloned().collect()
+    }
+}
+
+// Create the table and load every rule; symbols named by enabled rules get subscribed
+pub async fn load(state: &AppState) {
+    let Some(db) = &state.db else { return };
+    if let Err(e) = db.batch_execute(CREATE_TABLE).await {
+        error!("Failed to create alert_rules: {}", e);
+        return;
+    }
+    let rows = match db.query(
+        "SELECT id, name, condition, symbols, cooldown_secs, enabled, created_at, updated_at, fire_count, last_fired
+         FROM alert_rules ORDER BY id",
+        &[],
+    ).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to load alert rules: {}", e);
+            return;
+        }
+    };
+
+    let mut book = state.rules.write().await;
+    for r in rows {
+        let id = r.get::<_, i64>(0) as u64;
+        let condition: String = r.get(2);
+        let expr = match condition::parse(&condition) {
+            Ok(expr) => Some(expr),
+            Err(e) => {
+                warn!("Rule {} has an invalid condition '{}': {}", id, condition, e);
+                None
+            }
+        };
+        let created_at: DateTime<chrono::Utc> = r.get(6);
+        let updated_at: DateTime<chrono::Utc> = r.get(7);
+        let last_fired: serde_json::Value = r.get(9);
+        book.insert(Rule {
+            id,
+            name: r.get(1),
+            condition,
+            symbols: r.get(3),
+            cooldown_secs: r.get::<_, i64>(4) as u64,
+            enabled: r.get(5),
+            created_at_ns: to_ns(created_at),
+            updated_at_ns: to_ns(updated_at),
+            fire_count: r.get::<_, i64>(8) as u64,
+            last_fired: serde_json::from_value(last_fired).unwrap_or_default(),
+            expr,
+            matching: HashSet::new(),
+        });
+    }
+    book.rules.values().for_each(Rule::warn_unfed);
+    let symbols = book.symbols();
+    info!("Loaded {} alert rules; watching {} symbols", book.rules.len(), symbols.len());
+    drop(book);
+    if !symbols.is_empty() {
+        let _ = start_live_subscription(symbols, state.clone()).await;
+    }
+}
+
+async fn persist(state: &AppState, rule: &Rule) {
+    let Some(db) = &state.db else { return };
+    let last_fired = serde_json::to_value(&rule.last_fired).unwrap_or_default();
+    let result = db.execute(
+        "INSERT INTO alert_rules (id, name, condition, symbols, cooldown_secs, enabled, created_at, updated_at,
+                                  fire_count, last_fired)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+         ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name,
+                                        condition = EXCLUDED.condition,
+                                        symbols = EXCLUDED.symbols,
+                                        cooldown_secs = EXCLUDED.cooldown_secs,
+                                        enabled = EXCLUDED.enabled,
+                                        updated_at = EXCLUDED.updated_at,
+                                        fire_count = EXCLUDED.fire_count,
+                                        last_fired = EXCLUDED.last_fired",
+        &[
+            &(rule.id as i64),
+            &rule.name,
+            &rule.condition,
+            &rule.symbols,
+            &(rule.cooldown_secs as i64),
+            &rule.enabled,
+            &to_dt(rule.created_at_ns),
+            &to_dt(rule.updated_at_ns),
+            &(rule.fire_count as i64),
+            &last_fired,
+        ],
+    ).await;
+    if let Err(e) = result {
+        error!("Failed to persist rule {}: {}", rule.id, e);
+    }
+}
+
+fn publish_hits(state: &AppState, hits: Vec<RuleHit>) {
+    for hit in hits {
+        info!("Rule {} ({}) fired for {} on {}", hit.rule_id, hit.condition, hit.symbol, hit.trigger);
+        let rule_id = hit.rule_id;
+        state.events.publish(StreamEvent::Rule(hit));
+        let persist_state = state.clone();
+        tokio::spawn(async move {
+            let rule = persist_state.rules.read().await.rules.get(&rule_id).cloned();
+            if let Some(rule) = rule {
+                persist(&persist_state, &rule).await;
+            }
+        });
+    }
+}
+
+async fn fetch_prev_close(state: AppState, symbol: String, date: NaiveDate) {
+    match hist::previous_close(&sessions::dataset_for(&state, &symbol).await, &symbol, date).await {
+        Ok(Some(close)) => {
+            info!("Previous close for {}: ${:.4}", symbol, close);
+            let mut book = state.rules.write().await;
+            if let Some(fields) = book.fields.get_mut(&symbol).filter(|f| f.day == Some(date)) {
+                fields.prev_close = Some(close);
+            }
+        }
+        Ok(None) => info!("No previous close found for {}", symbol),
+        Err(e) => warn!("Previous close lookup failed for {}: {}", symbol, e),
+    }
+}
+
+// Called for every live trade
+pub async fn on_trade(state: &AppState, trade: &TapeTrade) {
+    let (hits, prev_close_day) = {
+        let mut book = state.rules.write().await;
+        book.fields.entry(trade.symbol.clone()).or_default().on_trade(trade.price, trade.size, trade.ts_event);
+        let prev_close_day = book.wants_prev_close(&trade.symbol);
+        (book.evaluate(&trade.symbol, "trade", trade.ts_event), prev_close_day)
+    };
+    if let Some(date) = prev_close_day {
+        tokio::spawn(fetch_prev_close(state.clone(), trade.symbol.clone(), date));
+    }
+    publish_hits(state, hits);
+}
+
+// Top of book from the equity quote feed
+pub async fn apply_quote(state: &AppState, symbol: &str, quote: &BboMsg) {
+    if state.blacklist.read().await.is_muted(symbol) {
+        return;
+    }
+    let level = &quote.levels[0];
+    let hits = {
+        let mut book = state.rules.write().await;
+        book.fields.entry(symbol.to_string()).or_default().on_quote(options::px(level.bid_px), options::px(level.ask_px), quote.ts_recv);
+        book.evaluate(symbol, "quote", quote.ts_recv)
+    };
+    publish_hits(state, hits);
+}
+
+// Whether a status action halts (true) or resumes (false) trading; None leaves it as is
+pub fn halts(action: StatusAction) -> Option<bool> {
+    match action {
+        StatusAction::Halt | StatusAction::Pause | StatusAction::Suspend => Some(true),
+        StatusAction::Trading => Some(false),
+        _ => None,
+    }
+}
+
+// Trading halts and resumptions; published as symbol status events
+pub async fn apply_status(state: &AppState, dataset: &str, symbol: &str, msg: &StatusMsg) {
+    let Some(halted) = msg.action().ok().and_then(halts) else { return };
+    let ts_ns = msg.hd.ts_event;
+    let hits = {
+        let mut book = state.rules.write().await;
+        if !book.fields.entry(symbol.to_string()).or_default().on_status(halted, ts_ns) {
+            return;
+        }
+        book.evaluate(symbol, "status", ts_ns)
+    };
+    let reason = msg.reason().ok().map(|r| format!("{:?}", r));
+    info!("{} {} on {} ({:?})", symbol, if halted { "halted" } else { "resumed" }, dataset, reason);
+    state.events.publish(StreamEvent::Status {
+        symbol: Some(symbol.to_string()),
+        dataset: dataset.to_string(),
+        status: if halted { "halted" } else { "resumed" }.to_string(),
+        detail: reason,
+    });
+    publish_hits(state, hits);
+}
+
+// Canonicalize rule symbols, subscribing any that aren't live yet
+async fn watch_symbols(state: &AppState, symbols: &[String]) -> Result<Vec<String>, serde_json::Value> {
+    if symbols.is_empty() {
+        return Ok(Vec::new());
+    }
+    let resolution = resolve::resolve_symbols(state, symbols).await;
+    if !resolution.rejected.is_empty() {
+        return Err(serde_json::json!({"error": "invalid symbols", "rejected": resolution.rejected}));
+    }
+    let missing: Vec<String> = {
+        let subscribed = state.subscribed_symbols.read().await;
+        resolution.accepted.iter().filter(|s| !subscribed.contains(*s)).cloned().collect()
+    };
+    if !missing.is_empty() {
+        if let Err(e) = start_live_subscription(missing, state.clone()).await {
+            error!("Failed to subscribe rule symbols: {}", e);
+        }
+    }
+    Ok(resolution.accepted)
+}
+
+fn bad_condition(e: condition::ParseError) -> (StatusCode, Json<serde_json::Value>) {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+        "error": format!("invalid condition: {}", e),
+        "position": e.pos
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRuleBody {
+    condition: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    symbols: Vec<String>,
+    #[serde(default)]
+    cooldown_secs: Option<u64>,
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+// POST /api/rules { condition, name?, symbols?: [...], cooldown_secs?, enabled? }
+// Without symbols the rule applies to every live symbol.
+pub async fn create_rule(State(state): State<AppState>, Json(body): Json<CreateRuleBody>) -> impl IntoResponse {
+    let expr = match condition::parse(&body.condition) {
+        Ok(expr) => expr,
+        Err(e) => return bad_condition(e),
+    };
+    let symbols = match watch_symbols(&state, &body.symbols).await {
+        Ok(symbols) => symbols,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, Json(e)),
+    };
+
+    let rule = state.rules.write().await.create(
+        expr,
+        &body.condition,
+        body.name,
+        symbols,
+        body.cooldown_secs.unwrap_or_else(default_cooldown_secs),
+        body.enabled.unwrap_or(true),
+    );
+    persist(&state, &rule).await;
+    rule.warn_unfed();
+    info!("Rule {} created: {}", rule.id, rule.condition);
+    (StatusCode::CREATED, Json(serde_json::json!(rule)))
+}
+
+// GET /api/rules
+pub async fn list_rules(State(state): State<AppState>) -> impl IntoResponse {
+    let rules: Vec<Rule> = state.rules.read().await.rules.values().cloned().collect();
+    Json(serde_json::json!({ "rules": rules }))
+}
+
+// GET /api/rules/:id
+pub async fn get_rule(Path(id): Path<u64>, State(state): State<AppState>) -> impl IntoResponse {
+    match state.rules.read().await.rules.get(&id) {
+        Some(rule) => (StatusCode::OK, Json(serde_json::json!(rule))),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such rule"}))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRuleBody {
+    #[serde(default)]
+    condition: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    symbols: Option<Vec<String>>,
+    #[serde(default)]
+    cooldown_secs: Option<u64>,
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+// PUT /api/rules/:id { condition?, name?, symbols?, cooldown_secs?, enabled? } - omitted fields are kept
+pub async fn update_rule(
+    Path(id): Path<u64>,
+    State(state): State<AppState>,
+    Json(body): Json<UpdateRuleBody>,
+) -> impl IntoResponse {
+    let expr = match body.condition.as_deref().map(condition::parse).transpose() {
+        Ok(expr) => expr,
+        Err(e) => return bad_condition(e),
+    };
+    if !state.rules.read().await.rules.contains_key(&id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such rule"})));
+    }
+    let symbols = match &body.symbols {
+        Some(symbols) => match watch_symbols(&state, symbols).await {
+            Ok(symbols) => Some(symbols),
+            Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, Json(e)),
+        },
+        None => None,
+    };
+
+    let rule = {
+        let mut book = state.rules.write().await;
+        let Some(rule) = book.rules.get_mut(&id) else {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such rule"})));
+        };
+        if let (Some(condition), Some(expr)) = (body.condition, expr) {
+            rule.condition = condition.trim().to_string();
+            rule.expr = Some(expr);
+            rule.matching.clear();
+        }
+        if let Some(name) = body.name.filter(|n| !n.trim().is_empty()) {
+            rule.name = name;
+        }
+        if let Some(symbols) = symbols {
+            rule.symbols = symbols;
+        }
+        if let Some(cooldown_secs) = body.cooldown_secs {
+            rule.cooldown_secs = cooldown_secs;
+        }
+        if let Some(enabled) = body.enabled {
+            rule.enabled = enabled;
+            rule.matching.clear();
+        }
+        rule.updated_at_ns = current_time_ns();
+        rule.clone()
+    };
+    persist(&state, &rule).await;
+    rule.warn_unfed();
+    (StatusCode::OK, Json(serde_json::json!(rule)))
+}
+
+// DELETE /api/rules/:id
+pub async fn delete_rule(Path(id): Path<u64>, State(state): State<AppState>) -> impl IntoResponse {
+    let Some(rule) = state.rules.write().await.rules.remove(&id) else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no such rule"})));
+    };
+    if let Some(db) = &state.db {
+        if let Err(e) = db.execute("DELETE FROM alert_rules WHERE id = $1", &[&(id as i64)]).await {
+            error!("Failed to delete rule {}: {}", id, e);
+        }
+    }
+    info!("Rule {} deleted", id);
+    (StatusCode::OK, Json(serde_json::json!(rule)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FieldsQuery {
+    #[serde(default)]
+    symbol: Option<String>,
+}
+
+// GET /api/rules/fields[?symbol=AAPL] - current field values rules are evaluated against
+pub async fn get_fields(Query(q): Query<FieldsQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let book = state.rules.read().await;
+    let symbol = q.symbol.map(|s| norm_symbol(&s));
+    let fields: BTreeMap<&String, BTreeMap<&'static str, f64>> = book.fields.iter()
+        .filter(|(sym, _)| symbol.as_ref().is_none_or(|s| s == *sym))
+        .map(|(sym, f)| (sym, f.values()))
+        .collect();
+    Json(serde_json::json!({ "fields": fields }))
+}
@@ -0,0 +1,348 @@
+// Momentum / volume-spike scanner over every symbol that trades. Driven purely by
+// event time (trade and bar timestamps) so the same logic can run over replayed data.
+
+use std::collections::{HashMap, VecDeque};
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{bars::Bar, calendar::exchange_date, env, AppState};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+const WINDOWS_SECS: [u64; 3] = [60, 5 * 60, 15 * 60];
+// Bars used as the relative-volume baseline
+const BASELINE_BARS: usize = 20;
+
+#[derive(Clone, Debug)]
+pub struct ScannerConfig {
+    pub move_pct: [f64; 3], // thresholds for the 1/5/15-minute windows
+    pub rvol: f64,
+    pub min_bar_volume: u64,
+    pub green_bars: u32,
+    pub cooldown_ns: u64,
+}
+
+impl ScannerConfig {
+    pub fn from_env() -> Self {
+        ScannerConfig {
+            move_pct: [
+                env("SCANNER_MOVE_1M_PCT", 2.0),
+                env("SCANNER_MOVE_5M_PCT", 5.0),
+                env("SCANNER_MOVE_15M_PCT", 10.0),
+            ],
+            rvol: env("SCANNER_RVOL", 3.0),
+            min_bar_volume: env("SCANNER_MIN_BAR_VOLUME", 10_000),
+            green_bars: env("SCANNER_GREEN_BARS", 3),
+            cooldown_ns: env("SCANNER_COOLDOWN_SECS", 60u64) * NS_PER_SEC,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScannerHit {
+    pub symbol: String,
+    pub kind: &'static str, // move_1m | move_5m | move_15m | volume_spike | hod_break | green_streak
+    pub value: f64,
+    pub price: f64,
+    pub ts_event_ns: u64,
+    pub score: f64,
+    pub rank: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Mover {
+    pub symbol: String,
+    pub last: f64,
+    pub change_1m: Option<f64>,
+    pub change_5m: Option<f64>,
+    pub change_15m: Option<f64>,
+    pub rel_volume: Option<f64>,
+    pub hod: f64,
+    pub hod_breaks: u32,
+    pub green_streak: u32,
+    pub score: f64,
+    pub ts_event_ns: u64,
+}
+
+#[derive(Debug, Default)]
+struct SymbolScan {
+    day: Option<NaiveDate>,
+    last: f64,
+    last_ts: u64,
+    hod: f64,
+    hod_breaks: u32,
+    last_hod_break_ns: u64,
+    // One price sample per second over the longest window
+    samples: VecDeque<(u64, f64)>,
+    bar_volumes: VecDeque<u64>,
+    rel_volume: Option<f64>,
+    green_streak: u32,
+    last_hit_ns: HashMap<&'static str, u64>,
+}
+
+fn pct(from: f64, to: f64) -> Option<f64> {
+    (from > 0.0).then(|| (to - from) / from * 100.0)
+}
+
+impl SymbolScan {
+    // HOD and streaks reset with the exchange trading day
+    fn reset_if_new_day(&mut self, ts_ns: u64) {
+        let day = exchange_date(ts_ns);
+        if self.day != Some(day) {
+            *self = SymbolScan { day: Some(day), ..Default::default() };
+        }
+    }
+
+    // Percent change over a window ending at the latest sample. Uses the newest
+    // sample at or before the window start, or the oldest one if history is shorter.
+    fn change(&self, window_ns: u64) -> Option<f64> {
+        let start = self.last_ts.saturating_sub(window_ns);
+        let base = self.samples.iter().rev().find(|(ts, _)| *ts <= start).or(self.samples.front())?;
+        if base.0 == self.last_ts {
+            return None;
+        }
+        pct(base.1, self.last)
+    }
+
+    fn changes(&self) -> [Option<f64>; 3] {
+        WINDOWS_SECS.map(|w| self.change(w * NS_PER_SEC))
+    }
+
+    fn score(&self) -> f64 {
+        let [c1, c5, c15] = self.changes();
+        let up = |c: Option<f64>| c.unwrap_or(0.0).max(0.0);
+        let mut score = up(c5) + 0.5 * up(c1) + 0.25 * up(c15);
+        if let Some(rvol) = self.rel_volume.filter(|r| *r > 1.0) {
+            score += 2.0 * rvol.ln();
+        }
+        // Recently breaking HOD counts while it's fresh
+        if self.last_ts.saturating_sub(self.last_hod_break_ns) < 60 * NS_PER_SEC && self.hod_breaks > 0 {
+            score += 2.0;
+        }
+        score + self.green_streak as f64 * 0.5
+    }
+
+    fn cooled_down(&mut self, kind: &'static str, ts_ns: u64, cooldown_ns: u64) -> bool {
+        let last = self.last_hit_ns.get(kind).copied().unwrap_or(0);
+        if last != 0 && ts_ns.saturating_sub(last) < cooldown_ns {
+            return false;
+        }
+        self.last_hit_ns.insert(kind, ts_ns);
+        true
+    }
+}
+
+pub struct Scanner {
+    config: ScannerConfig,
+    symbols: HashMap<String, SymbolScan>,
+}
+
+impl Scanner {
+    pub fn new(config: ScannerConfig) -> Self {
+        Scanner { config, symbols: HashMap::new() }
+    }
+
+    // Forget every symbol's day, keeping the configuration
+    pub fn reset(&mut self) {
+        self.symbols.clear();
+    }
+
+    pub fn on_trade(&mut self, symbol: &str, px: f64, ts_ns: u64) -> Vec<ScannerHit> {
+        let cfg = self.config.clone();
+        let scan = self.symbols.entry(symbol.to_string()).or_default();
+        scan.reset_if_new_day(ts_ns);
+        if ts_ns < scan.last_ts {
+            return Vec::new();
+        }
+
+        let mut kinds: Vec<(&'static str, f64)> = Vec::new();
+
+        if scan.hod > 0.0 && px > scan.hod {
+            scan.hod_breaks += 1;
+            scan.last_hod_break_ns = ts_ns;
+            kinds.push(("hod_break", px));
+        }
+        scan.hod = scan.hod.max(px);
+        scan.last = px;
+        scan.last_ts = ts_ns;
+
+        let oldest_needed = ts_ns.saturating_sub((WINDOWS_SECS[2] + 60) * NS_PER_SEC);
+        while scan.samples.front().is_some_and(|(ts, _)| *ts < oldest_needed) {
+            scan.samples.pop_front();
+        }
+        match scan.samples.back_mut() {
+            Some(last) if ts_ns - last.0 < NS_PER_SEC => last.1 = px,
+            _ => scan.samples.push_back((ts_ns, px)),
+        }
+
+        for (i, change) in scan.changes().into_iter().enumerate() {
+            if let Some(c) = change.filter(|c| *c >= cfg.move_pct[i]) {
+                kinds.push((["move_1m", "move_5m", "move_15m"][i], c));
+            }
+        }
+
+        self.emit(symbol, kinds, px, ts_ns)
+    }
+
+    pub fn on_bar(&mut self, bar: &Bar) -> Vec<ScannerHit> {
+        let cfg = self.config.clone();
+        let scan = self.symbols.entry(bar.symbol.clone()).or_default();
+        scan.reset_if_new_day(bar.start_ns);
+
+        let mut kinds: Vec<(&'static str, f64)> = Vec::new();
+
+        if scan.bar_volumes.len() >= 5 {
+            let baseline = scan.bar_volumes.iter().sum::<u64>() as f64 / scan.bar_volumes.len() as f64;
+            if baseline > 0.0 {
+                let rvol = bar.volume as f64 / baseline;
+                scan.rel_volume = Some(rvol);
+                if rvol >= cfg.rvol && bar.volume >= cfg.min_bar_volume {
+                    kinds.push(("volume_spike", rvol));
+                }
+            }
+        }
+        scan.bar_volumes.push_back(bar.volume);
+        while scan.bar_volumes.len() > BASELINE_BARS {
+            scan.bar_volumes.pop_front();
+        }
+
+        if bar.close > bar.open {
+            scan.green_streak += 1;
+            if scan.green_streak >= cfg.green_bars {
+                kinds.push(("green_streak", scan.green_streak as f64));
+            }
+        } else {
+            scan.green_streak = 0;
+        }
+
+        let ts = bar.start_ns + crate::bars::BAR_NS;
+        self.emit(&bar.symbol, kinds, bar.close, ts)
+    }
+
+    // Seed HOD, the volume baseline, streaks and move windows from historical bars
+    // that precede the live data. Never emits hits.
+    pub fn backfill(&mut self, symbol: &str, bars: &[Bar]) {
+        let (Some(first), Some(last)) = (bars.first(), bars.last()) else { return };
+        let scan = self.symbols.entry(symbol.to_string()).or_default();
+        scan.reset_if_new_day(first.start_ns);
+
+        scan.hod = bars.iter().fold(scan.hod, |hod, b| hod.max(b.high));
+
+        let live_volumes: Vec<u64> = scan.bar_volumes.drain(..).collect();
+        if live_volumes.is_empty() {
+            scan.green_streak = bars.iter().rev().take_while(|b| b.close > b.open).count() as u32;
+        }
+        scan.bar_volumes = bars.iter().map(|b| b.volume).chain(live_volumes).collect();
+        while scan.bar_volumes.len() > BASELINE_BARS {
+            scan.bar_volumes.pop_front();
+        }
+
+        let last_ts = last.start_ns + crate::bars::BAR_NS - 1;
+        if scan.last_ts == 0 {
+            scan.last = last.close;
+            scan.last_ts = last_ts;
+        }
+        let oldest_needed = scan.last_ts.saturating_sub((WINDOWS_SECS[2] + 60) * NS_PER_SEC);
+        let first_live = scan.samples.front().map(|(ts, _)| *ts).unwrap_or(u64::MAX);
+        for bar in bars.iter().rev() {
+            let ts = bar.start_ns + crate::bars::BAR_NS - 1;
+            if ts >= oldest_needed && ts < first_live {
+                scan.samples.push_front((ts, bar.close));
+            }
+        }
+    }
+
+    // Bring HOD and the volume baseline back in line after missed trades were folded
+    // into `closed` bars. Never emits hits.
+    pub fn resync(&mut self, symbol: &str, high: f64, closed: &[Bar]) {
+        let Some(scan) = self.symbols.get_mut(symbol) else { return };
+        scan.hod = scan.hod.max(high);
+        scan.bar_volumes = closed.iter().rev().take(BASELINE_BARS).rev().map(|b| b.volume).collect();
+    }
+
+    fn emit(&mut self, symbol: &str, kinds: Vec<(&'static str, f64)>, px: f64, ts_ns: u64) -> Vec<ScannerHit> {
+        if kinds.is_empty() {
+            return Vec::new();
+        }
+        let cooldown = self.config.cooldown_ns;
+        let Some(scan) = self.symbols.get_mut(symbol) else { return Vec::new() };
+        let fired: Vec<(&'static str, f64)> = kinds.into_iter()
+            .filter(|(kind, _)| scan.cooled_down(kind, ts_ns, cooldown))
+            .collect();
+        if fired.is_empty() {
+            return Vec::new();
+        }
+        let score = scan.score();
+        let rank = self.rank_of(symbol);
+        fired.into_iter().map(|(kind, value)| ScannerHit {
+            symbol: symbol.to_string(),
+            kind,
+            value,
+            price: px,
+            ts_event_ns: ts_ns,
+            score,
+            rank,
+        }).collect()
+    }
+
+    fn rank_of(&self, symbol: &str) -> usize {
+        let Some(score) = self.symbols.get(symbol).map(|s| s.score()) else { return 0 };
+        1 + self.symbols.iter().filter(|(sym, s)| sym.as_str() != symbol && s.score() > score).count()
+    }
+
+    // Symbols ranked by score, best first
+    pub fn movers(&self) -> Vec<Mover> {
+        let mut movers: Vec<Mover> = self.symbols.iter()
+            .filter(|(_, s)| s.last_ts > 0)
+            .map(|(symbol, s)| {
+                let [change_1m, change_5m, change_15m] = s.changes();
+                Mover {
+                    symbol: symbol.clone(),
+                    last: s.last,
+                    change_1m,
+                    change_5m,
+                    change_15m,
+                    rel_volume: s.rel_volume,
+                    hod: s.hod,
+                    hod_breaks: s.hod_breaks,
+                    green_streak: s.green_streak,
+                    score: s.score(),
+                    ts_event_ns: s.last_ts,
+                }
+            })
+            .collect();
+        movers.sort_by(|a, b| b.score.total_cmp(&a.score));
+        movers
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MoversQuery {
+    #[serde(default)]
+    limit: Option<usize>,
+    // score (default) | change_1m | change_5m | change_15m | rel_volume
+    #[serde(default)]
+    sort: Option<String>,
+}
+
+// GET /api/live/movers[?limit=20&sort=change_5m]
+pub async fn get_movers(Query(q): Query<MoversQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let mut movers = state.scanner.read().await.movers();
+    let key = |m: &Mover| -> f64 {
+        match q.sort.as_deref() {
+            Some("change_1m") => m.change_1m.unwrap_or(f64::MIN),
+            Some("change_5m") => m.change_5m.unwrap_or(f64::MIN),
+            Some("change_15m") => m.change_15m.unwrap_or(f64::MIN),
+            Some("rel_volume") => m.rel_volume.unwrap_or(f64::MIN),
+            _ => m.score,
+        }
+    };
+    movers.sort_by(|a, b| key(b).total_cmp(&key(a)));
+    movers.truncate(q.limit.unwrap_or(20));
+    Json(serde_json::json!({ "movers": movers }))
+}
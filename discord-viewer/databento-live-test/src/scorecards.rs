@@ -0,0 +1,371 @@
+// Trader scorecards. Each author's first-mention calls (the earliest detection of a
+// ticker on a trading day) are scored from their outcomes over trailing periods:
+// hit rate at +X%, average max gain and drawdown, median minutes to the peak and the
+// average return at the close. Recomputed periodically; stored in trader_scorecards
+// when Postgres is configured and served to the trader filter from memory.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{calendar, current_time_ns, env, to_dt, to_ns, AppState};
+
+const NS_PER_DAY: u64 = 86_400 * 1_000_000_000;
+
+const CREATE_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS trader_scorecards (
+  author VARCHAR(255) NOT NULL,
+  period_days INTEGER NOT NULL,
+  calls INTEGER NOT NULL,
+  scored INTEGER NOT NULL,
+  hits INTEGER NOT NULL,
+  hit_threshold_pct DOUBLE PRECISION NOT NULL,
+  hit_rate DOUBLE PRECISION,
+  avg_max_gain_pct DOUBLE PRECISION,
+  avg_max_drawdown_pct DOUBLE PRECISION,
+  median_mins_to_peak DOUBLE PRECISION,
+  avg_close_return_pct DOUBLE PRECISION,
+  last_call_at TIMESTAMP WITH TIME ZONE,
+  computed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  PRIMARY KEY (author, period_days)
+);
+";
+
+#[derive(Clone, Debug)]
+pub struct ScorecardConfig {
+    pub periods_days: Vec<u32>,
+    pub hit_pct: f64,
+    pub interval_secs: u64,
+}
+
+impl ScorecardConfig {
+    pub fn from_env() -> Self {
+        let mut periods_days: Vec<u32> = std::env::var("SCORECARD_PERIODS_DAYS")
+            .unwrap_or_else(|_| "7,30,90".to_string())
+            .split(',')
+            .filter_map(|p| p.trim().trim_end_matches('d').parse().ok())
+            .filter(|p| *p > 0)
+            .collect();
+        periods_days.sort();
+        periods_days.dedup();
+        ScorecardConfig {
+            periods_days,
+            hit_pct: env("SCORECARD_HIT_PCT", 10.0),
+            interval_secs: env("SCORECARD_INTERVAL_SECS", 900u64),
+        }
+    }
+}
+
+// One first-mention call and how it played out
+#[derive(Clone, Debug)]
+struct Call {
+    author: String,
+    symbol: String,
+    detected_at_ns: u64,
+    max_gain_pct: Option<f64>,
+    max_drawdown_pct: Option<f64>,
+    peak_ts_ns: Option<u64>,
+    close_return_pct: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Scorecard {
+    pub author: String,
+    pub period_days: u32,
+    pub calls: u32,
+    pub scored: u32, // calls with an outcome to score
+    pub hits: u32,
+    pub hit_threshold_pct: f64,
+    pub hit_rate: Option<f64>,
+    pub avg_max_gain_pct: Option<f64>,
+    pub avg_max_drawdown_pct: Option<f64>,
+    pub median_mins_to_peak: Option<f64>,
+    pub avg_close_return_pct: Option<f64>,
+    pub last_call_ns: u64,
+    pub last_call_symbol: String,
+}
+
+#[derive(Debug)]
+pub struct Scorecards {
+    pub config: ScorecardConfig,
+    pub computed_at_ns: u64,
+    cards: BTreeMap<(String, u32), Scorecard>,
+}
+
+impl Scorecards {
+    pub fn new() -> Self {
+        Scorecards { config: ScorecardConfig::from_env(), computed_at_ns: 0, cards: BTreeMap::new() }
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] })
+}
+
+// Keep the earliest detection of each symbol per trading day
+fn first_mentions(mut calls: Vec<Call>) -> Vec<Call> {
+    calls.sort_by_key(|c| c.detected_at_ns);
+    let mut seen: HashSet<(String, NaiveDate)> = HashSet::new();
+    calls.into_iter()
+        .filter(|c| seen.insert((c.symbol.clone(), calendar::exchange_date(c.detected_at_ns))))
+        .collect()
+}
+
+fn score(calls: &[Call], config: &ScorecardConfig, now_ns: u64) -> BTreeMap<(String, u32), Scorecard> {
+    let mut cards = BTreeMap::new();
+    for period in &config.periods_days {
+        let since = now_ns.saturating_sub(*period as u64 * NS_PER_DAY);
+        let mut by_author: HashMap<&str, Vec<&Call>> = HashMap::new();
+        for call in calls.iter().filter(|c| c.detected_at_ns >= since) {
+            by_author.entry(call.author.as_str()).or_default().push(call);
+        }
+        for (author, calls) in by_author {
+            let gains: Vec<f64> = calls.iter().filter_map(|c| c.max_gain_pct).collect();
+            let drawdowns: Vec<f64> = calls.iter().filter_map(|c| c.max_drawdown_pct).collect();
+            let closes: Vec<f64> = calls.iter().filter_map(|c| c.close_return_pct).collect();
+            let to_peak: Vec<f64> = calls.iter()
+                .filter(|c| c.max_gain_pct.is_some_and(|g| g > 0.0))
+                .filter_map(|c| c.peak_ts_ns.map(|p| p.saturating_sub(c.detected_at_ns) as f64 / 60e9))
+                .collect();
+            let hits = gains.iter().filter(|g| **g >= config.hit_pct).count() as u32;
+            let last = calls.iter().max_by_key(|c| c.detected_at_ns).expect("author has calls");
+            cards.insert((author.to_string(), *period), Scorecard {
+                author: author.to_string(),
+                period_days: *period,
+                calls: calls.len() as u32,
+                scored: gains.len() as u32,
+                hits,
+                hit_threshold_pct: config.hit_pct,
+                hit_rate: (!gains.is_empty()).then(|| hits as f64 / gains.len() as f64),
+                avg_max_gain_pct: mean(&gains),
+                avg_max_drawdown_pct: mean(&drawdowns),
+                median_mins_to_peak: median(to_peak),
+                avg_close_return_pct: mean(&closes),
+                last_call_ns: last.detected_at_ns,
+                last_call_symbol: last.symbol.clone(),
+            });
+        }
+    }
+    cards
+}
+
+// Calls in the longest period, from Postgres when configured
+async fn load_calls(state: &AppState, since_ns: u64) -> Option<Vec<Call>> {
+    let Some(db) = &state.db else {
+        let detections = state.detections.read().await;
+        return Some(detections.values()
+            .flatten()
+            .filter(|d| d.detected_at_ns >= since_ns)
+            .filter_map(|d| {
+                let author = d.author.clone().filter(|a| !a.is_empty())?;
+                let outcome = d.outcome.as_ref();
+                Some(Call {
+                    author,
+                    symbol: d.symbol.clone(),
+                    detected_at_ns: d.detected_at_ns,
+                    max_gain_pct: outcome.map(|o| o.max_gain_pct),
+                    max_drawdown_pct: outcome.map(|o| o.max_drawdown_pct),
+                    peak_ts_ns: outcome.map(|o| o.max_price_ts_ns),
+                    close_return_pct: outcome.and_then(|o| o.close_price.map(|c| (c - o.entry_price) / o.entry_price * 100.0)),
+                })
+            })
+            .collect());
+    };
+    let since = to_dt(since_ns);
+    let rows = db.query(
+        "SELECT author, ticker, detected_at, max_gain_pct, max_drawdown_pct, max_price_ts,
+                (close_price - price::float8) / NULLIF(price::float8, 0) * 100
+         FROM ticker_detection_prices
+         WHERE detected_at >= $1 AND author IS NOT NULL AND author <> ''",
+        &[&since],
+    ).await;
+    match rows {
+        Ok(rows) => Some(rows.iter().map(|r| Call {
+            author: r.get(0),
+            symbol: r.get(1),
+            detected_at_ns: to_ns(r.get(2)),
+            max_gain_pct: r.get(3),
+            max_drawdown_pct: r.get(4),
+            peak_ts_ns: r.get::<_, Option<DateTime<Utc>>>(5).map(to_ns),
+            close_return_pct: r.get(6),
+        }).collect()),
+        Err(e) => {
+            error!("Failed to load calls for scorecards: {}", e);
+            None
+        }
+    }
+}
+
+pub async fn recompute(state: &AppState) {
+    let config = state.scorecards.read().await.config.clone();
+    let Some(longest) = config.periods_days.last() else { return };
+    let now = current_time_ns();
+    let Some(calls) = load_calls(state, now.saturating_sub(*longest as u64 * NS_PER_DAY)).await else { return };
+    let calls = first_mentions(calls);
+    let cards = score(&calls, &config, now);
+    info!("Scored {} first-mention calls into {} trader scorecards", calls.len(), cards.len());
+    persist(state, &cards, now).await;
+
+    let mut scorecards = state.scorecards.write().await;
+    scorecards.cards = cards;
+    scorecards.computed_at_ns = now;
+}
+
+async fn persist(state: &AppState, cards: &BTreeMap<(String, u32), Scorecard>, now_ns: u64) {
+    let Some(db) = &state.db else { return };
+    let computed_at = to_dt(now_ns);
+    for card in cards.values() {
+        let result = db.execute(
+            "INSERT INTO trader_scorecards (author, period_days, calls, scored, hits, hit_threshold_pct, hit_rate,
+                avg_max_gain_pct, avg_max_drawdown_pct, median_mins_to_peak, avg_close_return_pct, last_call_at, computed_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+             ON CONFLICT (author, period_days) DO UPDATE SET
+                calls = EXCLUDED.calls, scored = EXCLUDED.scored, hits = EXCLUDED.hits,
+                hit_threshold_pct = EXCLUDED.hit_threshold_pct, hit_rate = EXCLUDED.hit_rate,
+                avg_max_gain_pct = EXCLUDED.avg_max_gain_pct, avg_max_drawdown_pct = EXCLUDED.avg_max_drawdown_pct,
+                median_mins_to_peak = EXCLUDED.median_mins_to_peak, avg_close_return_pct = EXCLUDED.avg_close_return_pct,
+                last_call_at = EXCLUDED.last_call_at, computed_at = EXCLUDED.computed_at",
+            &[
+                &card.author,
+                &(card.period_days as i32),
+                &(card.calls as i32),
+                &(card.scored as i32),
+                &(card.hits as i32),
+                &card.hit_threshold_pct,
+                &card.hit_rate,
+                &card.avg_max_gain_pct,
+                &card.avg_max_drawdown_pct,
+                &card.median_mins_to_peak,
+                &card.avg_close_return_pct,
+                &to_dt(card.last_call_ns),
+                &computed_at,
+            ],
+        ).await;
+        if let Err(e) = result {
+            error!("Failed to persist scorecard for {}: {}", card.author, e);
+        }
+    }
+    // Authors with no calls left in the window
+    if let Err(e) = db.execute("DELETE FROM trader_scorecards WHERE computed_at < $1", &[&computed_at]).await {
+        error!("Failed to prune trader scorecards: {}", e);
+    }
+}
+
+// Recompute every SCORECARD_INTERVAL_SECS (default 900)
+pub async fn run(state: AppState) {
+    if let Some(db) = &state.db {
+        if let Err(e) = db.batch_execute(CREATE_TABLE).await {
+            error!("Failed to create trader_scorecards: {}", e);
+        }
+    }
+    let secs = state.scorecards.read().await.config.interval_secs;
+    let mut ticker = tokio::time::interval(tokio::time::Duration::from_secs(secs.max(1)));
+    loop {
+        ticker.tick().await;
+        recompute(&state).await;
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScorecardsQuery {
+    // Period in days, e.g. 30 or 30d; defaults to the shortest configured
+    #[serde(default)]
+    period: Option<String>,
+    // Comma-separated usernames
+    #[serde(default)]
+    authors: Option<String>,
+    #[serde(default)]
+    min_calls: Option<u32>,
+    // hit_rate | avg_max_gain | calls | avg_close_return; descending
+    #[serde(default)]
+    sort: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+fn parse_period(s: Option<&str>, scorecards: &Scorecards) -> Result<u32, String> {
+    let configured = &scorecards.config.periods_days;
+    let Some(s) = s else { return configured.first().copied().ok_or_else(|| "no scorecard periods configured".to_string()) };
+    let days: u32 = s.trim().trim_end_matches('d').parse().map_err(|_| format!("invalid period '{}'", s))?;
+    if configured.contains(&days) {
+        Ok(days)
+    } else {
+        Err(format!("period {}d is not scored; available: {:?}", days, configured))
+    }
+}
+
+// GET /api/traders/scorecards[?period=30d][&authors=a,b][&min_calls=3][&sort=hit_rate][&limit=100]
+pub async fn get_scorecards(Query(q): Query<ScorecardsQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let scorecards = state.scorecards.read().await;
+    let period = match parse_period(q.period.as_deref(), &scorecards) {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": e}))),
+    };
+    let authors: Option<Vec<String>> = q.authors.as_ref()
+        .map(|a| a.split(',').map(|s| s.trim().trim_start_matches('@').to_lowercase()).filter(|s| !s.is_empty()).collect());
+    let mut cards: Vec<&Scorecard> = scorecards.cards.values()
+        .filter(|c| c.period_days == period)
+        .filter(|c| c.calls >= q.min_calls.unwrap_or(1))
+        .filter(|c| authors.as_ref().is_none_or(|a| a.contains(&c.author.to_lowercase())))
+        .collect();
+    let key = |c: &Scorecard| -> f64 {
+        match q.sort.as_deref().unwrap_or("hit_rate") {
+            "avg_max_gain" | "avg_max_gain_pct" => c.avg_max_gain_pct.unwrap_or(f64::MIN),
+            "calls" => c.calls as f64,
+            "avg_close_return" | "avg_close_return_pct" => c.avg_close_return_pct.unwrap_or(f64::MIN),
+            _ => c.hit_rate.unwrap_or(f64::MIN),
+        }
+    };
+    cards.sort_by(|a, b| key(b).total_cmp(&key(a)).then(b.calls.cmp(&a.calls)));
+    cards.truncate(q.limit.unwrap_or(100));
+    (StatusCode::OK, Json(serde_json::json!({
+        "period_days": period,
+        "periods_days": scorecards.config.periods_days,
+        "hit_threshold_pct": scorecards.config.hit_pct,
+        "computed_at_ns": scorecards.computed_at_ns,
+        "scorecards": cards
+    })))
+}
+
+// GET /api/traders/:author/scorecard - one author across every period
+pub async fn get_scorecard(Path(author): Path<String>, State(state): State<AppState>) -> impl IntoResponse {
+    let author = author.trim_start_matches('@').to_lowercase();
+    let scorecards = state.scorecards.read().await;
+    let cards: Vec<&Scorecard> = scorecards.cards.values().filter(|c| c.author.to_lowercase() == author).collect();
+    if cards.is_empty() {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "no scored calls for author", "author": author})));
+    }
+    (StatusCode::OK, Json(serde_json::json!({
+        "author": cards[0].author,
+        "hit_threshold_pct": scorecards.config.hit_pct,
+        "computed_at_ns": scorecards.computed_at_ns,
+        "periods": cards
+    })))
+}
+
+// POST /api/traders/scorecards/recompute
+pub async fn post_recompute(State(state): State<AppState>) -> impl IntoResponse {
+    recompute(&state).await;
+    let scorecards = state.scorecards.read().await;
+    (StatusCode::OK, Json(serde_json::json!({
+        "computed_at_ns": scorecards.computed_at_ns,
+        "scorecards": scorecards.cards.len()
+    })))
+}
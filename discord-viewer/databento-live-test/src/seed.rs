@@ -0,0 +1,57 @@
+// Startup seeding: re-subscribe everything mentioned today so a mid-day restart
+// restores coverage without waiting for the UI or new detections.
+
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::{calendar, resolve, start_live_subscription, AppState};
+
+const TODAY_TICKERS: &str = "
+SELECT td.ticker_symbol
+FROM ticker_detections td
+JOIN messages m ON m.id = td.message_id
+WHERE m.discord_timestamp >= $1
+  AND NOT EXISTS (SELECT 1 FROM ticker_blacklist b WHERE UPPER(b.ticker) = UPPER(td.ticker_symbol))
+GROUP BY td.ticker_symbol
+ORDER BY MIN(m.discord_timestamp)
+";
+
+// Load today's detected tickers and subscribe them in one batch. Disabled with SEED_ON_START=false.
+pub async fn seed_from_db(state: AppState) {
+    if std::env::var("SEED_ON_START").is_ok_and(|v| v == "false" || v == "0") {
+        info!("Startup seeding disabled");
+        return;
+    }
+    if std::env::var("DATABENTO_API_KEY").map(|v| v.is_empty()).unwrap_or(true) {
+        return;
+    }
+    let Some(db) = &state.db else {
+        info!("No database; skipping startup seeding");
+        return;
+    };
+
+    let since = calendar::chicago_day_start(Utc::now());
+    let tickers: Vec<String> = match db.query(TODAY_TICKERS, &[&since]).await {
+        Ok(rows) => rows.iter().map(|r| r.get::<_, String>(0)).collect(),
+        Err(e) => {
+            error!("Failed to load today's tickers: {}", e);
+            return;
+        }
+    };
+    if tickers.is_empty() {
+        info!("No tickers detected since {}; nothing to seed", since);
+        return;
+    }
+
+    let resolution = resolve::resolve_symbols(&state, &tickers).await;
+    if !resolution.rejected.is_empty() {
+        info!("Startup seeding skipped {} symbols: {:?}", resolution.rejected.len(), resolution.rejected);
+    }
+    if resolution.accepted.is_empty() {
+        return;
+    }
+    match start_live_subscription(resolution.accepted, state.clone()).await {
+        Ok(symbols) => info!("Seeded {} symbols detected since {}: {:?}", symbols.len(), since, symbols),
+        Err(e) => error!("Startup seeding failed: {}", e),
+    }
+}
@@ -0,0 +1,541 @@
+// Live session management: one Databento LiveClient per dataset, with routing
+// rules deciding which dataset serves a symbol and a fallback chain used when
+// the gateway rejects a symbol in its primary dataset.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use databento::{live::Subscription, LiveClient};
+use databento::dbn::{BboMsg, CbboMsg, ErrorMsg, SType, Schema, StatusMsg, SymbolMappingMsg, SystemMsg, TradeMsg};
+
+use crate::{apply_trade, gaps, options, rules, stream::StreamEvent, AppState};
+
+// Messages consumed by the session router
+#[derive(Debug)]
+pub enum SessionCommand {
+    Subscribe(Vec<String>),
+    SubscribeOptions(Vec<String>), // "AAPL.OPT" parents or OCC contract symbols
+    Rejected { dataset: String, symbols: Vec<String>, reason: String },
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SessionInfo {
+    pub dataset: String,
+    pub status: String,
+    pub symbols: Vec<String>,
+    pub rejected: Vec<String>,
+    pub last_error: Option<String>,
+    pub connected_at_ns: Option<u64>,
+}
+
+// Routing rules loaded from env:
+//   DATABENTO_DATASET            primary dataset (default EQUS.MINI)
+//   DATABENTO_ROUTES             "XNAS.ITCH=AAPL,NVDA;XNYS.PILLAR=F,T" explicit symbol -> dataset
+//   DATABENTO_FALLBACK_DATASETS  "XNAS.ITCH,XNYS.PILLAR" tried in order after a rejection
+//   DATABENTO_OPRA_DATASET       options dataset (default OPRA.PILLAR), never part of the fallback chain
+//   DATABENTO_EQUITY_SCHEMAS     schemas for equity symbols (default "trades"); add bbo-1s and status
+//                                to feed the quote and halt rule fields, paid for on every symbol
+#[derive(Clone, Debug)]
+pub struct RoutingRules {
+    pub primary: String,
+    pub routes: HashMap<String, String>,
+    pub fallbacks: Vec<String>,
+    pub options: String,
+}
+
+impl RoutingRules {
+    pub fn from_env() -> Self {
+        let primary = std::env::var("DATABENTO_DATASET").unwrap_or_else(|_| "EQUS.MINI".to_string());
+        let routes = std::env::var("DATABENTO_ROUTES")
+            .map(|v| parse_routes(&v))
+            .unwrap_or_default();
+        let fallbacks = std::env::var("DATABENTO_FALLBACK_DATASETS")
+            .map(|v| parse_list(&v))
+            .unwrap_or_default();
+        RoutingRules { primary, routes, fallbacks, options: options::options_dataset() }
+    }
+
+    // Dataset that should serve a symbol before any rejection
+    pub fn route(&self, symbol: &str) -> String {
+        self.routes.get(symbol).cloned().unwrap_or_else(|| self.primary.clone())
+    }
+
+    // Next dataset to try for a symbol, skipping datasets that already rejected it
+    pub fn next_fallback(&self, tried: &[String]) -> Option<String> {
+        std::iter::once(&self.primary)
+            .chain(self.fallbacks.iter())
+            .find(|ds| !tried.contains(ds))
+            .cloned()
+    }
+}
+
+// Dataset a symbol's data comes from: where the router put it, or where it would go
+pub async fn dataset_for(state: &AppState, symbol: &str) -> String {
+    match state.symbol_routes.read().await.get(symbol) {
+        Some(dataset) => dataset.clone(),
+        None => RoutingRules::from_env().route(symbol),
+    }
+}
+
+fn parse_list(v: &str) -> Vec<String> {
+    v.split(',').map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty()).collect()
+}
+
+fn parse_routes(v: &str) -> HashMap<String, String> {
+    let mut routes = HashMap::new();
+    for rule in v.split(';') {
+        let Some((dataset, symbols)) = rule.split_once('=') else { continue };
+        let dataset = dataset.trim().to_uppercase();
+        for sym in parse_list(symbols) {
+            routes.insert(sym, dataset.clone());
+        }
+    }
+    routes
+}
+
+// Routes subscription requests to per-dataset sessions, spawning sessions lazily
+// and moving rejected symbols along the fallback chain.
+pub async fn session_router(state: AppState, mut rx: mpsc::UnboundedReceiver<SessionCommand>) {
+    let api_key = match std::env::var("DATABENTO_API_KEY") {
+        Ok(key) => key,
+        Err(_) => {
+            error!("DATABENTO_API_KEY not set for session router");
+            return;
+        }
+    };
+
+    let rules = RoutingRules::from_env();
+    info!("Session router started: primary={}, routes={:?}, fallbacks={:?}", rules.primary, rules.routes, rules.fallbacks);
+
+    let mut sessions: HashMap<String, mpsc::UnboundedSender<Vec<String>>> = HashMap::new();
+    // symbol -> datasets that rejected it
+    let mut tried: HashMap<String, Vec<String>> = HashMap::new();
+
+    while let Some(cmd) = rx.recv().await {
+        let mut by_dataset: HashMap<String, Vec<String>> = HashMap::new();
+        match cmd {
+            SessionCommand::Subscribe(symbols) => {
+                let routes = state.symbol_routes.read().await;
+                for sym in symbols {
+                    // Symbols already served somewhere stay where they are
+                    let dataset = routes.get(&sym).cloned().unwrap_or_else(|| rules.route(&sym));
+                    by_dataset.entry(dataset).or_default().push(sym);
+                }
+            }
+            SessionCommand::SubscribeOptions(symbols) => {
+                by_dataset.insert(rules.options.clone(), symbols);
+            }
+            SessionCommand::Rejected { dataset, symbols, reason } if dataset == rules.options => {
+                // Option contracts have nowhere else to go
+                warn!("Options dataset {} rejected {:?}: {}", dataset, symbols, reason);
+                let mut book = state.options.write().await;
+                for sym in &symbols {
+                    book.subscriptions.remove(sym);
+                }
+            }
+            SessionCommand::Rejected { dataset, symbols, reason } => {
+                warn!("Dataset {} rejected {:?}: {}", dataset, symbols, reason);
+                // symbol_routes is released before subscribed_symbols is taken; subscribe
+                // takes them the other way round
+                let mut unserved = Vec::new();
+                let mut routes = state.symbol_routes.write().await;
+                for sym in symbols {
+                    let tried_for = tried.entry(sym.clone()).or_default();
+                    if !tried_for.contains(&dataset) {
+                        tried_for.push(dataset.clone());
+                    }
+                    routes.remove(&sym);
+                    match rules.next_fallback(tried_for) {
+                        Some(next) => {
+                            info!("Falling back {} from {} to {}", sym, dataset, next);
+                            by_dataset.entry(next).or_default().push(sym);
+                        }
+                        None => {
+                            warn!("No dataset left to serve {}", sym);
+                            unserved.push(sym);
+                        }
+                    }
+                }
+                drop(routes);
+                if !unserved.is_empty() {
+                    let mut subscribed = state.subscribed_symbols.write().await;
+                    for sym in &unserved {
+                        subscribed.remove(sym);
+                    }
+                }
+            }
+        }
+
+        for (dataset, symbols) in by_dataset {
+            if dataset != rules.options {
+                let mut routes = state.symbol_routes.write().await;
+                for sym in &symbols {
+                    routes.insert(sym.clone(), dataset.clone());
+                }
+            }
+            let sender = sessions.entry(dataset.clone()).or_insert_with(|| {
+                let (tx, session_rx) = mpsc::unbounded_channel();
+                tokio::spawn(run_dataset_session(dataset.clone(), api_key.clone(), state.clone(), session_rx));
+                tx
+            });
+            if let Err(e) = sender.send(symbols) {
+                error!("Session for {} is gone: {}", dataset, e);
+                sessions.remove(&dataset);
+            }
+        }
+    }
+
+    info!("Session router stopped");
+}
+
+// Update a session's info, publishing a dataset-level status event when its status changes
+async fn set_session_info(state: &AppState, dataset: &str, update: impl FnOnce(&mut SessionInfo)) {
+    let mut sessions = state.sessions.write().await;
+    let info = sessions.entry(dataset.to_string()).or_insert_with(|| SessionInfo {
+        dataset: dataset.to_string(),
+        ..Default::default()
+    });
+    let previous = info.status.clone();
+    update(info);
+    if info.status != previous {
+        state.events.publish(StreamEvent::Status {
+            symbol: None,
+            dataset: dataset.to_string(),
+            status: info.status.clone(),
+            detail: info.last_error.clone(),
+        });
+    }
+}
+
+fn publish_symbol_status(state: &AppState, dataset: &str, symbol: &str, status: &str, detail: Option<String>) {
+    state.events.publish(StreamEvent::Status {
+        symbol: Some(symbol.to_string()),
+        dataset: dataset.to_string(),
+        status: status.to_string(),
+        detail,
+    });
+}
+
+pub fn equity_schemas() -> Vec<Schema> {
+    let schemas: Vec<Schema> = std::env::var("DATABENTO_EQUITY_SCHEMAS")
+        .unwrap_or_else(|_| "trades".to_string())
+        .split(',')
+        .filter_map(|s| s.trim().to_lowercase().parse().ok())
+        .collect();
+    if schemas.contains(&Schema::Trades) {
+        schemas
+    } else {
+        std::iter::once(Schema::Trades).chain(schemas).collect()
+    }
+}
+
+// Options sessions also take consolidated quotes for bid/ask; parent symbols
+// ("AAPL.OPT") use parent symbology, everything else is a raw symbol.
+async fn subscribe_symbols(client: &mut LiveClient, symbols: &[String], options: bool) -> databento::Result<()> {
+    let schemas = if options { vec![Schema::Trades, Schema::Cbbo1S] } else { equity_schemas() };
+    let (parents, raw): (Vec<String>, Vec<String>) = symbols.iter().cloned().partition(|s| options::is_parent_symbol(s));
+    for (stype, group) in [(SType::Parent, parents), (SType::RawSymbol, raw)] {
+        if group.is_empty() {
+            continue;
+        }
+        for schema in &schemas {
+            let subscription = Subscription::builder()
+                .schema(*schema)
+                .stype_in(stype)
+                .symbols(group.clone())
+                .build();
+            client.subscribe(&subscription).await?;
+        }
+    }
+    Ok(())
+}
+
+// Symbols from `pending` that are named in a gateway error/system message
+fn symbols_in_message(text: &str, pending: &HashSet<String>) -> Vec<String> {
+    let lower = text.to_lowercase();
+    if !(lower.contains("symbol") || lower.contains("resolve")) {
+        return Vec::new();
+    }
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'))
+        .map(|tok| tok.trim_matches('.').to_uppercase())
+        .filter(|tok| pending.contains(tok))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+// Where the data missed in an outage starts: just after the last live trade, or at
+// the last record's receipt when no trade has come in on this session
+fn outage_start(last_trade_ns: Option<u64>, last_record_ns: Option<u64>) -> u64 {
+    match last_trade_ns {
+        Some(ts) => ts + 1,
+        None => last_record_ns.unwrap_or_else(crate::current_time_ns),
+    }
+}
+
+// A single dataset's live session. Reconnects on stream errors and resubscribes
+// everything it was serving.
+async fn run_dataset_session(
+    dataset: String,
+    api_key: String,
+    state: AppState,
+    mut symbol_rx: mpsc::UnboundedReceiver<Vec<String>>,
+) {
+    let is_options = dataset == options::options_dataset();
+    info!("Starting Databento session for dataset: {} (options={})", dataset, is_options);
+
+    // Symbols this session serves, and those still waiting for a symbol mapping
+    let mut symbols: Vec<String> = Vec::new();
+    let mut pending: HashSet<String> = HashSet::new();
+
+    // Event time of the last live trade, wall-clock time of the last record received,
+    // and the start of the current outage
+    let mut last_trade_ns: Option<u64> = None;
+    let mut last_record_ns: Option<u64> = None;
+    let mut outage_start_ns: Option<u64> = None;
+
+    'connect: loop {
+        set_session_info(&state, &dataset, |s| s.status = "connecting".to_string()).await;
+
+        let mut client = match LiveClient::builder().key(&api_key) {
+            Ok(builder) => match builder.dataset(&dataset).build().await {
+                Ok(c) => c,
+                Err(e) => {
+                    error!("Failed to create Databento client for {}: {}", dataset, e);
+                    set_session_info(&state, &dataset, |s| {
+                        s.status = "disconnected".to_string();
+                        s.last_error = Some(e.to_string());
+                    }).await;
+                    tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                    continue 'connect;
+                }
+            },
+            Err(e) => {
+                error!("Invalid Databento API key: {}", e);
+                return;
+            }
+        };
+
+        // Symbol mappings are per connection
+        let mut mapping: HashMap<u32, String> = HashMap::new();
+        let mut client_started = false;
+
+        if !symbols.is_empty() {
+            info!("Resubscribing {} symbols on {}", symbols.len(), dataset);
+            pending.extend(symbols.iter().cloned());
+            if let Err(e) = subscribe_symbols(&mut client, &symbols, is_options).await {
+                error!("Failed to resubscribe on {}: {}", dataset, e);
+                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                continue 'connect;
+            }
+            match client.start().await {
+                Ok(metadata) => {
+                    client_started = true;
+                    if let Some(recorder) = &state.recorder {
+                        recorder.start(&dataset, &metadata);
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to start Databento client for {}: {}", dataset, e);
+                    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                    continue 'connect;
+                }
+            }
+
+            // Live data resumes now; pull what was missed from the historical API
+            if let Some(start_ns) = outage_start_ns.take() {
+                if !is_options {
+                    tokio::spawn(gaps::recover(state.clone(), dataset.clone(), start_ns, crate::current_time_ns(), symbols.clone()));
+                }
+            }
+        }
+
+        set_session_info(&state, &dataset, |s| {
+            s.status = if client_started { "streaming" } else { "idle" }.to_string();
+            s.symbols = symbols.clone();
+            s.connected_at_ns = Some(crate::current_time_ns());
+        }).await;
+
+        loop {
+            tokio::select! {
+                symbols_opt = symbol_rx.recv() => {
+                    let Some(requested) = symbols_opt else {
+                        warn!("Symbol channel closed for {}", dataset);
+                        break 'connect;
+                    };
+
+                    let new_symbols: Vec<String> = requested.into_iter()
+                        .filter(|s| !symbols.contains(s))
+                        .collect();
+                    if new_symbols.is_empty() {
+                        continue;
+                    }
+
+                    info!("Subscribing to new symbols on {}: {:?}", dataset, new_symbols);
+                    match subscribe_symbols(&mut client, &new_symbols, is_options).await {
+                        Ok(_) => {
+                            for sym in &new_symbols {
+                                publish_symbol_status(&state, &dataset, sym, "subscribed", None);
+                            }
+                            symbols.extend(new_symbols.iter().cloned());
+                            pending.extend(new_symbols.iter().cloned());
+                            if !client_started {
+                                match client.start().await {
+                                    Ok(metadata) => {
+                                        info!("Databento client started for {}", dataset);
+                                        client_started = true;
+                                        if let Some(recorder) = &state.recorder {
+                                            recorder.start(&dataset, &metadata);
+                                        }
+                                    }
+                                    Err(e) => {
+                                        error!("Failed to start Databento client for {}: {}", dataset, e);
+                                        continue 'connect;
+                                    }
+                                }
+                            }
+                            set_session_info(&state, &dataset, |s| {
+                                s.status = "streaming".to_string();
+                                s.symbols = symbols.clone();
+                            }).await;
+                        }
+                        Err(e) => {
+                            // A failed write is the connection, not the symbols: keep them and
+                            // resubscribe on a fresh one. Gateway rejections arrive as
+                            // ErrorMsg/SystemMsg records and go through reject_pending.
+                            error!("Failed to subscribe to symbols {:?} on {}: {}", new_symbols, dataset, e);
+                            symbols.extend(new_symbols);
+                            if client_started {
+                                if let Some(recorder) = &state.recorder {
+                                    recorder.stop(&dataset, format!("subscribe failed: {}", e));
+                                }
+                                outage_start_ns.get_or_insert(outage_start(last_trade_ns, last_record_ns));
+                            }
+                            set_session_info(&state, &dataset, |s| {
+                                s.status = "disconnected".to_string();
+                                s.last_error = Some(e.to_string());
+                            }).await;
+                            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                            continue 'connect;
+                        }
+                    }
+                }
+
+                rec_result = client.next_record(), if client_started => {
+                    match rec_result {
+                        Ok(Some(rec)) => {
+                            last_record_ns = Some(crate::current_time_ns());
+                            if let Some(msg) = rec.get::<SymbolMappingMsg>() {
+                                let raw_symbol = msg.stype_out_symbol().unwrap_or_default().to_string();
+                                let in_symbol = msg.stype_in_symbol().unwrap_or_default().to_uppercase();
+                                info!("Symbol mapping on {}: instrument_id={} -> symbol={}", dataset, msg.hd.instrument_id, raw_symbol);
+                                if pending.remove(&in_symbol) {
+                                    publish_symbol_status(&state, &dataset, &in_symbol, "live", None);
+                                }
+                                mapping.insert(msg.hd.instrument_id, raw_symbol);
+                            } else if let Some(trade) = rec.get::<TradeMsg>() {
+                                last_trade_ns = last_trade_ns.max(Some(trade.hd.ts_event));
+                                let inst = trade.hd.instrument_id;
+                                let symbol = mapping.get(&inst).cloned().unwrap_or_else(|| format!("INST:{}", inst));
+                                if is_options {
+                                    options::apply_option_trade(&state, &symbol, trade).await;
+                                } else {
+                                    apply_trade(&state, &symbol, trade).await;
+                                }
+                            } else if let Some(quote) = rec.get::<CbboMsg>() {
+                                if let Some(symbol) = mapping.get(&quote.hd.instrument_id) {
+                                    options::apply_option_quote(&state, symbol, quote).await;
+                                }
+                            } else if let Some(quote) = rec.get::<BboMsg>() {
+                                if let Some(symbol) = mapping.get(&quote.hd.instrument_id) {
+                                    rules::apply_quote(&state, symbol, quote).await;
+                                }
+                            } else if let Some(status) = rec.get::<StatusMsg>() {
+                                if let Some(symbol) = mapping.get(&status.hd.instrument_id) {
+                                    rules::apply_status(&state, &dataset, symbol, status).await;
+                                }
+                            } else if let Some(msg) = rec.get::<ErrorMsg>() {
+                                let text = msg.err().unwrap_or_default().to_string();
+                                error!("Gateway error on {}: {}", dataset, text);
+                                reject_pending(&state, &dataset, &text, &mut symbols, &mut pending).await;
+                            } else if let Some(msg) = rec.get::<SystemMsg>() {
+                                let text = msg.msg().unwrap_or_default().to_string();
+                                if !msg.is_heartbeat() {
+                                    info!("Gateway message on {}: {}", dataset, text);
+                                    reject_pending(&state, &dataset, &text, &mut symbols, &mut pending).await;
+                                }
+                            }
+                            if let Some(recorder) = &state.recorder {
+                                recorder.record(&dataset, &rec, &mapping);
+                            }
+                        }
+                        Ok(None) => {
+                            info!("Databento stream ended for {}", dataset);
+                            if let Some(recorder) = &state.recorder {
+                                recorder.stop(&dataset, "stream ended");
+                            }
+                            outage_start_ns.get_or_insert(outage_start(last_trade_ns, last_record_ns));
+                            set_session_info(&state, &dataset, |s| s.status = "disconnected".to_string()).await;
+                            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                            continue 'connect;
+                        }
+                        Err(e) => {
+                            error!("Databento client error on {}: {}", dataset, e);
+                            if let Some(recorder) = &state.recorder {
+                                recorder.stop(&dataset, format!("client error: {}", e));
+                            }
+                            outage_start_ns.get_or_insert(outage_start(last_trade_ns, last_record_ns));
+                            set_session_info(&state, &dataset, |s| {
+                                s.status = "disconnected".to_string();
+                                s.last_error = Some(e.to_string());
+                            }).await;
+                            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                            continue 'connect;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(recorder) = &state.recorder {
+        recorder.stop(&dataset, "stopped");
+    }
+    set_session_info(&state, &dataset, |s| s.status = "stopped".to_string()).await;
+    info!("Databento session for {} stopped", dataset);
+}
+
+// Drop symbols named in a rejection from this session and hand them back to the router
+async fn reject_pending(
+    state: &AppState,
+    dataset: &str,
+    text: &str,
+    symbols: &mut Vec<String>,
+    pending: &mut HashSet<String>,
+) {
+    let rejected = symbols_in_message(text, pending);
+    if rejected.is_empty() {
+        return;
+    }
+    symbols.retain(|s| !rejected.contains(s));
+    for sym in &rejected {
+        pending.remove(sym);
+        publish_symbol_status(state, dataset, sym, "rejected", Some(text.to_string()));
+    }
+    set_session_info(state, dataset, |s| {
+        s.symbols = symbols.clone();
+        for sym in &rejected {
+            if !s.rejected.contains(sym) {
+                s.rejected.push(sym.clone());
+            }
+        }
+    }).await;
+    let _ = state.session_sender.send(SessionCommand::Rejected {
+        dataset: dataset.to_string(),
+        symbols: rejected,
+        reason: text.to_string(),
+    });
+}
@@ -0,0 +1,438 @@
+// Internal event fan-out. Everything downstream of the live sessions (the Node
+// WebSocket broadcaster, SSE clients) subscribes here. Events are sequenced
+// globally and per symbol; recent events are kept in a ring buffer so clients can
+// fetch a snapshot at a sequence, replay deltas from it, or resume SSE with
+// Last-Event-ID. Individual prints go to a ring of their own, so a burst of them
+// can't push state changes out of the replay window.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+    response::IntoResponse,
+    Json,
+};
+use http::StatusCode;
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::{alerts::LevelAlert, bars::Bar, calendar::Session, norm_symbol, rules::RuleHit, scanner::ScannerHit, tape::TapeTrade, AppState, PriceUpdate};
+
+// Ring buffer sizes: every event but prints (replay and snapshots-at-sequence),
+// and prints (replay of the opt-in trade channel)
+const HISTORY_LEN: usize = 10_000;
+const TRADE_HISTORY_LEN: usize = 10_000;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Price(PriceUpdate),
+    Status {
+        symbol: Option<String>,
+        dataset: String,
+        status: String,
+        detail: Option<String>,
+    },
+    BarClose(Bar),
+    Trade(TapeTrade),
+    Scanner(ScannerHit),
+    Alert(LevelAlert),
+    Rule(RuleHit),
+}
+
+impl StreamEvent {
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            StreamEvent::Price(p) => Some(&p.symbol),
+            StreamEvent::Status { symbol, .. } => symbol.as_deref(),
+            StreamEvent::BarClose(b) => Some(&b.symbol),
+            StreamEvent::Trade(t) => Some(&t.symbol),
+            StreamEvent::Scanner(h) => Some(&h.symbol),
+            StreamEvent::Alert(a) => Some(&a.symbol),
+            StreamEvent::Rule(r) => Some(&r.symbol),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamEvent::Price(_) => "price",
+            StreamEvent::Status { .. } => "status",
+            StreamEvent::BarClose(_) => "bar",
+            StreamEvent::Trade(_) => "trade",
+            StreamEvent::Scanner(_) => "scanner",
+            StreamEvent::Alert(_) => "alert",
+            StreamEvent::Rule(_) => "rule",
+        }
+    }
+}
+
+// Streaming protocol version, carried on every envelope
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Every event gets a global sequence (also the SSE id) and, when it concerns a
+// symbol, that symbol's own sequence. Both start at 1 and never repeat. Prints
+// are opt-in, so they take no symbol sequence: a gap in it is always a lost event.
+#[derive(Clone, Debug, Serialize)]
+pub struct Envelope {
+    pub v: u32,
+    pub seq: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol_seq: Option<u64>,
+    #[serde(flatten)]
+    pub event: StreamEvent,
+}
+
+// Per-symbol state as seen by the stream protocol
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SymbolSnapshot {
+    pub symbol_seq: u64,
+    pub price: Option<f64>,
+    pub ts_event_ns: Option<u64>,
+    pub session: Option<Session>,
+    pub live: bool,
+    pub status: Option<String>,
+    pub last_bar: Option<Bar>,
+}
+
+impl SymbolSnapshot {
+    fn apply(&mut self, envelope: &Envelope) {
+        if let Some(seq) = envelope.symbol_seq {
+            self.symbol_seq = seq;
+        }
+        match &envelope.event {
+            StreamEvent::Price(p) => {
+                self.price = Some(p.price);
+                self.ts_event_ns = Some(p.timestamp);
+                self.session = Some(p.session);
+                self.live = p.live;
+            }
+            StreamEvent::Status { status, .. } => self.status = Some(status.clone()),
+            StreamEvent::BarClose(bar) => self.last_bar = Some(bar.clone()),
+            // Prints, scanner and alert hits are notifications; they don't change symbol state
+            StreamEvent::Trade(_) | StreamEvent::Scanner(_) | StreamEvent::Alert(_) | StreamEvent::Rule(_) => {}
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Snapshot {
+    pub v: u32,
+    pub seq: u64,
+    pub symbols: HashMap<String, SymbolSnapshot>,
+}
+
+struct HubInner {
+    next_seq: u64,
+    symbol_seqs: HashMap<String, u64>,
+    history: VecDeque<Arc<Envelope>>,
+    // State as of `base_seq`, the last event evicted from history. Any snapshot
+    // between base_seq and the head is base + replay of history.
+    base: HashMap<String, SymbolSnapshot>,
+    base_seq: u64,
+    trades: VecDeque<Arc<Envelope>>,
+    trade_base_seq: u64, // last print evicted from `trades`
+}
+
+impl HubInner {
+    // Events after `from_seq` in sequence order, prints only if `trades`; None if
+    // any of them has already been evicted
+    fn since(&self, from_seq: u64, trades: bool) -> Option<Vec<Arc<Envelope>>> {
+        if from_seq < self.base_seq || (trades && from_seq < self.trade_base_seq) {
+            return None;
+        }
+        let mut events: Vec<Arc<Envelope>> = self.history.iter().filter(|e| e.seq > from_seq).cloned().collect();
+        if trades {
+            events.extend(self.trades.iter().filter(|e| e.seq > from_seq).cloned());
+            events.sort_by_key(|e| e.seq);
+        }
+        Some(events)
+    }
+}
+
+pub struct StreamHub {
+    tx: broadcast::Sender<Arc<Envelope>>,
+    inner: Mutex<HubInner>,
+}
+
+impl StreamHub {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(4096);
+        StreamHub {
+            tx,
+            inner: Mutex::new(HubInner {
+                next_seq: 1,
+                symbol_seqs: HashMap::new(),
+                history: VecDeque::with_capacity(HISTORY_LEN),
+                base: HashMap::new(),
+                base_seq: 0,
+                trades: VecDeque::with_capacity(TRADE_HISTORY_LEN),
+                trade_base_seq: 0,
+            }),
+        }
+    }
+
+    pub fn publish(&self, event: StreamEvent) {
+        // Sequence assignment, history and broadcast under one lock so every
+        // receiver sees events in sequence order
+        let mut inner = self.inner.lock().unwrap();
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+        let is_trade = matches!(event, StreamEvent::Trade(_));
+        let symbol_seq = event.symbol().filter(|_| !is_trade).map(|sym| {
+            let n = inner.symbol_seqs.entry(sym.to_string()).or_insert(0);
+            *n += 1;
+            *n
+        });
+        let envelope = Arc::new(Envelope { v: PROTOCOL_VERSION, seq, symbol_seq, event });
+        if is_trade {
+            inner.trades.push_back(envelope.clone());
+            if inner.trades.len() > TRADE_HISTORY_LEN {
+                if let Some(evicted) = inner.trades.pop_front() {
+                    inner.trade_base_seq = evicted.seq;
+                }
+            }
+        } else {
+            inner.history.push_back(envelope.clone());
+        }
+        if inner.history.len() > HISTORY_LEN {
+            if let Some(evicted) = inner.history.pop_front() {
+                if let Some(sym) = evicted.event.symbol() {
+                    inner.base.entry(sym.to_string()).or_default().apply(&evicted);
+                }
+                inner.base_seq = evicted.seq;
+            }
+        }
+        // No receivers is fine; nobody is listening yet
+        let _ = self.tx.send(envelope);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Envelope>> {
+        self.tx.subscribe()
+    }
+
+    pub fn head_seq(&self) -> u64 {
+        self.inner.lock().unwrap().next_seq - 1
+    }
+
+    // Events after `from_seq` (prints only if `trades`), or None if `from_seq` has
+    // already fallen out of history
+    pub fn replay(&self, from_seq: u64, trades: bool) -> Option<Vec<Arc<Envelope>>> {
+        self.inner.lock().unwrap().since(from_seq, trades)
+    }
+
+    // Subscribe and grab everything after `from_seq` atomically, so nothing is
+    // missed or duplicated between the replay and the live receiver.
+    pub fn resume(&self, from_seq: u64, trades: bool) -> (Option<Vec<Arc<Envelope>>>, broadcast::Receiver<Arc<Envelope>>) {
+        let inner = self.inner.lock().unwrap();
+        let rx = self.tx.subscribe();
+        (inner.since(from_seq, trades), rx)
+    }
+
+    // State as of `at_seq` (default: head). None if `at_seq` is older than history.
+    pub fn snapshot(&self, at_seq: Option<u64>, symbols: Option<&[String]>) -> Option<Snapshot> {
+        let inner = self.inner.lock().unwrap();
+        let head = inner.next_seq - 1;
+        let at = at_seq.unwrap_or(head).min(head);
+        if at < inner.base_seq {
+            return None;
+        }
+        let wanted = |sym: &str| symbols.is_none_or(|list| list.iter().any(|s| s == sym));
+        let mut state: HashMap<String, SymbolSnapshot> = inner.base.iter()
+            .filter(|(sym, _)| wanted(sym))
+            .map(|(sym, snap)| (sym.clone(), snap.clone()))
+            .collect();
+        for envelope in inner.history.iter().take_while(|e| e.seq <= at) {
+            if let Some(sym) = envelope.event.symbol().filter(|sym| wanted(sym)) {
+                state.entry(sym.to_string()).or_default().apply(envelope);
+            }
+        }
+        Some(Snapshot { v: PROTOCOL_VERSION, seq: at, symbols: state })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SseQuery {
+    #[serde(default)]
+    symbols: Option<String>,
+    // Event types to include; defaults to price,status,bar,scanner,alert,rule (trade is opt-in)
+    #[serde(default)]
+    channels: Option<String>,
+}
+
+fn to_sse(envelope: &Envelope) -> Event {
+    Event::default()
+        .id(envelope.seq.to_string())
+        .event(envelope.event.name())
+        .data(serde_json::to_string(envelope).unwrap_or_default())
+}
+
+// GET /api/live/stream?symbols=AAPL,TSLA[&channels=price,status,bar,scanner,alert,rule,trade]
+// One-way event stream of price, status, bar-close and scanner events, plus individual
+// prints when the trade channel is requested. Omitting `symbols` streams
+// everything. Dataset-level status events are always included.
+pub async fn sse_stream(
+    Query(q): Query<SseQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let filter = parse_symbols(q.symbols);
+    let channels: Vec<String> = q.channels.as_deref().unwrap_or("price,status,bar,scanner,alert,rule")
+        .split(',').map(|c| c.trim().to_lowercase()).collect();
+    let trades = channels.iter().any(|c| c == "trade");
+    let wants = move |e: &Envelope| channels.iter().any(|c| c == e.event.name())
+        && match (&filter, e.event.symbol()) {
+            (Some(symbols), Some(sym)) => symbols.iter().any(|s| s == sym),
+            _ => true,
+        };
+
+    let last_seq = headers.get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+
+    let (replay, rx) = match last_seq {
+        Some(seq) => state.events.resume(seq, trades),
+        None => (Some(Vec::new()), state.events.subscribe()),
+    };
+
+    // If the client is too far behind to replay, tell it to refetch state
+    let head: Vec<Result<Event, Infallible>> = match replay {
+        Some(events) => events.iter().filter(|e| wants(e)).map(|e| Ok(to_sse(e))).collect(),
+        None => vec![Ok(Event::default().event("resync").data("{\"reason\":\"history_expired\"}"))],
+    };
+
+    let live = stream::unfold((rx, wants), |(mut rx, wants)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(e) if wants(&e) => return Some((Ok(to_sse(&e)), (rx, wants))),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("SSE client lagged by {} events", n);
+                    let resync = Event::default().event("resync").data(format!("{{\"reason\":\"lagged\",\"skipped\":{}}}", n));
+                    return Some((Ok(resync), (rx, wants)));
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    let events: std::pin::Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>> =
+        Box::pin(stream::iter(head).chain(live));
+    Sse::new(events).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)).text("keep-alive"))
+}
+
+fn parse_symbols(s: Option<String>) -> Option<Vec<String>> {
+    s.map(|s| s.split(',').map(norm_symbol).filter(|s| !s.is_empty()).collect())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SnapshotQuery {
+    #[serde(default)]
+    symbols: Option<String>,
+    #[serde(default)]
+    at_seq: Option<u64>,
+}
+
+// GET /api/live/snapshot?symbols=AAPL,TSLA[&at_seq=N]
+// Consistent per-symbol state as of a global sequence (default: latest). Apply
+// deltas from /api/live/replay?from_seq=<seq> or SSE with Last-Event-ID: <seq>.
+pub async fn get_snapshot(Query(q): Query<SnapshotQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let symbols = parse_symbols(q.symbols);
+    match state.events.snapshot(q.at_seq, symbols.as_deref()) {
+        Some(snapshot) => (StatusCode::OK, Json(serde_json::to_value(snapshot).unwrap_or_default())),
+        None => (StatusCode::GONE, Json(serde_json::json!({
+            "error": "sequence no longer in history",
+            "v": PROTOCOL_VERSION,
+            "head_seq": state.events.head_seq()
+        }))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayQuery {
+    from_seq: u64,
+    #[serde(default)]
+    symbols: Option<String>,
+    #[serde(default)]
+    trades: bool,
+}
+
+// GET /api/live/replay?from_seq=N[&symbols=AAPL][&trades=true]
+// Events with seq > N from the ring buffer, individual prints only with
+// trades=true; 410 if N is too old to replay.
+pub async fn get_replay(Query(q): Query<ReplayQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let symbols = parse_symbols(q.symbols);
+    match state.events.replay(q.from_seq, q.trades) {
+        Some(events) => {
+            let events: Vec<&Envelope> = events.iter()
+                .map(|e| e.as_ref())
+                .filter(|e| match (&symbols, e.event.symbol()) {
+                    (Some(list), Some(sym)) => list.iter().any(|s| s == sym),
+                    (Some(_), None) => false,
+                    (None, _) => true,
+                })
+                .collect();
+            (StatusCode::OK, Json(serde_json::json!({
+                "v": PROTOCOL_VERSION,
+                "from_seq": q.from_seq,
+                "last_seq": events.last().map(|e| e.seq).unwrap_or(q.from_seq),
+                "events": events
+            })))
+        }
+        None => (StatusCode::GONE, Json(serde_json::json!({
+            "error": "sequence no longer in history",
+            "v": PROTOCOL_VERSION,
+            "head_seq": state.events.head_seq()
+        }))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(symbol: &str, price: f64) -> StreamEvent {
+        StreamEvent::Price(PriceUpdate { symbol: symbol.into(), price, timestamp: 1, session: Session::Regular, live: true })
+    }
+
+    fn trade(symbol: &str, price: f64) -> StreamEvent {
+        StreamEvent::Trade(TapeTrade { symbol: symbol.into(), price, size: 100, side: 'A', publisher_id: 1, publisher: None, flags: 0, ts_event: 1, ts_recv: 1 })
+    }
+
+    #[test]
+    fn prints_leave_no_symbol_sequence_gaps() {
+        let hub = StreamHub::new();
+        for event in [price("AAPL", 1.0), trade("AAPL", 1.0), trade("AAPL", 1.1), price("MSFT", 2.0), price("AAPL", 1.2), trade("AAPL", 1.2), price("AAPL", 1.3)] {
+            hub.publish(event);
+        }
+        let events = hub.replay(0, false).unwrap();
+        let aapl: Vec<u64> = events.iter().filter(|e| e.event.symbol() == Some("AAPL")).filter_map(|e| e.symbol_seq).collect();
+        assert_eq!(aapl, [1, 2, 3]);
+        assert_eq!(events.iter().map(|e| e.seq).collect::<Vec<_>>(), [1, 4, 5, 7]);
+
+        let all = hub.replay(0, true).unwrap();
+        assert_eq!(all.iter().map(|e| e.seq).collect::<Vec<_>>(), (1..=7).collect::<Vec<_>>());
+        assert!(all.iter().filter(|e| e.event.name() == "trade").all(|e| e.symbol_seq.is_none()));
+
+        let snapshot = hub.snapshot(None, None).unwrap();
+        assert_eq!((snapshot.seq, snapshot.symbols["AAPL"].symbol_seq, snapshot.symbols["AAPL"].price), (7, 3, Some(1.3)));
+    }
+
+    #[test]
+    fn trade_ring_evictions_only_expire_trade_replays() {
+        let hub = StreamHub::new();
+        hub.publish(price("AAPL", 1.0));
+        for _ in 0..=TRADE_HISTORY_LEN {
+            hub.publish(trade("AAPL", 1.0));
+        }
+        assert_eq!(hub.replay(0, false).map(|e| e.len()), Some(1));
+        assert!(hub.replay(0, true).is_none());
+        assert!(hub.replay(2, true).is_some());
+    }
+}
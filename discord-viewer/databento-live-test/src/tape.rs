@@ -0,0 +1,103 @@
+// Time and sales: a bounded ring of recent trades per subscribed symbol.
+
+use std::collections::{HashMap, VecDeque};
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use databento::dbn::TradeMsg;
+
+use crate::{env, norm_symbol, AppState};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TapeTrade {
+    pub symbol: String,
+    pub price: f64,
+    pub size: u32,
+    pub side: char, // 'A' ask (buyer aggressor), 'B' bid (seller aggressor), 'N' none
+    pub publisher_id: u16,
+    #[serde(skip_deserializing)]
+    pub publisher: Option<&'static str>,
+    pub flags: u8,
+    pub ts_event: u64,
+    pub ts_recv: u64,
+}
+
+impl TapeTrade {
+    pub fn from_msg(symbol: &str, trade: &TradeMsg) -> Self {
+        TapeTrade {
+            symbol: symbol.to_string(),
+            price: trade.price as f64 / 1_000_000_000.0,
+            size: trade.size,
+            side: trade.side as u8 as char,
+            publisher_id: trade.hd.publisher_id,
+            publisher: trade.hd.publisher().ok().map(|p| p.as_str()),
+            flags: trade.flags.raw(),
+            ts_event: trade.hd.ts_event,
+            ts_recv: trade.ts_recv,
+        }
+    }
+}
+
+pub struct Tape {
+    capacity: usize,
+    trades: HashMap<String, VecDeque<TapeTrade>>,
+}
+
+impl Tape {
+    // Ring size per symbol from TAPE_LEN (default 5000)
+    pub fn from_env() -> Self {
+        let capacity = env("TAPE_LEN", 5000);
+        Tape { capacity, trades: HashMap::new() }
+    }
+
+    pub fn push(&mut self, trade: TapeTrade) {
+        let ring = self.trades.entry(trade.symbol.clone()).or_default();
+        ring.push_back(trade);
+        while ring.len() > self.capacity {
+            ring.pop_front();
+        }
+    }
+
+    // Most recent `limit` trades after `since` (ts_event ns), oldest first
+    pub fn recent(&self, symbol: &str, since: Option<u64>, limit: usize) -> Vec<TapeTrade> {
+        let Some(ring) = self.trades.get(symbol) else { return Vec::new() };
+        let mut out: Vec<TapeTrade> = ring.iter()
+            .rev()
+            .take_while(|t| since.is_none_or(|s| t.ts_event > s))
+            .take(limit)
+            .cloned()
+            .collect();
+        out.reverse();
+        out
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TradesQuery {
+    symbol: String,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    since: Option<u64>,
+}
+
+// GET /api/live/trades?symbol=AAPL[&limit=100][&since=<ts_event ns>]
+pub async fn get_trades(Query(q): Query<TradesQuery>, State(state): State<AppState>) -> impl IntoResponse {
+    let symbol = norm_symbol(&q.symbol);
+    if symbol.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "symbol is required"})));
+    }
+    let limit = q.limit.unwrap_or(100).min(5000);
+    let trades = state.tape.read().await.recent(&symbol, q.since, limit);
+    (StatusCode::OK, Json(serde_json::json!({
+        "symbol": symbol,
+        "count": trades.len(),
+        "trades": trades
+    })))
+}